@@ -0,0 +1,161 @@
+use axum::extract::FromRequestParts;
+use axum::http::{request::Parts, StatusCode};
+use axum::Json;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::db::{self, Account};
+
+// OWASP's 2023 recommendation for PBKDF2-HMAC-SHA256.
+const PBKDF2_ROUNDS: u32 = 600_000;
+const SESSION_TTL_SECS: i64 = 30 * 24 * 3600;
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Body accepted when registering or signing in.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    pub email: Option<String>,
+}
+
+/// A freshly issued session. The token is only ever shown once.
+pub struct Session {
+    pub token: String,
+    pub expires_at: i64,
+}
+
+fn validate_username(username: &str) -> Result<(), String> {
+    let valid_chars = username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !(3..=32).contains(&username.len()) || !valid_chars {
+        return Err("username must be 3-32 letters, digits, '.', '_' or '-'".to_string());
+    }
+    Ok(())
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// `pbkdf2-sha256$<rounds>$<salt>$<hash>`, hex encoded.
+fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, PBKDF2_ROUNDS, &mut hash);
+    format!("pbkdf2-sha256${}${}${}", PBKDF2_ROUNDS, hex::encode(salt), hex::encode(hash))
+}
+
+fn verify_password(password: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [scheme, rounds, salt, expected] = parts.as_slice() else {
+        return false;
+    };
+    let (Ok(rounds), Ok(salt), Ok(expected)) = (rounds.parse::<u32>(), hex::decode(salt), hex::decode(expected)) else {
+        return false;
+    };
+    if *scheme != "pbkdf2-sha256" {
+        return false;
+    }
+
+    let mut hash = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, rounds, &mut hash);
+    // Compare without short-circuiting so timing doesn't leak a prefix match.
+    hash.iter().zip(&expected).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn new_session(account_id: i64, now: i64) -> Result<Session, sqlx::Error> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let expires_at = now + SESSION_TTL_SECS;
+    db::shared().insert_session(&hash_token(&token), account_id, now, expires_at).await?;
+    Ok(Session { token, expires_at })
+}
+
+/// Creates the account and signs it in.
+pub async fn register(input: Credentials, now: i64) -> Result<(Account, Session), String> {
+    validate_username(&input.username)?;
+    if input.password.len() < MIN_PASSWORD_LEN {
+        return Err(format!("password must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+    let email = input.email.filter(|e| !e.trim().is_empty());
+    if let Some(email) = &email {
+        crate::email::validate_address(email)?;
+    }
+
+    // Hashing is deliberately slow; keep it off the async workers.
+    let password = input.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let id = db::shared().insert_account(&input.username, email.as_deref(), &password_hash, now)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("username is already taken")?;
+    let account = Account { id, username: input.username, email, password_hash, created_at: now };
+    let session = new_session(id, now).await.map_err(|e| e.to_string())?;
+    Ok((account, session))
+}
+
+pub async fn login(input: Credentials, now: i64) -> Result<(Account, Session), String> {
+    const INVALID: &str = "invalid username or password";
+    let account = db::shared().get_account_by_username(&input.username)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or(INVALID)?;
+
+    let (password, stored) = (input.password, account.password_hash.clone());
+    let valid = tokio::task::spawn_blocking(move || verify_password(&password, &stored))
+        .await
+        .map_err(|e| e.to_string())?;
+    if !valid {
+        return Err(INVALID.to_string());
+    }
+
+    let session = new_session(account.id, now).await.map_err(|e| e.to_string())?;
+    Ok((account, session))
+}
+
+/// The signed-in operator, from an `Authorization: Bearer <token>` header.
+pub struct Operator {
+    pub account: Account,
+    pub token_hash: String,
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Operator {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let unauthorized = |msg: &str| (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": msg })));
+
+        let token = parts
+            .headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| unauthorized("sign in required"))?;
+
+        let token_hash = hash_token(token.trim());
+        match db::shared().get_session_account(&token_hash, crate::now_secs()).await {
+            Ok(Some(account)) => Ok(Operator { account, token_hash }),
+            Ok(None) => Err(unauthorized("session expired or invalid")),
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))),
+        }
+    }
+}
+
+/// Pubkeys the operator claimed followed by the rest of their watchlist, each
+/// once, in the order they were added.
+pub async fn my_pubkeys(account_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    let mut pubkeys: Vec<String> = db::shared().get_claimed_pubkeys(account_id).await?.into_iter().map(|c| c.pubkey).collect();
+    for entry in db::shared().get_watchlist(account_id).await? {
+        if !pubkeys.contains(&entry.pubkey) {
+            pubkeys.push(entry.pubkey);
+        }
+    }
+    Ok(pubkeys)
+}
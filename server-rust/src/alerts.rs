@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{self, Alert, AlertRule, NodeRecord};
+use crate::reputation::version_key;
+
+pub const KINDS: [&str; 5] = ["node_offline", "high_latency", "storage_above", "version_below", "network_online_drop"];
+pub const SEVERITIES: [&str; 3] = ["info", "warning", "critical"];
+// `rule` puts every subject of a rule in one notification group, so fifty nodes
+// dropping at once is one incident; `subject` notifies per node.
+pub const GROUP_BY: [&str; 2] = ["rule", "subject"];
+
+pub const NETWORK_SUBJECT: &str = "network";
+
+// A drop in online nodes is measured against the best count in the last hour.
+const NETWORK_PEAK_WINDOW_SECS: i64 = 3600;
+// Nodes absent from gossip count as missing only if gossip last saw them
+// within this window; older ones are taken as retired, not down. A day
+// leaves room for `duration_secs` on node_offline rules.
+const MISSING_WINDOW_SECS: i64 = 24 * 3600;
+
+/// Body accepted when creating or replacing a rule.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleInput {
+    pub name: String,
+    pub kind: String,
+    pub pubkey: Option<String>,
+    pub threshold: Option<f64>,
+    pub min_version: Option<String>,
+    pub duration_secs: Option<i64>,
+    pub severity: Option<String>,
+    pub enabled: Option<bool>,
+    pub group_by: Option<String>,
+}
+
+/// A pending alert started firing, or a firing alert resolved. A firing
+/// `node_offline` alert whose node has been gone from gossip for longer than
+/// the missing window is closed with the state `retired`, which closes its
+/// group but is never announced.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertNotice {
+    pub alert_id: i64,
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub kind: String,
+    pub severity: String,
+    pub subject: String,
+    pub state: String,
+    pub value: Option<f64>,
+    pub message: String,
+    pub at: i64,
+    pub group_key: String,
+    /// Private rules notify only their owner, never shared channels.
+    pub owner_account_id: Option<i64>,
+}
+
+impl AlertNotice {
+    fn new(rule: &AlertRule, alert: &Alert, at: i64) -> Self {
+        let group_key = match rule.group_by.as_str() {
+            "subject" => format!("rule:{}/subject:{}", rule.id, alert.subject),
+            _ => format!("rule:{}", rule.id),
+        };
+        AlertNotice {
+            alert_id: alert.id,
+            rule_id: rule.id,
+            rule_name: rule.name.clone(),
+            kind: rule.kind.clone(),
+            severity: rule.severity.clone(),
+            subject: alert.subject.clone(),
+            state: alert.state.clone(),
+            value: alert.value,
+            message: alert.message.clone(),
+            at,
+            group_key,
+            owner_account_id: rule.owner_account_id,
+        }
+    }
+}
+
+/// Checks a rule body and turns it into a rule ready to store.
+pub fn validate(input: RuleInput, id: i64, created_at: i64) -> Result<AlertRule, String> {
+    if input.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if !KINDS.contains(&input.kind.as_str()) {
+        return Err(format!("unknown kind '{}', expected one of {}", input.kind, KINDS.join(", ")));
+    }
+    let severity = input.severity.unwrap_or_else(|| "warning".to_string());
+    if !SEVERITIES.contains(&severity.as_str()) {
+        return Err(format!("unknown severity '{}', expected one of {}", severity, SEVERITIES.join(", ")));
+    }
+    let group_by = input.group_by.unwrap_or_else(|| "rule".to_string());
+    if !GROUP_BY.contains(&group_by.as_str()) {
+        return Err(format!("unknown group_by '{}', expected one of {}", group_by, GROUP_BY.join(", ")));
+    }
+    let duration_secs = input.duration_secs.unwrap_or(0);
+    if duration_secs < 0 {
+        return Err("duration_secs must not be negative".to_string());
+    }
+    // A missing node stops breaching once it leaves the missing window, so a
+    // longer wait could never fire.
+    if input.kind == "node_offline" && duration_secs >= MISSING_WINDOW_SECS {
+        return Err(format!("node_offline rules need a duration_secs below {}", MISSING_WINDOW_SECS));
+    }
+
+    match input.kind.as_str() {
+        "high_latency" | "storage_above" | "network_online_drop" if input.threshold.is_none() => {
+            return Err(format!("{} rules need a threshold", input.kind));
+        }
+        "version_below" if input.min_version.as_deref().map(version_key).unwrap_or_default().is_empty() => {
+            return Err("version_below rules need a min_version like 0.8.0".to_string());
+        }
+        "network_online_drop" if input.pubkey.is_some() => {
+            return Err("network_online_drop rules apply to the whole network, not a pubkey".to_string());
+        }
+        _ => {}
+    }
+
+    Ok(AlertRule {
+        id,
+        name: input.name,
+        kind: input.kind,
+        pubkey: input.pubkey,
+        threshold: input.threshold,
+        min_version: input.min_version,
+        duration_secs,
+        severity,
+        enabled: input.enabled.unwrap_or(true),
+        created_at,
+        group_by,
+        owner_account_id: None,
+    })
+}
+
+/// What the network looked like in the cycle being evaluated.
+struct CycleView<'a> {
+    nodes: Vec<&'a NodeRecord>,
+    /// Stored nodes absent from this cycle that gossip saw recently.
+    missing: Vec<&'a NodeRecord>,
+    online: i64,
+    peak_online: Option<i64>,
+}
+
+/// Subjects currently breaching the rule, with the offending value and a
+/// human readable message.
+fn breaches(rule: &AlertRule, view: &CycleView) -> HashMap<String, (Option<f64>, String)> {
+    let mut out = HashMap::new();
+    let in_scope = |n: &&&NodeRecord| rule.pubkey.as_deref().is_none_or(|p| p == n.pubkey);
+    let threshold = rule.threshold.unwrap_or_default();
+
+    match rule.kind.as_str() {
+        "node_offline" => {
+            for n in view.nodes.iter().filter(in_scope) {
+                if n.status.as_deref() != Some("online") {
+                    out.insert(n.pubkey.clone(), (None, format!("Node {} reports offline", n.pubkey)));
+                }
+            }
+            for n in view.missing.iter().filter(in_scope) {
+                out.insert(n.pubkey.clone(), (None, format!("Node {} is missing from gossip", n.pubkey)));
+            }
+        }
+        "high_latency" => {
+            for n in view.nodes.iter().filter(in_scope) {
+                if let Some(latency) = n.latency_ms.filter(|l| *l as f64 > threshold) {
+                    out.insert(
+                        n.pubkey.clone(),
+                        (Some(latency as f64), format!("Node {} latency {}ms above {}ms", n.pubkey, latency, threshold)),
+                    );
+                }
+            }
+        }
+        "storage_above" => {
+            for n in view.nodes.iter().filter(in_scope) {
+                let usage = match (n.storage_used, n.storage_committed) {
+                    (Some(used), Some(committed)) if committed > 0 => used as f64 / committed as f64 * 100.0,
+                    _ => continue,
+                };
+                if usage > threshold {
+                    out.insert(
+                        n.pubkey.clone(),
+                        (Some(usage), format!("Node {} storage at {:.1}% of committed, above {}%", n.pubkey, usage, threshold)),
+                    );
+                }
+            }
+        }
+        "version_below" => {
+            let min = rule.min_version.as_deref().map(version_key).unwrap_or_default();
+            for n in view.nodes.iter().filter(in_scope) {
+                if let Some(version) = n.version.as_deref().filter(|v| version_key(v) < min) {
+                    out.insert(
+                        n.pubkey.clone(),
+                        (None, format!("Node {} runs {} below minimum {}", n.pubkey, version, rule.min_version.as_deref().unwrap_or_default())),
+                    );
+                }
+            }
+        }
+        "network_online_drop" => {
+            if let Some(peak) = view.peak_online.filter(|p| *p > 0) {
+                let drop = (peak - view.online) as f64 / peak as f64 * 100.0;
+                if drop >= threshold {
+                    out.insert(
+                        NETWORK_SUBJECT.to_string(),
+                        (Some(drop), format!("Online nodes dropped {:.1}% to {} from {} in the last hour", drop, view.online, peak)),
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Advances every enabled rule's alerts by one refresh cycle and returns the
+/// alerts that started firing or resolved. Only called for cycles in which the
+/// observer saw the network, so a blind observer never resolves or fires alerts.
+pub async fn evaluate(cycle_at: i64, seen: &[NodeRecord]) -> Result<Vec<AlertNotice>, sqlx::Error> {
+    let rules: Vec<AlertRule> = db::store().get_alert_rules().await?.into_iter().filter(|r| r.enabled).collect();
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let all_nodes = db::store().get_all_nodes().await?;
+    let seen_keys: HashSet<&str> = seen.iter().map(|n| n.pubkey.as_str()).collect();
+    let view = CycleView {
+        nodes: seen.iter().collect(),
+        missing: all_nodes
+            .iter()
+            .filter(|n| !seen_keys.contains(n.pubkey.as_str()))
+            .filter(|n| n.last_seen.is_some_and(|at| at >= cycle_at - MISSING_WINDOW_SECS))
+            .collect(),
+        online: seen.iter().filter(|n| n.status.as_deref() == Some("online")).count() as i64,
+        peak_online: db::store().get_peak_online_since(cycle_at - NETWORK_PEAK_WINDOW_SECS).await?,
+    };
+
+    let mut notices = Vec::new();
+    for rule in &rules {
+        let breaching = breaches(rule, &view);
+        let active: HashMap<String, Alert> = db::store().get_active_alerts_for_rule(rule.id)
+            .await?
+            .into_iter()
+            .map(|a| (a.subject.clone(), a))
+            .collect();
+
+        for (subject, (value, message)) in &breaching {
+            match active.get(subject) {
+                None => {
+                    let firing = rule.duration_secs == 0;
+                    let mut alert = Alert {
+                        id: 0,
+                        rule_id: rule.id,
+                        subject: subject.clone(),
+                        state: if firing { "firing" } else { "pending" }.to_string(),
+                        value: *value,
+                        message: message.clone(),
+                        started_at: cycle_at,
+                        fired_at: firing.then_some(cycle_at),
+                        resolved_at: None,
+                        updated_at: cycle_at,
+                        group_key: None,
+                        suppressed_by: None,
+                    };
+                    alert.id = db::store().insert_alert(&alert).await?;
+                    if firing {
+                        notices.push(AlertNotice::new(rule, &alert, cycle_at));
+                    }
+                }
+                Some(existing) => {
+                    let mut alert = existing.clone();
+                    alert.value = *value;
+                    alert.message = message.clone();
+                    alert.updated_at = cycle_at;
+                    if alert.state == "pending" && cycle_at - alert.started_at >= rule.duration_secs {
+                        alert.state = "firing".to_string();
+                        alert.fired_at = Some(cycle_at);
+                        notices.push(AlertNotice::new(rule, &alert, cycle_at));
+                    }
+                    db::store().update_alert(&alert).await?;
+                }
+            }
+        }
+
+        for (subject, existing) in active {
+            if breaching.contains_key(&subject) {
+                continue;
+            }
+            // A pending alert that clears never happened as far as anyone is told.
+            if existing.state == "pending" {
+                db::store().delete_alert(existing.id).await?;
+                continue;
+            }
+            let mut alert = existing;
+            alert.state = "resolved".to_string();
+            alert.resolved_at = Some(cycle_at);
+            alert.updated_at = cycle_at;
+            db::store().update_alert(&alert).await?;
+            // A node that stopped breaching without being seen didn't come
+            // back; it aged out of the missing window and is taken as retired.
+            let retired = rule.kind == "node_offline" && !seen_keys.contains(subject.as_str());
+            let notice = AlertNotice::new(rule, &alert, cycle_at);
+            notices.push(if retired { AlertNotice { state: "retired".to_string(), ..notice } } else { notice });
+        }
+    }
+
+    Ok(notices)
+}
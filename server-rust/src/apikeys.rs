@@ -0,0 +1,215 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequestParts, Request};
+use axum::http::{request::Parts, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::db::{self, ApiKey};
+use crate::ratelimit;
+
+/// Scopes a key can carry. Read-only routes are open to everyone, so a key
+/// without scopes only buys its own rate limit; `admin` unlocks the admin
+/// routes.
+pub const SCOPES: [&str; 1] = ["admin"];
+
+const KEY_PREFIX: &str = "xo_";
+// `last_used_at` is a hint for spotting stale keys, not an audit log, so it is
+// written at most this often per key.
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Body accepted when creating an API key.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyInput {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+    pub rate_limit_per_min: Option<u32>,
+}
+
+/// Who made the request, attached to every request by [`authenticate`].
+#[derive(Debug, Clone)]
+pub enum Caller {
+    Anonymous(String),
+    Key(ApiKey),
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", KEY_PREFIX, hex::encode(bytes))
+}
+
+/// Checks a key body. `scopes` defaults to none.
+pub fn validate(input: ApiKeyInput) -> Result<(String, Vec<String>, Option<u32>), String> {
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    let mut scopes = input.scopes.unwrap_or_default();
+    if let Some(bad) = scopes.iter().find(|s| !SCOPES.contains(&s.as_str())) {
+        return Err(format!("unknown scope '{}', expected one of {}", bad, SCOPES.join(", ")));
+    }
+    scopes.sort_by_key(|s| SCOPES.iter().position(|k| k == s));
+    scopes.dedup();
+    if input.rate_limit_per_min == Some(0) {
+        return Err("rate_limit_per_min must be at least 1".to_string());
+    }
+    Ok((name, scopes, input.rate_limit_per_min))
+}
+
+/// Stores a new key and returns it with the plaintext key, which is only ever
+/// shown this once.
+pub async fn create(input: ApiKeyInput, now: i64) -> Result<(ApiKey, String), String> {
+    let (name, scopes, rate_limit_per_min) = validate(input)?;
+    let key = generate_key();
+    let mut api_key = ApiKey {
+        id: 0,
+        name,
+        prefix: key[..KEY_PREFIX.len() + 6].to_string(),
+        scopes: scopes.join(","),
+        rate_limit_per_min: rate_limit_per_min.map(i64::from),
+        created_at: now,
+        last_used_at: None,
+        revoked_at: None,
+    };
+    api_key.id = db::shared().insert_api_key(&api_key, &hash_key(&key)).await.map_err(|e| e.to_string())?;
+    Ok((api_key, key))
+}
+
+/// Makes sure an admin key exists. `ADMIN_API_KEY` is registered as one if
+/// set; otherwise, when no admin key is left, a new one is generated and
+/// printed once so the server can't end up with no way to administer it.
+/// A revoked `ADMIN_API_KEY` stays revoked and is treated as unset.
+pub async fn bootstrap() -> Result<(), sqlx::Error> {
+    let now = crate::now_secs();
+    if let Ok(key) = std::env::var("ADMIN_API_KEY") {
+        if !key.is_empty() {
+            let hash = hash_key(&key);
+            match db::shared().get_api_key_by_hash(&hash).await? {
+                Some(existing) if existing.revoked_at.is_none() => return Ok(()),
+                Some(_) => eprintln!("ADMIN_API_KEY was revoked and will be rejected; set a new one or use an existing admin key"),
+                None => {
+                    let api_key = ApiKey {
+                        id: 0,
+                        name: "ADMIN_API_KEY".to_string(),
+                        prefix: key.chars().take(KEY_PREFIX.len() + 6).collect(),
+                        scopes: "admin".to_string(),
+                        rate_limit_per_min: None,
+                        created_at: now,
+                        last_used_at: None,
+                        revoked_at: None,
+                    };
+                    db::shared().insert_api_key(&api_key, &hash).await?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let has_admin = db::shared().get_api_keys().await?.iter().any(|k| k.revoked_at.is_none() && k.has_scope("admin"));
+    if !has_admin {
+        let input = ApiKeyInput { name: "bootstrap".to_string(), scopes: Some(vec!["admin".to_string()]), rate_limit_per_min: None };
+        let (_, key) = create(input, now).await.map_err(sqlx::Error::Protocol)?;
+        println!("No admin API key configured; created one. Store it now, it will not be shown again: {}", key);
+    }
+    Ok(())
+}
+
+/// Client address for anonymous rate limiting. Behind a reverse proxy set
+/// `rate_limit.trust_proxy` so the first `X-Forwarded-For` hop is used
+/// instead of the proxy's own address.
+fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> String {
+    let trust_proxy = crate::config::get().rate_limit.trust_proxy;
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    match forwarded {
+        Some(ip) if trust_proxy => ip,
+        _ => peer.ip().to_string(),
+    }
+}
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": msg }))).into_response()
+}
+
+fn set_rate_headers(headers: &mut HeaderMap, decision: &ratelimit::Decision) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(decision.reset_secs));
+}
+
+/// Middleware identifying the caller from the `X-API-Key` header and applying
+/// its rate limit. Unknown or revoked keys are rejected rather than treated as
+/// anonymous, so a typo doesn't silently fall back to the lower limit.
+pub async fn authenticate(ConnectInfo(peer): ConnectInfo<SocketAddr>, mut request: Request, next: Next) -> Response {
+    let presented = request.headers().get("x-api-key").map(|v| v.to_str().unwrap_or_default().trim().to_string());
+
+    let caller = match presented {
+        None => Caller::Anonymous(client_ip(request.headers(), peer)),
+        Some(key) => match db::shared().get_api_key_by_hash(&hash_key(&key)).await {
+            Ok(Some(api_key)) if api_key.revoked_at.is_none() => Caller::Key(api_key),
+            Ok(_) => return error(StatusCode::UNAUTHORIZED, "invalid or revoked API key"),
+            Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        },
+    };
+
+    let limits = &crate::config::get().rate_limit;
+    let decision = match &caller {
+        Caller::Anonymous(ip) => ratelimit::check(&format!("ip:{}", ip), limits.anonymous_per_min),
+        Caller::Key(key) => {
+            let limit = key.rate_limit_per_min.map(|l| l as u32).unwrap_or(limits.keyed_per_min);
+            ratelimit::check(&format!("key:{}", key.id), limit)
+        }
+    };
+    if !decision.allowed {
+        let mut response = error(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+        set_rate_headers(response.headers_mut(), &decision);
+        response.headers_mut().insert("retry-after", HeaderValue::from(decision.retry_after_secs));
+        return response;
+    }
+
+    if let Caller::Key(key) = &caller {
+        let now = crate::now_secs();
+        if key.last_used_at.is_none_or(|at| now - at >= LAST_USED_RESOLUTION_SECS) {
+            let id = key.id;
+            tokio::spawn(async move {
+                if let Err(e) = db::shared().touch_api_key(id, now).await {
+                    eprintln!("Failed to update API key usage: {}", e);
+                }
+            });
+        }
+    }
+
+    request.extensions_mut().insert(caller);
+    let mut response = next.run(request).await;
+    set_rate_headers(response.headers_mut(), &decision);
+    response
+}
+
+/// A caller holding an API key with the admin scope.
+pub struct Admin(pub ApiKey);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Admin {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Caller>() {
+            Some(Caller::Key(key)) if key.has_scope("admin") => Ok(Admin(key.clone())),
+            Some(Caller::Key(_)) => Err((StatusCode::FORBIDDEN, Json(serde_json::json!({ "error": "API key lacks the admin scope" })))),
+            _ => Err((StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "admin API key required" })))),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::db;
+
+const STAMP: &str = "%Y%m%dT%H%M%SZ";
+
+// One backup at a time, so a scheduled and an on-demand one can't race for
+// the same file name.
+static RUNNING: Mutex<()> = Mutex::const_new(());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    pub network: String,
+    pub path: String,
+    pub created_at: i64,
+    pub size_bytes: u64,
+}
+
+fn dir() -> PathBuf {
+    PathBuf::from(&crate::config::get().backup.dir)
+}
+
+/// When the backup in `file_name` was taken, if it is one of `network`'s.
+fn taken_at(network: &str, file_name: &str) -> Option<i64> {
+    let stamp = file_name.strip_prefix(network)?.strip_prefix('-')?.strip_suffix(".db")?;
+    let at = NaiveDateTime::parse_from_str(stamp, STAMP).ok()?;
+    Some(Utc.from_utc_datetime(&at).timestamp())
+}
+
+fn describe(network: &str, path: &Path) -> Option<Backup> {
+    let created_at = taken_at(network, path.file_name()?.to_str()?)?;
+    Some(Backup {
+        network: network.to_string(),
+        path: path.display().to_string(),
+        created_at,
+        size_bytes: std::fs::metadata(path).ok()?.len(),
+    })
+}
+
+/// The current network's backups, newest first.
+pub fn list() -> Vec<Backup> {
+    let network = &crate::networks::current().name;
+    let Ok(entries) = std::fs::read_dir(dir()) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<Backup> = entries.filter_map(|e| describe(network, &e.ok()?.path())).collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    backups
+}
+
+/// Backs up the current network's database and drops the backups beyond
+/// `backup.keep`.
+pub async fn snapshot() -> Result<Backup, String> {
+    let _running = RUNNING.lock().await;
+    let network = &crate::networks::current().name;
+    let dir = dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let path = dir.join(format!("{}-{}.db", network, Utc::now().format(STAMP)));
+    if path.exists() {
+        return Err(format!("{} already exists; backups are at most one a second", path.display()));
+    }
+    db::store().backup(&path).await.map_err(|e| e.to_string())?;
+    let backup = describe(network, &path).ok_or_else(|| format!("{} vanished after the backup", path.display()))?;
+
+    for old in list().into_iter().skip(crate::config::get().backup.keep) {
+        if let Err(e) = std::fs::remove_file(&old.path) {
+            eprintln!("Failed to delete old backup {}: {}", old.path, e);
+        }
+    }
+    Ok(backup)
+}
+
+/// Takes a backup every `backup.interval_secs`, skipping PostgreSQL
+/// networks, which are backed up with pg_dump.
+pub async fn run_schedule() {
+    let interval_secs = crate::config::get().backup.interval_secs;
+    if interval_secs == 0 || db::is_postgres(&crate::networks::current().database_url) {
+        return;
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match snapshot().await {
+            Ok(backup) => println!("Backed up {} to {} ({} bytes)", backup.network, backup.path, backup.size_bytes),
+            Err(e) => eprintln!("Backup of {} failed: {}", crate::networks::current().name, e),
+        }
+    }
+}
+
+/// `server-rust restore`: swaps `network`'s database for the backup at `path`.
+pub async fn restore(path: &str, network: &'static crate::config::Network) -> Result<(), String> {
+    let kept = db::restore(&network.database_url, Path::new(path))
+        .await
+        .map_err(|e| format!("Failed to restore {} from {}: {}", network.name, path, e))?;
+    match kept {
+        Some(kept) => println!("Restored {} from {}; the previous database is kept as {}", network.name, path, kept.display()),
+        None => println!("Restored {} from {}", network.name, path),
+    }
+    Ok(())
+}
@@ -0,0 +1,355 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::config::{Command, ExportTable, Network};
+use crate::{db, export, networks};
+
+/// Runs a subcommand instead of the server. Output is a table, or JSON with
+/// `--json`.
+pub async fn run(command: Command, json: bool) -> Result<(), String> {
+    match command {
+        Command::Serve => unreachable!("main serves without a subcommand"),
+        // Restoring swaps the database file, so it must not be opened first.
+        Command::Restore { path, network } => crate::backup::restore(&path, one(network)?).await,
+        Command::Probe { address, network } => networks::scope(one(network)?, probe(address, json)).await,
+        Command::Migrate => {
+            open().await?;
+            let rows: Vec<Vec<String>> = networks::all()
+                .iter()
+                .map(|n| vec![n.name.clone(), db::display_url(&n.database_url)])
+                .collect();
+            let value: Vec<_> = rows.iter().map(|r| serde_json::json!({ "network": r[0], "database": r[1] })).collect();
+            print(json, &value, &["NETWORK", "DATABASE"], rows);
+            Ok(())
+        }
+        Command::RefreshOnce { network } => {
+            open().await?;
+            crate::email::init();
+            let mut cycles = Vec::new();
+            for network in some_or_all(network)? {
+                crate::load_network_state(network).await;
+                let (deliveries, cycle) = networks::scope(network, async {
+                    let deliveries = crate::refresh_data().await;
+                    (deliveries, db::store().get_observer_cycles(1).await)
+                })
+                .await;
+                // Alerts the cycle fired would be lost if the process exited
+                // with their deliveries still running.
+                for delivery in deliveries {
+                    let _ = delivery.await;
+                }
+                let cycle = cycle.map_err(|e| e.to_string())?;
+                cycles.extend(cycle.into_iter().map(|c| (network.name.clone(), c)));
+            }
+            let rows = cycles
+                .iter()
+                .map(|(network, c)| {
+                    vec![
+                        network.clone(),
+                        c.outcome.clone(),
+                        cell(c.seed.as_ref()),
+                        c.pods_received.to_string(),
+                        format!("{}ms", c.duration_ms),
+                        cell(c.error.as_ref()),
+                    ]
+                })
+                .collect();
+            let value: Vec<_> = cycles.iter().map(|(network, c)| serde_json::json!({ "network": network, "cycle": c })).collect();
+            print(json, &value, &["NETWORK", "OUTCOME", "SEED", "PODS", "DURATION", "ERROR"], rows);
+            Ok(())
+        }
+        Command::Pods { network } => {
+            open().await?;
+            networks::scope(one(network)?, pods(json)).await
+        }
+        Command::Node { pubkey, network } => {
+            open().await?;
+            networks::scope(one(network)?, node(pubkey, json)).await
+        }
+        Command::Prune { older_than_days, network } => {
+            open().await?;
+            let before = crate::now_secs() - older_than_days as i64 * 24 * 3600;
+            let mut results = Vec::new();
+            for network in some_or_all(network)? {
+                let pruned = networks::scope(network, async { db::store().prune(before).await })
+                    .await
+                    .map_err(|e| format!("Failed to prune {}: {}", network.name, e))?;
+                results.push((network.name.clone(), pruned));
+            }
+            let rows = results
+                .iter()
+                .map(|(network, p)| {
+                    vec![
+                        network.clone(),
+                        p.node_history.to_string(),
+                        p.metrics.to_string(),
+                        p.observer_cycles.to_string(),
+                        p.credits_history.to_string(),
+                    ]
+                })
+                .collect();
+            let value: Vec<_> = results.iter().map(|(network, p)| serde_json::json!({ "network": network, "deleted": p })).collect();
+            print(json, &value, &["NETWORK", "NODE_HISTORY", "METRICS", "OBSERVER_CYCLES", "CREDITS_HISTORY"], rows);
+            Ok(())
+        }
+        Command::Export { table, node, format, from, to, output, network } => {
+            open().await?;
+            let query = export::ExportQuery { format: Some(format), from, to };
+            networks::scope(one(network)?, write_export(table, node, query, output)).await
+        }
+        Command::Backup { network } => {
+            open().await?;
+            let mut failed = false;
+            for network in some_or_all(network)? {
+                match networks::scope(network, crate::backup::snapshot()).await {
+                    Ok(b) => println!("Backed up {} to {} ({} bytes)", b.network, b.path, b.size_bytes),
+                    Err(e) => {
+                        eprintln!("Backup of {} failed: {}", network.name, e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                return Err("Some backups failed".to_string());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Opens, and migrates, every network's database.
+async fn open() -> Result<(), String> {
+    db::init_db().await.map_err(|e| format!("Failed to open the database: {}", e))
+}
+
+/// The named network, or the default one.
+fn one(name: Option<String>) -> Result<&'static Network, String> {
+    match name {
+        Some(name) => networks::find(&name).ok_or_else(|| format!("Unknown network '{}'", name)),
+        None => Ok(networks::default()),
+    }
+}
+
+/// The named network, or every network.
+fn some_or_all(name: Option<String>) -> Result<Vec<&'static Network>, String> {
+    match name {
+        Some(_) => Ok(vec![one(name)?]),
+        None => Ok(networks::all().iter().collect()),
+    }
+}
+
+async fn pods(json: bool) -> Result<(), String> {
+    let nodes = db::store().get_all_nodes().await.map_err(|e| e.to_string())?;
+    let rows = nodes
+        .iter()
+        .map(|n| {
+            vec![
+                n.pubkey.clone(),
+                n.ip.clone(),
+                cell(n.version.as_ref()),
+                cell(n.status.as_ref()),
+                bytes(n.storage_used),
+                cell(n.storage_usage_percent.map(|p| format!("{:.2}%", p))),
+                cell(n.latency_ms.map(|l| format!("{}ms", l))),
+                cell(n.credits),
+                cell(n.country.as_ref()),
+            ]
+        })
+        .collect();
+    let value = crate::PodsResponseDto {
+        total_count: nodes.len(),
+        pods: nodes.iter().cloned().map(crate::pod_dto).collect(),
+    };
+    print(json, &value, &["PUBKEY", "ADDRESS", "VERSION", "STATUS", "STORAGE", "USAGE", "LATENCY", "CREDITS", "COUNTRY"], rows);
+    Ok(())
+}
+
+async fn node(pubkey: String, json: bool) -> Result<(), String> {
+    let n = db::store()
+        .get_node_by_id(&pubkey)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Node {} not found", pubkey))?;
+    let location = match (&n.city, &n.country) {
+        (Some(city), Some(country)) if !city.is_empty() => Some(format!("{}, {}", city, country)),
+        (_, country) => country.clone(),
+    };
+    let rows = [
+        ("pubkey", n.pubkey.clone()),
+        ("address", n.ip.clone()),
+        ("version", cell(n.version.as_ref())),
+        ("status", cell(n.status.as_ref())),
+        ("last seen", cell(n.last_seen.map(time))),
+        ("uptime", cell(n.uptime.map(|u| format!("{}s", u)))),
+        ("storage used", bytes(n.storage_used)),
+        ("storage committed", bytes(n.storage_committed)),
+        ("storage usage", cell(n.storage_usage_percent.map(|p| format!("{:.2}%", p)))),
+        ("credits", cell(n.credits)),
+        ("latency", cell(n.latency_ms.map(|l| format!("{}ms", l)))),
+        ("location", cell(location)),
+    ]
+    .into_iter()
+    .map(|(k, v)| vec![k.to_string(), v])
+    .collect();
+    print(json, &crate::pod_dto(n), &["FIELD", "VALUE"], rows);
+    Ok(())
+}
+
+async fn probe(address: String, json: bool) -> Result<(), String> {
+    let network = networks::current();
+    let target = if address.contains(':') { address } else { format!("{}:{}", address, network.rpc_port) };
+
+    let connect_ms = crate::latency::measure_latency(&target).await;
+    let started = std::time::Instant::now();
+    let result = crate::fetch_pods_from(&target).await;
+    let rpc_ms = started.elapsed().as_millis() as i64;
+
+    let (pods, error) = match result {
+        Ok(pods) => (pods, None),
+        Err(e) => (Vec::new(), Some(e.message)),
+    };
+    if json {
+        let value = serde_json::json!({
+            "address": target,
+            "rpc_url": network.rpc_url(&target),
+            "connect_ms": connect_ms,
+            "rpc_ms": rpc_ms,
+            "error": error,
+            "pods": pods,
+        });
+        print_json(&value);
+    } else {
+        let summary = vec![
+            vec!["rpc url".to_string(), network.rpc_url(&target)],
+            vec!["tcp connect".to_string(), cell(connect_ms.map(|ms| format!("{}ms", ms)))],
+            vec!["get-pods-with-stats".to_string(), format!("{}ms", rpc_ms)],
+            vec!["pods".to_string(), pods.len().to_string()],
+            vec!["error".to_string(), cell(error.as_ref())],
+        ];
+        print_table(&["FIELD", "VALUE"], &summary);
+        if !pods.is_empty() {
+            emit("");
+            let rows: Vec<Vec<String>> = pods
+                .iter()
+                .map(|p| {
+                    vec![
+                        cell(p.pubkey.as_ref()),
+                        cell(p.address.as_ref()),
+                        cell(p.version.as_ref()),
+                        cell(p.uptime.map(|u| format!("{}s", u))),
+                        bytes(p.storage_used),
+                        cell(p.last_seen_timestamp.map(time)),
+                    ]
+                })
+                .collect();
+            print_table(&["PUBKEY", "ADDRESS", "VERSION", "UPTIME", "STORAGE", "LAST SEEN"], &rows);
+        }
+    }
+    if error.is_some() {
+        return Err(format!("Probe of {} failed", target));
+    }
+    Ok(())
+}
+
+async fn write_export(table: ExportTable, node: Option<String>, query: export::ExportQuery, output: Option<String>) -> Result<(), String> {
+    let (format, from, to) = query.parse()?;
+    let mut chunks = match (table, node) {
+        (ExportTable::Pods, None) => export::encode(db::store().stream_nodes(from, to), format),
+        (ExportTable::Pods, Some(_)) => return Err("--node only applies to history exports".to_string()),
+        (ExportTable::History, None) => export::encode(db::store().stream_metrics(from, to), format),
+        (ExportTable::History, Some(pubkey)) => {
+            if db::store().get_node_by_id(&pubkey).await.map_err(|e| e.to_string())?.is_none() {
+                return Err(format!("Node {} not found", pubkey));
+            }
+            export::encode(db::store().stream_node_history(&pubkey, from, to), format)
+        }
+    };
+
+    let out: Box<dyn Write + Send> = match &output {
+        Some(path) => Box::new(std::fs::File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut out = std::io::BufWriter::new(out);
+    let written = async {
+        while let Some(chunk) = chunks.recv().await {
+            out.write_all(&chunk?)?;
+        }
+        out.flush()
+    };
+    match written.await {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|e| format!("Export failed: {}", e)),
+    }
+}
+
+/// Prints `value` as JSON, or `rows` as a table under `headers`.
+fn print<T: Serialize>(json: bool, value: &T, headers: &[&str], rows: Vec<Vec<String>>) {
+    if json {
+        print_json(value);
+    } else {
+        print_table(headers, &rows);
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    emit(&serde_json::to_string_pretty(value).expect("output always serialises"));
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let padded: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        emit(padded.join("  ").trim_end());
+    }
+}
+
+/// Writes a line to stdout, exiting quietly once the reader has gone away,
+/// as when piped into `head`.
+fn emit(line: &str) {
+    match writeln!(std::io::stdout(), "{}", line) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(e) => {
+            eprintln!("Failed to write output: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+/// Decimal units, as pNodes report storage.
+fn bytes(value: Option<i64>) -> String {
+    let Some(value) = value else {
+        return "-".to_string();
+    };
+    let mut size = value as f64;
+    let mut unit = "B";
+    for next in ["KB", "MB", "GB", "TB"] {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = next;
+    }
+    if unit == "B" {
+        format!("{} B", value)
+    } else {
+        format!("{:.1} {}", size, unit)
+    }
+}
+
+fn time(at: i64) -> String {
+    chrono::DateTime::from_timestamp(at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| at.to_string())
+}
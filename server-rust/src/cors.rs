@@ -0,0 +1,389 @@
+use std::sync::{Arc, RwLock};
+
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+static POLICY: Lazy<RwLock<Arc<CorsPolicy>>> = Lazy::new(|| RwLock::new(Arc::new(CorsPolicy::default())));
+
+/// Which browser origins may call the API, as read from the file named by
+/// `server.cors_config` (`cors.toml` by default).
+///
+/// Origins are exact (`https://observer.example.com`), a wildcard subdomain
+/// (`https://*.example.com`, which does not match `https://example.com`
+/// itself) or `*` for any origin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsPolicy {
+    // The frontend's own dev and docker-compose origin, as the old Node server
+    // allowed.
+    fn default() -> Self {
+        CorsPolicy {
+            allowed_origins: vec!["http://localhost:8080".to_string()],
+            allowed_methods: ["GET", "POST", "PUT", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: ["content-type", "authorization", "x-api-key"].map(String::from).to_vec(),
+            expose_headers: ["x-ratelimit-limit", "x-ratelimit-remaining", "x-ratelimit-reset", "retry-after"]
+                .map(String::from)
+                .to_vec(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// Normalises the policy and rejects combinations browsers would refuse.
+    pub fn validate(mut self) -> Result<Self, String> {
+        for origin in &mut self.allowed_origins {
+            *origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        }
+        for method in &mut self.allowed_methods {
+            *method = method.to_ascii_uppercase();
+        }
+        for name in self.allowed_headers.iter_mut().chain(&mut self.expose_headers) {
+            *name = name.to_ascii_lowercase();
+        }
+
+        for origin in &self.allowed_origins {
+            if origin == "*" {
+                continue;
+            }
+            let Some((scheme, host)) = origin.split_once("://") else {
+                return Err(format!("origin '{}' must include a scheme, e.g. https://{}", origin, origin));
+            };
+            if !matches!(scheme, "http" | "https") || host.is_empty() || host.contains('/') {
+                return Err(format!("origin '{}' must look like https://host[:port] without a path", origin));
+            }
+            let rest = host.strip_prefix("*.").unwrap_or(host);
+            if rest.is_empty() || rest.contains('*') {
+                return Err(format!("origin '{}': a wildcard may only stand for the leading subdomain", origin));
+            }
+        }
+        if self.allow_credentials && self.allowed_origins.iter().any(|o| o == "*") {
+            return Err("allow_credentials cannot be combined with the '*' origin".to_string());
+        }
+        for method in &self.allowed_methods {
+            Method::from_bytes(method.as_bytes()).map_err(|_| format!("invalid method '{}'", method))?;
+        }
+        for name in self.allowed_headers.iter().chain(&self.expose_headers) {
+            header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name '{}'", name))?;
+        }
+        Ok(self)
+    }
+
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.allowed_origins.iter().any(|allowed| {
+            if allowed == "*" {
+                return true;
+            }
+            match allowed.split_once("://*.") {
+                // `https://*.example.com` needs a non-empty subdomain label in
+                // front of `.example.com`, under the same scheme.
+                Some((scheme, suffix)) => origin
+                    .strip_prefix(scheme)
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .and_then(|host| host.strip_suffix(suffix))
+                    .and_then(|sub| sub.strip_suffix('.'))
+                    .is_some_and(|sub| !sub.is_empty() && !sub.contains(['/', ':'])),
+                None => *allowed == origin,
+            }
+        })
+    }
+
+    fn allow_origin_value(&self, origin: &HeaderValue) -> HeaderValue {
+        // With credentials the exact origin must be echoed; `*` is refused.
+        if self.allowed_origins.iter().any(|o| o == "*") && !self.allow_credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        }
+    }
+
+    /// Headers answering a preflight, or `None` when the origin, method or any
+    /// requested header isn't allowed.
+    pub fn preflight(&self, origin: &HeaderValue, method: &str, request_headers: Option<&str>) -> Option<HeaderMap> {
+        if !self.allows_origin(origin.to_str().ok()?) {
+            return None;
+        }
+        let method = method.trim().to_ascii_uppercase();
+        // GET, HEAD and POST are always allowed by browsers without listing.
+        if !matches!(method.as_str(), "GET" | "HEAD" | "POST") && !self.allowed_methods.contains(&method) {
+            return None;
+        }
+        let requested: Vec<String> = request_headers
+            .unwrap_or_default()
+            .split(',')
+            .map(|h| h.trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        if requested.iter().any(|h| !self.allowed_headers.contains(h)) {
+            return None;
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin));
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_str(&self.allowed_methods.join(", ")).ok()?);
+        if !self.allowed_headers.is_empty() {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_str(&self.allowed_headers.join(", ")).ok()?);
+        }
+        if self.allow_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age_secs));
+        Some(headers)
+    }
+
+    /// Headers added to an actual (non-preflight) response.
+    pub fn response_headers(&self, origin: &HeaderValue) -> Option<HeaderMap> {
+        if !self.allows_origin(origin.to_str().ok()?) {
+            return None;
+        }
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin));
+        if self.allow_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if !self.expose_headers.is_empty() {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_str(&self.expose_headers.join(", ")).ok()?);
+        }
+        Some(headers)
+    }
+}
+
+/// Reads the policy from the config file, or the defaults when there is none.
+/// `CORS_ALLOWED_ORIGINS` (comma separated) overrides the file's origins.
+fn read_policy() -> Result<CorsPolicy, String> {
+    let path = &crate::config::get().server.cors_config;
+    let mut policy = match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => CorsPolicy::default(),
+        Err(e) => return Err(format!("{}: {}", path, e)),
+    };
+    if let Ok(origins) = std::env::var("CORS_ALLOWED_ORIGINS") {
+        policy.allowed_origins = origins.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect();
+    }
+    policy.validate()
+}
+
+pub fn current() -> Arc<CorsPolicy> {
+    POLICY.read().unwrap().clone()
+}
+
+/// Re-reads the policy. A bad file leaves the running policy in place.
+pub fn reload() -> Result<Arc<CorsPolicy>, String> {
+    let policy = Arc::new(read_policy()?);
+    *POLICY.write().unwrap() = policy.clone();
+    println!("CORS policy loaded: origins {}", policy.allowed_origins.join(", "));
+    Ok(policy)
+}
+
+/// Applies `policy` to one request: answers preflights directly and adds the
+/// CORS headers to everything else.
+pub async fn respond(policy: &CorsPolicy, request: Request, next: Next) -> Response {
+    let origin = request.headers().get(header::ORIGIN).cloned();
+    let preflight_method = request
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let Some(origin) = origin else {
+        return next.run(request).await;
+    };
+
+    if request.method() == Method::OPTIONS {
+        if let Some(method) = preflight_method {
+            let requested = request
+                .headers()
+                .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+                .and_then(|v| v.to_str().ok());
+            let mut response = match policy.preflight(&origin, &method, requested) {
+                Some(headers) => (StatusCode::NO_CONTENT, headers).into_response(),
+                None => StatusCode::FORBIDDEN.into_response(),
+            };
+            response.headers_mut().insert(header::VARY, HeaderValue::from_static("origin, access-control-request-method, access-control-request-headers"));
+            return response;
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if let Some(headers) = policy.response_headers(&origin) {
+        response.headers_mut().extend(headers);
+    }
+    response.headers_mut().append(header::VARY, HeaderValue::from_static("origin"));
+    response
+}
+
+/// Middleware applying whichever policy is loaded when the request arrives,
+/// so a reload takes effect immediately.
+pub async fn layer(request: Request, next: Next) -> Response {
+    let policy = current();
+    respond(&policy, request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn policy(origins: &[&str]) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            ..CorsPolicy::default()
+        }
+        .validate()
+        .unwrap()
+    }
+
+    fn app(policy: CorsPolicy) -> Router {
+        let policy = Arc::new(policy);
+        Router::new()
+            .route("/pods", get(|| async { "pods" }).put(|| async { "updated" }))
+            .layer(axum::middleware::from_fn(move |request: Request, next: Next| {
+                let policy = policy.clone();
+                async move { respond(&policy, request, next).await }
+            }))
+    }
+
+    fn preflight(origin: &str, method: &str, headers: Option<&str>) -> Request {
+        let mut builder = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/pods")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method);
+        if let Some(headers) = headers {
+            builder = builder.header(header::ACCESS_CONTROL_REQUEST_HEADERS, headers);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn preflight_from_allowed_origin_is_answered() {
+        let response = app(policy(&["https://observer.example.com"]))
+            .oneshot(preflight("https://observer.example.com", "PUT", Some("Content-Type, X-API-Key")))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://observer.example.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, POST, PUT, DELETE");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "content-type, authorization, x-api-key");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+
+    #[tokio::test]
+    async fn preflight_from_unknown_origin_is_refused() {
+        let response = app(policy(&["https://observer.example.com"]))
+            .oneshot(preflight("https://evil.example.net", "GET", None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn preflight_with_unlisted_method_or_header_is_refused() {
+        let app = app(policy(&["https://observer.example.com"]));
+        let response = app.clone().oneshot(preflight("https://observer.example.com", "PATCH", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.oneshot(preflight("https://observer.example.com", "GET", Some("x-custom"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn wildcard_subdomains_match_only_subdomains() {
+        let app = app(policy(&["https://*.xandeum.network"]));
+        for (origin, allowed) in [
+            ("https://observer.xandeum.network", true),
+            ("https://a.b.xandeum.network", true),
+            ("https://xandeum.network", false),
+            ("http://observer.xandeum.network", false),
+            ("https://evilxandeum.network", false),
+            ("https://xandeum.network.evil.com", false),
+        ] {
+            let response = app.clone().oneshot(preflight(origin, "GET", None)).await.unwrap();
+            assert_eq!(response.status() == StatusCode::NO_CONTENT, allowed, "{}", origin);
+        }
+    }
+
+    #[tokio::test]
+    async fn credentials_echo_the_origin() {
+        let policy = CorsPolicy { allow_credentials: true, max_age_secs: 60, ..policy(&["http://localhost:8080"]) };
+        let response = app(policy).oneshot(preflight("http://localhost:8080", "GET", None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:8080");
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "60");
+    }
+
+    #[tokio::test]
+    async fn any_origin_answers_with_a_star() {
+        let response = app(policy(&["*"])).oneshot(preflight("https://anywhere.example", "DELETE", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+
+    #[tokio::test]
+    async fn simple_requests_get_origin_and_exposed_headers() {
+        let app = app(policy(&["https://observer.example.com"]));
+        let request = |origin: &str| {
+            Request::builder().uri("/pods").header(header::ORIGIN, origin).body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request("https://observer.example.com")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://observer.example.com");
+        assert!(response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap().contains("x-ratelimit-remaining"));
+
+        // The request still goes through; the browser is what withholds it.
+        let response = app.oneshot(request("https://evil.example.net")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        let invalid = |p: CorsPolicy| p.validate().is_err();
+        assert!(invalid(CorsPolicy { allowed_origins: vec!["observer.example.com".into()], ..CorsPolicy::default() }));
+        assert!(invalid(CorsPolicy { allowed_origins: vec!["https://api.*.example.com".into()], ..CorsPolicy::default() }));
+        assert!(invalid(CorsPolicy { allowed_origins: vec!["*".into()], allow_credentials: true, ..CorsPolicy::default() }));
+        assert!(invalid(CorsPolicy { allowed_methods: vec!["GE T".into()], ..CorsPolicy::default() }));
+    }
+
+    #[test]
+    fn policy_parses_from_toml() {
+        let policy: CorsPolicy = toml::from_str(
+            r#"
+            allowed_origins = ["https://*.Example.com/"]
+            allow_credentials = true
+            max_age_secs = 120
+            "#,
+        )
+        .unwrap();
+        let policy = policy.validate().unwrap();
+        assert_eq!(policy.allowed_origins, ["https://*.example.com"]);
+        assert_eq!(policy.allowed_methods, ["GET", "POST", "PUT", "DELETE"]);
+        assert!(policy.allows_origin("https://Observer.example.com"));
+    }
+}
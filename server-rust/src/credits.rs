@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::db;
+use crate::outbound::{self, Failure};
+
+const CREDITS_PATH: &str = "/api/pods-credits";
+
+// Keyed by network.
+static CACHE: Lazy<RwLock<HashMap<String, CreditsCache>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Last successfully ingested credits plus the outcome of the latest attempt.
+/// A failed fetch never clears `credits`; it only sets `last_error`, so callers
+/// keep serving the previous values and can see how stale they are.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreditsCache {
+    pub fetched_at: Option<i64>,
+    pub last_attempt_at: Option<i64>,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    pub credits: HashMap<String, i64>,
+}
+
+#[derive(Debug)]
+pub enum CreditsError {
+    Request(String),
+    Decode(String),
+    Empty,
+}
+
+impl std::fmt::Display for CreditsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreditsError::Request(e) => write!(f, "credits request failed: {}", e),
+            CreditsError::Decode(e) => write!(f, "credits payload not understood: {}", e),
+            CreditsError::Empty => write!(f, "credits API returned no entries"),
+        }
+    }
+}
+
+pub fn base_url() -> String {
+    crate::networks::current().credits_base_url.clone()
+}
+
+pub fn refresh_interval_secs() -> u64 {
+    crate::networks::current().credits_refresh_interval_secs
+}
+
+fn network() -> String {
+    crate::networks::current().name.clone()
+}
+
+pub fn cached(pubkey: &str) -> Option<i64> {
+    CACHE.read().unwrap().get(&network())?.credits.get(pubkey).copied()
+}
+
+pub fn snapshot() -> CreditsCache {
+    CACHE.read().unwrap().get(&network()).cloned().unwrap_or_default()
+}
+
+/// Seeds the in-memory cache from the most recent stored fetch so a restart
+/// serves the last known credits instead of nothing.
+pub async fn load_cached() -> Result<(), sqlx::Error> {
+    if let Some((fetched_at, credits)) = db::store().get_latest_credits().await? {
+        let mut all = CACHE.write().unwrap();
+        let cache = all.entry(network()).or_default();
+        cache.fetched_at = Some(fetched_at);
+        cache.credits = credits.into_iter().collect();
+    }
+    Ok(())
+}
+
+async fn fetch_credits() -> Result<Vec<(String, i64)>, CreditsError> {
+    let url = format!("{}{}", base_url().trim_end_matches('/'), CREDITS_PATH);
+    let json = outbound::call(&format!("credits:{}", network()), || async {
+        let resp = outbound::CLIENT
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(Failure::request)?;
+        if !resp.status().is_success() {
+            return Err(Failure::status(resp.status()));
+        }
+        resp.json::<serde_json::Value>()
+            .await
+            .map_err(|e| Failure::permanent(format!("credits payload not understood: {}", e)))
+    })
+    .await
+    .map_err(|e| CreditsError::Request(e.to_string()))?;
+
+    let credits = parse_credits(&json)?;
+    if credits.is_empty() {
+        return Err(CreditsError::Empty);
+    }
+    Ok(credits)
+}
+
+/// Accepts either a bare array of `{ pubkey, credits }` entries or an object
+/// wrapping that array, which is how the API has shaped it over time.
+fn parse_credits(json: &serde_json::Value) -> Result<Vec<(String, i64)>, CreditsError> {
+    let entries = match json {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(map) => map
+            .values()
+            .find_map(|v| v.as_array())
+            .ok_or_else(|| CreditsError::Decode("no credits array in response".to_string()))?,
+        _ => return Err(CreditsError::Decode("unexpected response type".to_string())),
+    };
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let pubkey = entry.get("pubkey")?.as_str()?;
+            let credits = entry.get("credits")?;
+            let credits = credits.as_i64().or_else(|| credits.as_f64().map(|c| c.round() as i64))?;
+            Some((pubkey.to_string(), credits))
+        })
+        .collect())
+}
+
+/// Runs one ingestion: fetch, persist to `credits_history`, join onto `nodes`
+/// and swap the cache. On any failure the cache and database are left as-is.
+pub async fn refresh_credits() {
+    let now = crate::now_secs();
+
+    let result = match fetch_credits().await {
+        Ok(credits) => db::store().save_credits(now, &credits).await.map(|_| credits).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let mut all = CACHE.write().unwrap();
+    let cache = all.entry(network()).or_default();
+    cache.last_attempt_at = Some(now);
+    match result {
+        Ok(credits) => {
+            println!("Ingested credits for {} pods", credits.len());
+            cache.fetched_at = Some(now);
+            cache.last_error = None;
+            cache.credits = credits.into_iter().collect();
+        }
+        Err(e) => {
+            eprintln!("Failed to refresh credits: {}", e);
+            cache.last_error = Some(e);
+        }
+    }
+}
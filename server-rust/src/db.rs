@@ -1,75 +1,113 @@
-use sqlx::{sqlite::SqlitePool, Row};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
 use tokio::sync::OnceCell;
 
-static DB_POOL: OnceCell<SqlitePool> = OnceCell::const_new();
+mod postgres;
+mod sqlite;
+
+// One store per network, keyed by network name.
+static STORES: OnceCell<HashMap<String, Box<dyn Storage>>> = OnceCell::const_new();
 
+/// Opens and migrates every network's database.
 pub async fn init_db() -> Result<(), sqlx::Error> {
-    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:xandeum.db".to_string());
-    
-    // Create database file if it doesn't exist
-    if !std::path::Path::new("xandeum.db").exists() {
-        std::fs::File::create("xandeum.db").expect("Failed to create database file");
+    let mut stores = HashMap::new();
+    for network in crate::networks::all() {
+        stores.insert(network.name.clone(), open(&network.database_url).await?);
     }
-
-    let pool = SqlitePool::connect(&database_url).await?;
-    
-    // Create tables
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS nodes (
-            pubkey TEXT PRIMARY KEY,
-            ip TEXT NOT NULL,
-            version TEXT,
-            status TEXT,
-            last_seen INTEGER,
-            storage_used INTEGER,
-            storage_committed INTEGER,
-            storage_usage_percent REAL,
-            credits INTEGER,
-            latency_ms INTEGER,
-            country TEXT,
-            city TEXT,
-            lat REAL,
-            lon REAL
-        );
-        "#
-    ).execute(&pool).await?;
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS metrics (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            timestamp INTEGER NOT NULL,
-            total_nodes INTEGER,
-            online_nodes INTEGER,
-            total_storage INTEGER
-        );
-        "#
-    ).execute(&pool).await?;
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS node_history (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            pubkey TEXT NOT NULL,
-            timestamp INTEGER NOT NULL,
-            latency_ms INTEGER,
-            status TEXT,
-            FOREIGN KEY(pubkey) REFERENCES nodes(pubkey)
-        );
-        CREATE INDEX IF NOT EXISTS idx_node_history_pubkey_timestamp ON node_history(pubkey, timestamp);
-        "#
-    ).execute(&pool).await?;
-
-    DB_POOL.set(pool).expect("Failed to set DB pool");
+    STORES.set(stores).unwrap_or_else(|_| panic!("Failed to set DB stores"));
     Ok(())
 }
 
-pub fn get_pool() -> &'static SqlitePool {
-    DB_POOL.get().expect("DB not initialized")
+/// Picks the backend from the URL scheme: `postgres://` or `postgresql://`
+/// for PostgreSQL (with TimescaleDB hypertables when enabled), `sqlite:`
+/// otherwise.
+async fn open(database_url: &str) -> Result<Box<dyn Storage>, sqlx::Error> {
+    if is_postgres(database_url) {
+        let timescale = crate::config::get().server.timescale;
+        Ok(Box::new(postgres::PostgresStorage::open(database_url, timescale).await?))
+    } else {
+        Ok(Box::new(sqlite::SqliteStorage::open(database_url).await?))
+    }
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
+pub fn is_postgres(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+/// Swaps the SQLite database at `database_url` for the backup at `backup`,
+/// which must pass an integrity check. Refuses while a server has it open.
+/// Returns where the previous database was kept, under a timestamped name,
+/// if there was one.
+pub async fn restore(database_url: &str, backup: &Path) -> Result<Option<PathBuf>, sqlx::Error> {
+    if is_postgres(database_url) {
+        return Err(sqlx::Error::Configuration("PostgreSQL databases are restored with pg_restore".into()));
+    }
+    sqlite::restore(database_url, backup).await
+}
+
+/// `database_url` with any password masked, for logs.
+pub fn display_url(database_url: &str) -> String {
+    let Some((scheme, rest)) = database_url.split_once("://") else {
+        return database_url.to_string();
+    };
+    match rest.split_once('@') {
+        Some((userinfo, host)) if userinfo.contains(':') => {
+            let user = userinfo.split(':').next().unwrap_or_default();
+            format!("{}://{}:***@{}", scheme, user, host)
+        }
+        _ => database_url.to_string(),
+    }
+}
+
+// Rows per multi-row INSERT, well under either backend's bind limit.
+const NODE_BATCH: usize = 500;
+
+const NODE_COLUMNS: &str = "INSERT INTO nodes (pubkey, ip, version, status, last_seen, storage_used, storage_committed, \
+    storage_usage_percent, credits, latency_ms, country, city, lat, lon, uptime) ";
+
+const NODE_UPSERT: &str = " ON CONFLICT(pubkey) DO UPDATE SET ip = excluded.ip, version = excluded.version, \
+    status = excluded.status, last_seen = excluded.last_seen, storage_used = excluded.storage_used, \
+    storage_committed = excluded.storage_committed, storage_usage_percent = excluded.storage_usage_percent, \
+    credits = excluded.credits, latency_ms = excluded.latency_ms, country = excluded.country, city = excluded.city, \
+    lat = excluded.lat, lon = excluded.lon, uptime = excluded.uptime";
+
+/// One record per pubkey, the last one listed winning. A single upsert
+/// statement may not touch the same row twice.
+fn latest_per_pubkey(nodes: &[NodeRecord]) -> Vec<&NodeRecord> {
+    let mut index = HashMap::new();
+    let mut latest: Vec<&NodeRecord> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match index.get(node.pubkey.as_str()) {
+            Some(&i) => latest[i] = node,
+            None => {
+                index.insert(node.pubkey.as_str(), latest.len());
+                latest.push(node);
+            }
+        }
+    }
+    latest
+}
+
+/// The current network's store.
+pub fn store() -> &'static dyn Storage {
+    let stores = STORES.get().expect("DB not initialized");
+    stores[&crate::networks::current().name].as_ref()
+}
+
+/// The default network's store, which also holds what isn't tied to a
+/// network: accounts and their sessions, pubkey claims, profiles and
+/// watchlists, API keys, and email subscriptions and the email log, so an
+/// owner's subscriptions follow their claims and a recipient's hourly limit
+/// counts mail from every network.
+pub fn shared() -> &'static dyn Storage {
+    let stores = STORES.get().expect("DB not initialized");
+    stores[&crate::networks::default().name].as_ref()
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
 pub struct NodeRecord {
     pub pubkey: String,
     pub ip: String,
@@ -85,6 +123,27 @@ pub struct NodeRecord {
     pub city: Option<String>,
     pub lat: Option<f64>,
     pub lon: Option<f64>,
+    pub uptime: Option<i64>,
+}
+
+/// A stored network snapshot; its id doubles as the cycle id.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct MetricsRecord {
+    pub cycle_id: i64,
+    pub timestamp: i64,
+    pub total_nodes: Option<i64>,
+    pub online_nodes: Option<i64>,
+    pub total_storage: Option<i64>,
+    pub total_committed: Option<i64>,
+}
+
+/// Network totals recorded once per refresh cycle.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub total_nodes: i64,
+    pub online_nodes: i64,
+    pub total_storage: i64,
+    pub total_committed: i64,
 }
 
 #[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
@@ -92,121 +151,598 @@ pub struct NodeHistoryRecord {
     pub timestamp: i64,
     pub latency_ms: Option<i64>,
     pub status: Option<String>,
+    pub storage_used: Option<i64>,
+    pub storage_committed: Option<i64>,
+    pub uptime: Option<i64>,
 }
 
-pub async fn upsert_node(node: &NodeRecord) -> Result<(), sqlx::Error> {
-    let pool = get_pool();
-    sqlx::query(
-        r#"
-        INSERT INTO nodes (pubkey, ip, version, status, last_seen, storage_used, storage_committed, storage_usage_percent, credits, latency_ms, country, city, lat, lon)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        ON CONFLICT(pubkey) DO UPDATE SET
-            ip = excluded.ip,
-            version = excluded.version,
-            status = excluded.status,
-            last_seen = excluded.last_seen,
-            storage_used = excluded.storage_used,
-            storage_committed = excluded.storage_committed,
-            storage_usage_percent = excluded.storage_usage_percent,
-            credits = excluded.credits,
-            latency_ms = excluded.latency_ms,
-            country = excluded.country,
-            city = excluded.city,
-            lat = excluded.lat,
-            lon = excluded.lon
-        "#
-    )
-    .bind(&node.pubkey)
-    .bind(&node.ip)
-    .bind(&node.version)
-    .bind(&node.status)
-    .bind(node.last_seen)
-    .bind(node.storage_used)
-    .bind(node.storage_committed)
-    .bind(node.storage_usage_percent)
-    .bind(node.credits)
-    .bind(node.latency_ms)
-    .bind(&node.country)
-    .bind(&node.city)
-    .bind(node.lat)
-    .bind(node.lon)
-    .execute(pool)
-    .await?;
-    Ok(())
+/// Rows deleted by [`Storage::prune`], per table.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct Pruned {
+    pub node_history: u64,
+    pub metrics: u64,
+    pub observer_cycles: u64,
+    pub credits_history: u64,
 }
 
-pub async fn get_all_nodes() -> Result<Vec<NodeRecord>, sqlx::Error> {
-    let pool = get_pool();
-    sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes")
-        .fetch_all(pool)
-        .await
-}
-
-pub async fn get_node_by_id(pubkey: &str) -> Result<Option<NodeRecord>, sqlx::Error> {
-    let pool = get_pool();
-    sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes WHERE pubkey = ?")
-        .bind(pubkey)
-        .fetch_optional(pool)
-        .await
-}
-
-pub async fn save_snapshot(total_nodes: u32, online_nodes: u32, total_storage: u64) -> Result<(), sqlx::Error> {
-    let pool = get_pool();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-
-    sqlx::query(
-        "INSERT INTO metrics (timestamp, total_nodes, online_nodes, total_storage) VALUES (?, ?, ?, ?)"
-    )
-    .bind(timestamp)
-    .bind(total_nodes)
-    .bind(online_nodes)
-    .bind(total_storage as i64)
-    .execute(pool)
-    .await?;
-    Ok(())
+/// A state change noticed between two refresh cycles: `first_seen`,
+/// `went_offline`, `came_online`, `version_changed` or `restarted`.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct NodeEvent {
+    pub pubkey: String,
+    pub timestamp: i64,
+    pub event_type: String,
+    pub previous: Option<String>,
+    pub current: Option<String>,
 }
 
-pub async fn save_node_history(pubkey: &str, latency_ms: Option<i64>, status: Option<&str>) -> Result<(), sqlx::Error> {
-    let pool = get_pool();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-
-    sqlx::query(
-        "INSERT INTO node_history (pubkey, timestamp, latency_ms, status) VALUES (?, ?, ?, ?)"
-    )
-    .bind(pubkey)
-    .bind(timestamp)
-    .bind(latency_ms)
-    .bind(status)
-    .execute(pool)
-    .await?;
-    Ok(())
+/// One run of the refresh loop. `outcome` is `ok` when a seed returned pods,
+/// `empty` when it answered with no pods and `failed` when every seed failed;
+/// only `ok` cycles count as the observer having seen the network.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct ObserverCycle {
+    pub id: i64,
+    /// The cycle's data, when it got as far as writing any.
+    pub cycle_id: Option<i64>,
+    pub started_at: i64,
+    pub duration_ms: i64,
+    pub outcome: String,
+    pub seed: Option<String>,
+    pub pods_received: i64,
+    pub error: Option<String>,
+}
+
+/// Exponentially decayed accumulators behind a node's reputation, together
+/// with the scores last derived from them.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReputationRecord {
+    pub pubkey: String,
+    pub first_seen: i64,
+    pub updated_at: i64,
+    pub weight: f64,
+    pub up_weight: f64,
+    pub latency_weight: f64,
+    pub latency_sum: f64,
+    pub latency_sq_sum: f64,
+    pub version_weight: f64,
+    pub version_sum: f64,
+    pub credits_weight: f64,
+    pub credits_sum: f64,
+    pub availability_score: f64,
+    pub latency_score: f64,
+    pub version_score: f64,
+    pub credits_score: f64,
+    pub tenure_score: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct AlertRule {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    pub pubkey: Option<String>,
+    pub threshold: Option<f64>,
+    pub min_version: Option<String>,
+    pub duration_secs: i64,
+    pub severity: String,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub group_by: String,
+    /// Set for private rules, which only their owner sees and is notified of.
+    pub owner_account_id: Option<i64>,
+}
+
+/// One occurrence of a rule matching a subject (a pubkey, or `network` for
+/// network-wide rules). Moves from `pending` to `firing` once the rule's
+/// duration has elapsed, then to `resolved` when the condition clears.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct Alert {
+    pub id: i64,
+    pub rule_id: i64,
+    pub subject: String,
+    pub state: String,
+    pub value: Option<f64>,
+    pub message: String,
+    pub started_at: i64,
+    pub fired_at: Option<i64>,
+    pub resolved_at: Option<i64>,
+    pub updated_at: i64,
+    pub group_key: Option<String>,
+    pub suppressed_by: Option<String>,
 }
 
-pub async fn get_history(limit: i64) -> Result<Vec<(i64, i64, i64, i64)>, sqlx::Error> {
-    let pool = get_pool();
-    let rows = sqlx::query("SELECT timestamp, total_nodes, online_nodes, total_storage FROM metrics ORDER BY timestamp DESC LIMIT ?")
-        .bind(limit)
-        .fetch_all(pool)
-        .await?;
+/// An HTTP endpoint that fired and resolved alerts are posted to. `format` is
+/// `json` (the alert itself, or `template` rendered), `slack` or `discord`.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub format: String,
+    pub template: Option<String>,
+    #[serde(skip)]
+    pub secret: Option<String>,
+    pub enabled: bool,
+    pub created_at: i64,
+}
 
-    Ok(rows.into_iter().map(|r| (
-        r.get(0), r.get(1), r.get(2), r.get(3)
-    )).collect())
+/// One attempt at delivering an alert to a webhook.
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub alert_id: Option<i64>,
+    pub alert_state: String,
+    pub attempt: i64,
+    pub status_code: Option<i64>,
+    pub error: Option<String>,
+    pub delivered: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct EmailSubscription {
+    pub id: i64,
+    pub email: String,
+    pub pubkey: String,
+    pub digest: bool,
+    pub created_at: i64,
+    /// Unset until the address confirms the subscription; only confirmed
+    /// subscriptions are mailed.
+    pub confirmed_at: Option<i64>,
+}
+
+/// An alert notice held back for a recipient's next digest.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QueuedEmail {
+    pub email: String,
+    pub alert_id: i64,
+    pub rule_name: String,
+    pub severity: String,
+    pub subject: String,
+    pub state: String,
+    pub message: String,
+    pub at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct EmailLogEntry {
+    pub id: i64,
+    pub email: String,
+    pub kind: String,
+    pub subject_line: String,
+    pub notices: i64,
+    pub error: Option<String>,
+    pub sent_at: i64,
+}
+
+/// Mutes notifications for alerts whose labels match every matcher between
+/// `starts_at` and `ends_at`. `matchers` is a JSON array of `{name, op, value}`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Silence {
+    pub id: i64,
+    pub matchers: String,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub comment: String,
+    pub created_by: Option<String>,
+    pub created_at: i64,
+}
+
+/// While an alert matching `source_matchers` fires, alerts matching
+/// `target_matchers` (and sharing the labels listed in `equal`) don't notify.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct InhibitRule {
+    pub id: i64,
+    pub name: String,
+    pub source_matchers: String,
+    pub target_matchers: String,
+    pub equal: String,
+    pub enabled: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct AlertGroup {
+    pub group_key: String,
+    pub rule_id: i64,
+    pub state: String,
+    pub opened_at: i64,
+    pub notified_at: i64,
+    pub resolved_at: Option<i64>,
+    pub alerts: i64,
+}
+
+/// A firing alert with the rule fields used as matcher labels.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FiringAlertLabels {
+    pub id: i64,
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub kind: String,
+    pub severity: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct Account {
+    pub id: i64,
+    pub username: String,
+    pub email: Option<String>,
+    #[serde(skip)]
+    pub password_hash: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct ClaimedPubkey {
+    pub pubkey: String,
+    pub claimed_at: i64,
+    /// `None` for claims made before ownership had to be proven.
+    pub verified_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct NodeProfile {
+    pub pubkey: String,
+    #[serde(skip)]
+    pub account_id: i64,
+    pub display_name: Option<String>,
+    pub website: Option<String>,
+    pub contact: Option<String>,
+    pub contact_public: bool,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    /// Comma separated, `admin` or empty. Keys created while every key carried
+    /// `read` still list it; it grants nothing.
+    pub scopes: String,
+    /// Overrides the default limit for keyed clients when set.
+    pub rate_limit_per_min: Option<i64>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.split(',').any(|s| s == scope)
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct Seed {
+    pub address: String,
+    pub source: String,
+    pub successes: i64,
+    pub failures: i64,
+    pub consecutive_failures: i64,
+    /// Moving average of successful fetches.
+    pub latency_ms: Option<f64>,
+    /// Pods in its last successful answer.
+    pub pod_count: Option<i64>,
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    pub quarantined_until: Option<i64>,
+    pub added_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct WatchlistEntry {
+    pub pubkey: String,
+    pub label: Option<String>,
+    pub added_at: i64,
 }
 
-pub async fn get_node_history(pubkey: &str, limit: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
-    let pool = get_pool();
-    sqlx::query_as::<_, NodeHistoryRecord>(
-        "SELECT timestamp, latency_ms, status FROM node_history WHERE pubkey = ? ORDER BY timestamp DESC LIMIT ?"
-    )
-    .bind(pubkey)
-    .bind(limit)
-    .fetch_all(pool)
-    .await
+/// Everything the observer persists. Implemented for SQLite and PostgreSQL;
+/// every network gets its own store.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn get_all_nodes(&self) -> Result<Vec<NodeRecord>, sqlx::Error>;
+
+    async fn get_node_by_id(&self, pubkey: &str) -> Result<Option<NodeRecord>, sqlx::Error>;
+
+    /// Writes one refresh cycle in a single transaction: the network snapshot,
+    /// every node's current state, a history row per node and the cycle's
+    /// node events, all stamped with `cycle_at`, and marks the `gone` nodes,
+    /// which dropped out of gossip, offline. Returns the cycle id, which is
+    /// the snapshot's row id.
+    async fn save_cycle(
+        &self,
+        cycle_at: i64,
+        snapshot: &Snapshot,
+        nodes: &[NodeRecord],
+        events: &[NodeEvent],
+        gone: &[String],
+    ) -> Result<i64, sqlx::Error>;
+
+    async fn get_history(&self, limit: i64) -> Result<Vec<(i64, i64, i64, i64)>, sqlx::Error>;
+
+    async fn get_node_history(&self, pubkey: &str, limit: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error>;
+
+    async fn get_node_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error>;
+
+    /// Every node's history since `since`, ordered by node and time.
+    async fn get_all_node_history_since(&self, since: i64) -> Result<Vec<(String, NodeHistoryRecord)>, sqlx::Error>;
+
+    /// Nodes last seen in `[from, to)`, read lazily for exports.
+    fn stream_nodes(&self, from: i64, to: i64) -> BoxStream<'_, Result<NodeRecord, sqlx::Error>>;
+
+    /// Network snapshots taken in `[from, to)`, oldest first, read lazily.
+    fn stream_metrics(&self, from: i64, to: i64) -> BoxStream<'_, Result<MetricsRecord, sqlx::Error>>;
+
+    /// One node's history in `[from, to)`, oldest first, read lazily.
+    fn stream_node_history(&self, pubkey: &str, from: i64, to: i64) -> BoxStream<'_, Result<NodeHistoryRecord, sqlx::Error>>;
+
+    /// Writes a consistent copy of the live database to `path`, which must
+    /// not exist yet, and checks the copy's integrity.
+    async fn backup(&self, path: &Path) -> Result<(), sqlx::Error>;
+
+    /// Deletes time series rows (node and network history, observer cycles
+    /// and credits history) older than `before`, in one transaction.
+    async fn prune(&self, before: i64) -> Result<Pruned, sqlx::Error>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn save_observer_cycle(
+        &self,
+        cycle_id: Option<i64>,
+        started_at: i64,
+        duration_ms: i64,
+        outcome: &str,
+        seed: Option<&str>,
+        pods_received: i64,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_observer_cycles(&self, limit: i64) -> Result<Vec<ObserverCycle>, sqlx::Error>;
+
+    /// Start time of every refresh cycle since `since`, oldest first, paired with
+    /// whether the observer actually saw the network during that cycle.
+    async fn get_cycles_since(&self, since: i64) -> Result<Vec<(i64, bool)>, sqlx::Error>;
+
+    /// Stores one credits fetch under a single timestamp and copies the values onto
+    /// the matching `nodes` rows, all in one transaction.
+    async fn save_credits(&self, timestamp: i64, credits: &[(String, i64)]) -> Result<(), sqlx::Error>;
+
+    /// The most recent credits fetch, if any has been stored.
+    async fn get_latest_credits(&self) -> Result<Option<(i64, Vec<(String, i64)>)>, sqlx::Error>;
+
+    async fn get_node_credits_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<(i64, i64)>, sqlx::Error>;
+
+    async fn get_credits_history_since(&self, since: i64) -> Result<Vec<(String, i64, i64)>, sqlx::Error>;
+
+    async fn get_node_history_before(&self, pubkey: &str, before: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error>;
+
+    async fn get_all_reputations(&self) -> Result<Vec<ReputationRecord>, sqlx::Error>;
+
+    async fn save_reputations(&self, records: &[&ReputationRecord]) -> Result<(), sqlx::Error>;
+
+    /// One page of reputations ordered best first, plus the total number ranked.
+    async fn get_reputation_page(&self, limit: i64, offset: i64) -> Result<(i64, Vec<ReputationRecord>), sqlx::Error>;
+
+    /// A node's events since `since`, oldest first, preceded by its latest
+    /// `first_seen` or `version_changed` event before then so the version it
+    /// was running at `since` is known.
+    async fn get_node_events_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error>;
+
+    /// [`Storage::get_node_events_since`] for every node, ordered by node and time.
+    async fn get_all_node_events_since(&self, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error>;
+
+    /// When each version was first seen anywhere in the network.
+    async fn get_version_first_seen(&self) -> Result<Vec<(String, i64)>, sqlx::Error>;
+
+    async fn save_prediction_model(&self, trained_at: i64, samples: i64, positives: i64, model: &str) -> Result<(), sqlx::Error>;
+
+    async fn get_latest_prediction_model(&self) -> Result<Option<String>, sqlx::Error>;
+
+    /// Hourly averages of each node's storage since `since`, ordered by node and
+    /// time: `(pubkey, hour, storage_used, storage_committed)`.
+    async fn get_hourly_node_storage_since(&self, since: i64) -> Result<Vec<(String, i64, f64, f64)>, sqlx::Error>;
+
+    /// Hourly averages of network storage since `since`: `(hour, used, committed)`.
+    /// Committed is `None` for snapshots taken before it was recorded.
+    async fn get_hourly_network_storage_since(&self, since: i64) -> Result<Vec<(i64, f64, Option<f64>)>, sqlx::Error>;
+
+    async fn get_alert_rules(&self) -> Result<Vec<AlertRule>, sqlx::Error>;
+
+    async fn get_alert_rule(&self, id: i64) -> Result<Option<AlertRule>, sqlx::Error>;
+
+    /// Inserts the rule, or updates it when `rule.id` names an existing one.
+    /// Returns the rule's id.
+    async fn save_alert_rule(&self, rule: &AlertRule) -> Result<i64, sqlx::Error>;
+
+    /// Deletes a rule together with its alerts. Returns whether the rule existed.
+    async fn delete_alert_rule(&self, id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Pending and firing alerts for a rule.
+    async fn get_active_alerts_for_rule(&self, rule_id: i64) -> Result<Vec<Alert>, sqlx::Error>;
+
+    async fn get_alerts(&self, state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error>;
+
+    async fn insert_alert(&self, alert: &Alert) -> Result<i64, sqlx::Error>;
+
+    async fn update_alert(&self, alert: &Alert) -> Result<(), sqlx::Error>;
+
+    async fn delete_alert(&self, id: i64) -> Result<(), sqlx::Error>;
+
+    /// Highest online node count in any snapshot since `since`.
+    async fn get_peak_online_since(&self, since: i64) -> Result<Option<i64>, sqlx::Error>;
+
+    async fn get_webhooks(&self) -> Result<Vec<Webhook>, sqlx::Error>;
+
+    async fn get_webhook(&self, id: i64) -> Result<Option<Webhook>, sqlx::Error>;
+
+    /// Inserts the webhook, or updates it when `hook.id` names an existing one.
+    /// Returns the webhook's id.
+    async fn save_webhook(&self, hook: &Webhook) -> Result<i64, sqlx::Error>;
+
+    /// Deletes a webhook and its delivery log. Returns whether it existed.
+    async fn delete_webhook(&self, id: i64) -> Result<bool, sqlx::Error>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn save_webhook_delivery(
+        &self,
+        webhook_id: i64,
+        alert_id: Option<i64>,
+        alert_state: &str,
+        attempt: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+        delivered: bool,
+        created_at: i64,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn get_webhook_deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<WebhookDelivery>, sqlx::Error>;
+
+    async fn get_email_subscriptions(&self) -> Result<Vec<EmailSubscription>, sqlx::Error>;
+
+    /// Subscribes the address to a pubkey pending confirmation with the token
+    /// whose hash is given. An existing unconfirmed subscription gets the new
+    /// token; a confirmed one only has its digest setting changed. Returns the
+    /// subscription's id and whether it is already confirmed.
+    async fn save_pending_email_subscription(
+        &self,
+        email: &str,
+        pubkey: &str,
+        digest: bool,
+        token_hash: &str,
+        created_at: i64,
+    ) -> Result<(i64, bool), sqlx::Error>;
+
+    /// Confirms the pending subscription holding the token, if any.
+    async fn confirm_email_subscription(&self, token_hash: &str, confirmed_at: i64) -> Result<Option<EmailSubscription>, sqlx::Error>;
+
+    async fn delete_email_subscription(&self, id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn queue_email(&self, item: &QueuedEmail) -> Result<(), sqlx::Error>;
+
+    /// Removes and returns everything queued for digests, grouped by recipient and
+    /// in the order the notices happened.
+    async fn take_email_queue(&self) -> Result<Vec<QueuedEmail>, sqlx::Error>;
+
+    async fn save_email_log(&self, email: &str, kind: &str, subject_line: &str, notices: i64, error: Option<&str>, sent_at: i64) -> Result<(), sqlx::Error>;
+
+    /// Emails successfully sent to the address since `since`, for rate limiting.
+    async fn count_emails_sent_since(&self, email: &str, since: i64) -> Result<i64, sqlx::Error>;
+
+    async fn get_email_log(&self, limit: i64) -> Result<Vec<EmailLogEntry>, sqlx::Error>;
+
+    async fn get_alert(&self, id: i64) -> Result<Option<Alert>, sqlx::Error>;
+
+    /// Records which notification group an alert belongs to and what, if
+    /// anything, kept it from notifying.
+    async fn set_alert_routing(&self, id: i64, group_key: Option<&str>, suppressed_by: Option<&str>) -> Result<(), sqlx::Error>;
+
+    async fn get_firing_alert_labels(&self) -> Result<Vec<FiringAlertLabels>, sqlx::Error>;
+
+    /// Firing alerts in the group that were actually announced.
+    async fn count_notified_firing_in_group(&self, group_key: &str) -> Result<i64, sqlx::Error>;
+
+    async fn get_silences(&self, include_expired: bool, now: i64) -> Result<Vec<Silence>, sqlx::Error>;
+
+    async fn get_silence(&self, id: i64) -> Result<Option<Silence>, sqlx::Error>;
+
+    async fn insert_silence(&self, silence: &Silence) -> Result<i64, sqlx::Error>;
+
+    /// Ends a silence now. Silences are kept after they end so the history of who
+    /// muted what stays visible.
+    async fn expire_silence(&self, id: i64, now: i64) -> Result<bool, sqlx::Error>;
+
+    async fn get_inhibit_rules(&self) -> Result<Vec<InhibitRule>, sqlx::Error>;
+
+    async fn get_inhibit_rule(&self, id: i64) -> Result<Option<InhibitRule>, sqlx::Error>;
+
+    async fn save_inhibit_rule(&self, rule: &InhibitRule) -> Result<i64, sqlx::Error>;
+
+    async fn delete_inhibit_rule(&self, id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn get_alert_group(&self, group_key: &str) -> Result<Option<AlertGroup>, sqlx::Error>;
+
+    async fn save_alert_group(&self, group: &AlertGroup) -> Result<(), sqlx::Error>;
+
+    async fn get_alert_groups(&self, state: Option<&str>, limit: i64) -> Result<Vec<AlertGroup>, sqlx::Error>;
+
+    /// Creates an account, returning `None` if the username is taken.
+    async fn insert_account(&self, username: &str, email: Option<&str>, password_hash: &str, created_at: i64) -> Result<Option<i64>, sqlx::Error>;
+
+    async fn get_account_by_username(&self, username: &str) -> Result<Option<Account>, sqlx::Error>;
+
+    /// Changes the account's email and, in the same transaction, moves its
+    /// subscriptions to the pubkeys it verifiably owns to the new address, or
+    /// drops them when the email is cleared.
+    async fn update_account_email(&self, account_id: i64, email: Option<&str>, now: i64) -> Result<(), sqlx::Error>;
+
+    async fn insert_session(&self, token_hash: &str, account_id: i64, created_at: i64, expires_at: i64) -> Result<(), sqlx::Error>;
+
+    /// The account behind an unexpired session token hash.
+    async fn get_session_account(&self, token_hash: &str, now: i64) -> Result<Option<Account>, sqlx::Error>;
+
+    /// Deletes the session and any expired ones along with it.
+    async fn delete_session(&self, token_hash: &str, now: i64) -> Result<(), sqlx::Error>;
+
+    async fn get_claimed_pubkeys(&self, account_id: i64) -> Result<Vec<ClaimedPubkey>, sqlx::Error>;
+
+    /// Links a pubkey whose ownership was just proven to the account. Any other
+    /// account's verified claim on it lapses, since only one party holds the key
+    /// that signed most recently, and in the same transaction the lapsed owners
+    /// lose what the claim gave them here, their private rules for the pubkey
+    /// and their email subscription to it, while the account's own email, if
+    /// set, is subscribed. Returns the lapsed owners so the other networks'
+    /// stores can be cleared with `revoke_owners`.
+    async fn save_verified_claim(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Vec<Account>, sqlx::Error>;
+
+    /// Deletes the accounts' private rules for the pubkey, with their alerts.
+    async fn revoke_owners(&self, owners: &[Account], pubkey: &str) -> Result<(), sqlx::Error>;
+
+    async fn is_verified_owner(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error>;
+
+    async fn save_ownership_challenge(&self, account_id: i64, pubkey: &str, message: &str, expires_at: i64) -> Result<(), sqlx::Error>;
+
+    /// The outstanding challenge message, if it hasn't expired.
+    async fn get_ownership_challenge(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Option<String>, sqlx::Error>;
+
+    async fn get_account(&self, id: i64) -> Result<Option<Account>, sqlx::Error>;
+
+    async fn get_node_profile(&self, pubkey: &str) -> Result<Option<NodeProfile>, sqlx::Error>;
+
+    async fn save_node_profile(&self, profile: &NodeProfile) -> Result<(), sqlx::Error>;
+
+    /// Drops the account's claim on the pubkey and, with it, the account's
+    /// email subscription to it.
+    async fn release_pubkey(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error>;
+
+    async fn get_watchlist(&self, account_id: i64) -> Result<Vec<WatchlistEntry>, sqlx::Error>;
+
+    /// Adds the pubkey to the watchlist, or relabels it if already there.
+    async fn save_watchlist_entry(&self, account_id: i64, pubkey: &str, label: Option<&str>, added_at: i64) -> Result<(), sqlx::Error>;
+
+    async fn delete_watchlist_entry(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error>;
+
+    /// Alerts about any of the given subjects from public rules and the account's
+    /// own private rules, newest first. Without a state only unresolved alerts are
+    /// returned, like `get_alerts`.
+    async fn get_alerts_for_subjects(&self, account_id: i64, subjects: &[String], state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error>;
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error>;
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+
+    async fn insert_api_key(&self, key: &ApiKey, key_hash: &str) -> Result<i64, sqlx::Error>;
+
+    async fn touch_api_key(&self, id: i64, now: i64) -> Result<(), sqlx::Error>;
+
+    /// Revoked keys are kept so their names and usage stay visible.
+    async fn revoke_api_key(&self, id: i64, now: i64) -> Result<bool, sqlx::Error>;
+
+    async fn get_seeds(&self) -> Result<Vec<Seed>, sqlx::Error>;
+
+    async fn save_seed(&self, seed: &Seed) -> Result<(), sqlx::Error>;
+
+    async fn delete_seed(&self, address: &str) -> Result<(), sqlx::Error>;
 }
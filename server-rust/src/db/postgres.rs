@@ -0,0 +1,1907 @@
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::{Executor, Postgres, QueryBuilder, Row};
+
+use super::{latest_per_pubkey, MetricsRecord, Pruned, Snapshot, NODE_BATCH, NODE_COLUMNS, NODE_UPSERT};
+use super::{
+    Account, Alert, AlertGroup, AlertRule, ApiKey, ClaimedPubkey, EmailLogEntry, EmailSubscription,
+    FiringAlertLabels, InhibitRule, NodeEvent, NodeHistoryRecord, NodeProfile, NodeRecord,
+    ObserverCycle, QueuedEmail, ReputationRecord, Seed, Silence, WatchlistEntry, Webhook,
+    WebhookDelivery, Storage,
+};
+
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub async fn open(database_url: &str, timescale: bool) -> Result<Self, sqlx::Error> {
+        Ok(PostgresStorage { pool: open(database_url, timescale).await? })
+    }
+}
+
+// PostgreSQL runs multi-statement strings only through the simple query
+// protocol, so the schema is applied as one batch rather than per table.
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS nodes (
+    pubkey TEXT PRIMARY KEY,
+    ip TEXT NOT NULL,
+    version TEXT,
+    status TEXT,
+    last_seen BIGINT,
+    storage_used BIGINT,
+    storage_committed BIGINT,
+    storage_usage_percent DOUBLE PRECISION,
+    credits BIGINT,
+    latency_ms BIGINT,
+    country TEXT,
+    city TEXT,
+    lat DOUBLE PRECISION,
+    lon DOUBLE PRECISION,
+    uptime BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS metrics (
+    id BIGSERIAL,
+    timestamp BIGINT NOT NULL,
+    total_nodes BIGINT,
+    online_nodes BIGINT,
+    total_storage BIGINT,
+    total_committed BIGINT,
+    PRIMARY KEY(id, timestamp)
+);
+CREATE INDEX IF NOT EXISTS idx_metrics_timestamp ON metrics(timestamp);
+
+CREATE TABLE IF NOT EXISTS node_history (
+    id BIGSERIAL,
+    cycle_id BIGINT,
+    pubkey TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    latency_ms BIGINT,
+    status TEXT,
+    storage_used BIGINT,
+    storage_committed BIGINT,
+    uptime BIGINT,
+    PRIMARY KEY(id, timestamp),
+    FOREIGN KEY(pubkey) REFERENCES nodes(pubkey)
+);
+CREATE INDEX IF NOT EXISTS idx_node_history_pubkey_timestamp ON node_history(pubkey, timestamp);
+
+CREATE TABLE IF NOT EXISTS observer_cycles (
+    id BIGSERIAL PRIMARY KEY,
+    cycle_id BIGINT,
+    started_at BIGINT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    outcome TEXT NOT NULL,
+    seed TEXT,
+    pods_received BIGINT NOT NULL DEFAULT 0,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_observer_cycles_started_at ON observer_cycles(started_at);
+
+CREATE TABLE IF NOT EXISTS credits_history (
+    id BIGSERIAL PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    credits BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_credits_history_pubkey_timestamp ON credits_history(pubkey, timestamp);
+CREATE INDEX IF NOT EXISTS idx_credits_history_timestamp ON credits_history(timestamp);
+
+CREATE TABLE IF NOT EXISTS node_reputation (
+    pubkey TEXT PRIMARY KEY,
+    first_seen BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    weight DOUBLE PRECISION NOT NULL,
+    up_weight DOUBLE PRECISION NOT NULL,
+    latency_weight DOUBLE PRECISION NOT NULL,
+    latency_sum DOUBLE PRECISION NOT NULL,
+    latency_sq_sum DOUBLE PRECISION NOT NULL,
+    version_weight DOUBLE PRECISION NOT NULL,
+    version_sum DOUBLE PRECISION NOT NULL,
+    credits_weight DOUBLE PRECISION NOT NULL,
+    credits_sum DOUBLE PRECISION NOT NULL,
+    availability_score DOUBLE PRECISION NOT NULL,
+    latency_score DOUBLE PRECISION NOT NULL,
+    version_score DOUBLE PRECISION NOT NULL,
+    credits_score DOUBLE PRECISION NOT NULL,
+    tenure_score DOUBLE PRECISION NOT NULL,
+    score DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_node_reputation_score ON node_reputation(score);
+
+CREATE TABLE IF NOT EXISTS node_events (
+    id BIGSERIAL PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    event_type TEXT NOT NULL,
+    previous TEXT,
+    current TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_node_events_pubkey_timestamp ON node_events(pubkey, timestamp);
+CREATE INDEX IF NOT EXISTS idx_node_events_type_timestamp ON node_events(event_type, timestamp);
+
+CREATE TABLE IF NOT EXISTS prediction_models (
+    id BIGSERIAL PRIMARY KEY,
+    trained_at BIGINT NOT NULL,
+    samples BIGINT NOT NULL,
+    positives BIGINT NOT NULL,
+    model TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    pubkey TEXT,
+    threshold DOUBLE PRECISION,
+    min_version TEXT,
+    duration_secs BIGINT NOT NULL DEFAULT 0,
+    severity TEXT NOT NULL DEFAULT 'warning',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at BIGINT NOT NULL,
+    group_by TEXT NOT NULL DEFAULT 'rule',
+    owner_account_id BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id BIGSERIAL PRIMARY KEY,
+    rule_id BIGINT NOT NULL,
+    subject TEXT NOT NULL,
+    state TEXT NOT NULL,
+    value DOUBLE PRECISION,
+    message TEXT NOT NULL,
+    started_at BIGINT NOT NULL,
+    fired_at BIGINT,
+    resolved_at BIGINT,
+    updated_at BIGINT NOT NULL,
+    group_key TEXT,
+    suppressed_by TEXT,
+    FOREIGN KEY(rule_id) REFERENCES alert_rules(id)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_active ON alerts(rule_id, subject) WHERE state != 'resolved';
+CREATE INDEX IF NOT EXISTS idx_alerts_state ON alerts(state, updated_at);
+
+CREATE TABLE IF NOT EXISTS webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    format TEXT NOT NULL DEFAULT 'json',
+    template TEXT,
+    secret TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL,
+    alert_id BIGINT,
+    alert_state TEXT NOT NULL,
+    attempt BIGINT NOT NULL,
+    status_code BIGINT,
+    error TEXT,
+    delivered BOOLEAN NOT NULL,
+    created_at BIGINT NOT NULL,
+    FOREIGN KEY(webhook_id) REFERENCES webhooks(id)
+);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, created_at);
+
+CREATE TABLE IF NOT EXISTS email_subscriptions (
+    id BIGSERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    pubkey TEXT NOT NULL,
+    digest BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL,
+    confirmed_at BIGINT,
+    confirm_token_hash TEXT,
+    UNIQUE(email, pubkey)
+);
+
+CREATE TABLE IF NOT EXISTS email_queue (
+    id BIGSERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    alert_id BIGINT NOT NULL,
+    rule_name TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    state TEXT NOT NULL,
+    message TEXT NOT NULL,
+    at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS email_log (
+    id BIGSERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    subject_line TEXT NOT NULL,
+    notices BIGINT NOT NULL,
+    error TEXT,
+    sent_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_email_log_email ON email_log(email, sent_at);
+
+CREATE TABLE IF NOT EXISTS silences (
+    id BIGSERIAL PRIMARY KEY,
+    matchers TEXT NOT NULL,
+    starts_at BIGINT NOT NULL,
+    ends_at BIGINT NOT NULL,
+    comment TEXT NOT NULL,
+    created_by TEXT,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_silences_ends_at ON silences(ends_at);
+
+CREATE TABLE IF NOT EXISTS alert_groups (
+    group_key TEXT PRIMARY KEY,
+    rule_id BIGINT NOT NULL,
+    state TEXT NOT NULL,
+    opened_at BIGINT NOT NULL,
+    notified_at BIGINT NOT NULL,
+    resolved_at BIGINT,
+    alerts BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id BIGSERIAL PRIMARY KEY,
+    username TEXT NOT NULL,
+    email TEXT,
+    password_hash TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_accounts_username ON accounts(lower(username));
+
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash TEXT PRIMARY KEY,
+    account_id BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_account ON sessions(account_id);
+
+CREATE TABLE IF NOT EXISTS account_pubkeys (
+    account_id BIGINT NOT NULL,
+    pubkey TEXT NOT NULL,
+    claimed_at BIGINT NOT NULL,
+    verified_at BIGINT,
+    PRIMARY KEY(account_id, pubkey),
+    FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
+CREATE INDEX IF NOT EXISTS idx_account_pubkeys_pubkey ON account_pubkeys(pubkey);
+
+CREATE TABLE IF NOT EXISTS watchlist (
+    account_id BIGINT NOT NULL,
+    pubkey TEXT NOT NULL,
+    label TEXT,
+    added_at BIGINT NOT NULL,
+    PRIMARY KEY(account_id, pubkey),
+    FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS ownership_challenges (
+    account_id BIGINT NOT NULL,
+    pubkey TEXT NOT NULL,
+    message TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY(account_id, pubkey)
+);
+
+CREATE TABLE IF NOT EXISTS node_profiles (
+    pubkey TEXT PRIMARY KEY,
+    account_id BIGINT NOT NULL,
+    display_name TEXT,
+    website TEXT,
+    contact TEXT,
+    contact_public BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    rate_limit_per_min BIGINT,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT,
+    revoked_at BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS seeds (
+    address TEXT PRIMARY KEY,
+    source TEXT NOT NULL,
+    successes BIGINT NOT NULL DEFAULT 0,
+    failures BIGINT NOT NULL DEFAULT 0,
+    consecutive_failures BIGINT NOT NULL DEFAULT 0,
+    latency_ms DOUBLE PRECISION,
+    pod_count BIGINT,
+    last_success_at BIGINT,
+    last_failure_at BIGINT,
+    quarantined_until BIGINT,
+    added_at BIGINT NOT NULL
+);
+"#;
+
+const INHIBIT_RULES: &str = r#"
+CREATE TABLE IF NOT EXISTS inhibit_rules (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    source_matchers TEXT NOT NULL,
+    target_matchers TEXT NOT NULL,
+    equal TEXT NOT NULL DEFAULT '',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at BIGINT NOT NULL
+);
+"#;
+
+/// What `Storage::revoke_owners` deletes, on the caller's transaction.
+async fn revoke_owners(conn: &mut sqlx::PgConnection, owners: &[Account], pubkey: &str) -> Result<(), sqlx::Error> {
+    for owner in owners {
+        let rules = "SELECT id FROM alert_rules WHERE owner_account_id = $1 AND pubkey = $2";
+        for table in ["alerts", "alert_groups"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE rule_id IN ({})", table, rules))
+                .bind(owner.id)
+                .bind(pubkey)
+                .execute(&mut *conn)
+                .await?;
+        }
+        sqlx::query("DELETE FROM alert_rules WHERE owner_account_id = $1 AND pubkey = $2")
+            .bind(owner.id)
+            .bind(pubkey)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Subscribes an owner's own address to a pubkey, confirmed from the start,
+/// or confirms an existing subscription, on the caller's transaction.
+async fn subscribe_owner(conn: &mut sqlx::PgConnection, email: &str, pubkey: &str, digest: bool, created_at: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO email_subscriptions (email, pubkey, digest, created_at, confirmed_at)
+        VALUES ($1, $2, $3, $4, $4)
+        ON CONFLICT(email, pubkey) DO UPDATE SET
+            digest = excluded.digest,
+            confirmed_at = COALESCE(email_subscriptions.confirmed_at, excluded.confirmed_at),
+            confirm_token_hash = NULL
+        "#
+    )
+    .bind(email)
+    .bind(pubkey)
+    .bind(digest)
+    .bind(created_at)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn unsubscribe(conn: &mut sqlx::PgConnection, email: &str, pubkey: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM email_subscriptions WHERE email = $1 AND pubkey = $2")
+        .bind(email)
+        .bind(pubkey)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn open(database_url: &str, timescale: bool) -> Result<PgPool, sqlx::Error> {
+    let settings = &crate::config::get().database;
+    let pool = PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .acquire_timeout(Duration::from_secs(settings.acquire_timeout_secs))
+        .connect(database_url)
+        .await?;
+
+    pool.execute(SCHEMA).await?;
+
+    let has_inhibit_rules: bool = sqlx::query_scalar("SELECT to_regclass('inhibit_rules') IS NOT NULL")
+        .fetch_one(&pool)
+        .await?;
+    pool.execute(INHIBIT_RULES).await?;
+    // Same default rule as the SQLite store seeds; see there.
+    if !has_inhibit_rules {
+        sqlx::query(
+            r#"
+            INSERT INTO inhibit_rules (name, source_matchers, target_matchers, equal, enabled, created_at)
+            VALUES (
+                'Network outage hides per-node offline alerts',
+                '[{"name":"kind","op":"=","value":"network_online_drop"}]',
+                '[{"name":"kind","op":"=","value":"node_offline"}]',
+                '',
+                TRUE,
+                EXTRACT(EPOCH FROM now())::BIGINT
+            )
+            "#
+        ).execute(&pool).await?;
+    }
+
+    if timescale {
+        enable_timescale(&pool).await?;
+    }
+
+    Ok(pool)
+}
+
+/// Partitions the two append-only history tables by day. Safe to run on every
+/// start; existing rows are moved into chunks the first time.
+async fn enable_timescale(pool: &PgPool) -> Result<(), sqlx::Error> {
+    pool.execute("CREATE EXTENSION IF NOT EXISTS timescaledb").await?;
+    for table in ["node_history", "metrics"] {
+        sqlx::query("SELECT create_hypertable($1, 'timestamp', chunk_time_interval => 86400::BIGINT, if_not_exists => TRUE, migrate_data => TRUE)")
+            .bind(table)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn get_all_nodes(&self) -> Result<Vec<NodeRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_node_by_id(&self, pubkey: &str) -> Result<Option<NodeRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes WHERE pubkey = $1")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_cycle(
+        &self,
+        cycle_at: i64,
+        snapshot: &Snapshot,
+        nodes: &[NodeRecord],
+        events: &[NodeEvent],
+        gone: &[String],
+    ) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        let cycle_id: i64 = sqlx::query_scalar(
+            "INSERT INTO metrics (timestamp, total_nodes, online_nodes, total_storage, total_committed) VALUES ($1, $2, $3, $4, $5) RETURNING id"
+        )
+        .bind(cycle_at)
+        .bind(snapshot.total_nodes)
+        .bind(snapshot.online_nodes)
+        .bind(snapshot.total_storage)
+        .bind(snapshot.total_committed)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for batch in latest_per_pubkey(nodes).chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Postgres>::new(NODE_COLUMNS);
+            query.push_values(batch, |mut row, node| {
+                row.push_bind(&node.pubkey)
+                    .push_bind(&node.ip)
+                    .push_bind(&node.version)
+                    .push_bind(&node.status)
+                    .push_bind(node.last_seen)
+                    .push_bind(node.storage_used)
+                    .push_bind(node.storage_committed)
+                    .push_bind(node.storage_usage_percent)
+                    .push_bind(node.credits)
+                    .push_bind(node.latency_ms)
+                    .push_bind(&node.country)
+                    .push_bind(&node.city)
+                    .push_bind(node.lat)
+                    .push_bind(node.lon)
+                    .push_bind(node.uptime);
+            });
+            query.push(NODE_UPSERT);
+            query.build().execute(&mut *tx).await?;
+        }
+
+        for batch in nodes.chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "INSERT INTO node_history (cycle_id, pubkey, timestamp, latency_ms, status, storage_used, storage_committed, uptime) "
+            );
+            query.push_values(batch, |mut row, node| {
+                row.push_bind(cycle_id)
+                    .push_bind(&node.pubkey)
+                    .push_bind(cycle_at)
+                    .push_bind(node.latency_ms)
+                    .push_bind(&node.status)
+                    .push_bind(node.storage_used)
+                    .push_bind(node.storage_committed)
+                    .push_bind(node.uptime);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        if !gone.is_empty() {
+            sqlx::query("UPDATE nodes SET status = 'offline' WHERE pubkey = ANY($1)")
+                .bind(gone)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for batch in events.chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Postgres>::new("INSERT INTO node_events (pubkey, timestamp, event_type, previous, current) ");
+            query.push_values(batch, |mut row, event| {
+                row.push_bind(&event.pubkey)
+                    .push_bind(event.timestamp)
+                    .push_bind(&event.event_type)
+                    .push_bind(&event.previous)
+                    .push_bind(&event.current);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(cycle_id)
+    }
+
+    async fn get_history(&self, limit: i64) -> Result<Vec<(i64, i64, i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT timestamp, total_nodes, online_nodes, total_storage FROM metrics ORDER BY timestamp DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (
+            r.get(0), r.get(1), r.get(2), r.get(3)
+        )).collect())
+    }
+
+    async fn get_node_history(&self, pubkey: &str, limit: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = $1 ORDER BY timestamp DESC LIMIT $2"
+        )
+        .bind(pubkey)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_node_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = $1 AND timestamp >= $2 ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_node_history_since(&self, since: i64) -> Result<Vec<(String, NodeHistoryRecord)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            "SELECT pubkey, timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE timestamp >= $1 ORDER BY pubkey, timestamp ASC"
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                let record = NodeHistoryRecord {
+                    timestamp: r.get(1),
+                    latency_ms: r.get(2),
+                    status: r.get(3),
+                    storage_used: r.get(4),
+                    storage_committed: r.get(5),
+                    uptime: r.get(6),
+                };
+                (r.get(0), record)
+            })
+            .collect())
+    }
+
+    fn stream_nodes(&self, from: i64, to: i64) -> BoxStream<'_, Result<NodeRecord, sqlx::Error>> {
+        sqlx::query_as::<_, NodeRecord>(
+            "SELECT * FROM nodes WHERE COALESCE(last_seen, 0) >= $1 AND COALESCE(last_seen, 0) < $2 ORDER BY pubkey"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    fn stream_metrics(&self, from: i64, to: i64) -> BoxStream<'_, Result<MetricsRecord, sqlx::Error>> {
+        sqlx::query_as::<_, MetricsRecord>(
+            "SELECT id AS cycle_id, timestamp, total_nodes, online_nodes, total_storage, total_committed FROM metrics \
+             WHERE timestamp >= $1 AND timestamp < $2 ORDER BY timestamp ASC"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    fn stream_node_history(&self, pubkey: &str, from: i64, to: i64) -> BoxStream<'_, Result<NodeHistoryRecord, sqlx::Error>> {
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history \
+             WHERE pubkey = $1 AND timestamp >= $2 AND timestamp < $3 ORDER BY timestamp ASC"
+        )
+        .bind(pubkey.to_string())
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    async fn backup(&self, _path: &Path) -> Result<(), sqlx::Error> {
+        Err(sqlx::Error::Configuration("PostgreSQL databases are backed up with pg_dump".into()))
+    }
+
+    async fn prune(&self, before: i64) -> Result<Pruned, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut pruned = Pruned::default();
+        for (table, column, count) in [
+            ("node_history", "timestamp", &mut pruned.node_history),
+            ("metrics", "timestamp", &mut pruned.metrics),
+            ("observer_cycles", "started_at", &mut pruned.observer_cycles),
+            ("credits_history", "timestamp", &mut pruned.credits_history),
+        ] {
+            let sql = format!("DELETE FROM {} WHERE {} < $1", table, column);
+            *count = sqlx::query(&sql).bind(before).execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(pruned)
+    }
+
+    async fn save_observer_cycle(
+        &self,
+        cycle_id: Option<i64>,
+        started_at: i64,
+        duration_ms: i64,
+        outcome: &str,
+        seed: Option<&str>,
+        pods_received: i64,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO observer_cycles (cycle_id, started_at, duration_ms, outcome, seed, pods_received, error) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(cycle_id)
+        .bind(started_at)
+        .bind(duration_ms)
+        .bind(outcome)
+        .bind(seed)
+        .bind(pods_received)
+        .bind(error)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_observer_cycles(&self, limit: i64) -> Result<Vec<ObserverCycle>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ObserverCycle>("SELECT * FROM observer_cycles ORDER BY started_at DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_cycles_since(&self, since: i64) -> Result<Vec<(i64, bool)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT started_at, outcome FROM observer_cycles WHERE started_at >= $1 ORDER BY started_at ASC")
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get::<String, _>(1) == "ok")).collect())
+    }
+
+    async fn save_credits(&self, timestamp: i64, credits: &[(String, i64)]) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        for (pubkey, value) in credits {
+            sqlx::query("INSERT INTO credits_history (pubkey, timestamp, credits) VALUES ($1, $2, $3)")
+                .bind(pubkey)
+                .bind(timestamp)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("UPDATE nodes SET credits = $1 WHERE pubkey = $2")
+                .bind(value)
+                .bind(pubkey)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn get_latest_credits(&self) -> Result<Option<(i64, Vec<(String, i64)>)>, sqlx::Error> {
+        let pool = &self.pool;
+        let latest: Option<i64> = sqlx::query_scalar("SELECT MAX(timestamp) FROM credits_history")
+            .fetch_one(pool)
+            .await?;
+
+        let Some(timestamp) = latest else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query("SELECT pubkey, credits FROM credits_history WHERE timestamp = $1")
+            .bind(timestamp)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(Some((timestamp, rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())))
+    }
+
+    async fn get_node_credits_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT timestamp, credits FROM credits_history WHERE pubkey = $1 AND timestamp >= $2 ORDER BY timestamp ASC")
+            .bind(pubkey)
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn get_credits_history_since(&self, since: i64) -> Result<Vec<(String, i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT pubkey, timestamp, credits FROM credits_history WHERE timestamp >= $1 ORDER BY pubkey, timestamp ASC")
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect())
+    }
+
+    async fn get_node_history_before(&self, pubkey: &str, before: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = $1 AND timestamp < $2 ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(before)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_reputations(&self) -> Result<Vec<ReputationRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ReputationRecord>("SELECT * FROM node_reputation")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_reputations(&self, records: &[&ReputationRecord]) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        for r in records {
+            sqlx::query(
+                r#"
+                INSERT INTO node_reputation (
+                    pubkey, first_seen, updated_at, weight, up_weight, latency_weight, latency_sum, latency_sq_sum,
+                    version_weight, version_sum, credits_weight, credits_sum, availability_score, latency_score,
+                    version_score, credits_score, tenure_score, score
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+                ON CONFLICT(pubkey) DO UPDATE SET
+                    first_seen = excluded.first_seen,
+                    updated_at = excluded.updated_at,
+                    weight = excluded.weight,
+                    up_weight = excluded.up_weight,
+                    latency_weight = excluded.latency_weight,
+                    latency_sum = excluded.latency_sum,
+                    latency_sq_sum = excluded.latency_sq_sum,
+                    version_weight = excluded.version_weight,
+                    version_sum = excluded.version_sum,
+                    credits_weight = excluded.credits_weight,
+                    credits_sum = excluded.credits_sum,
+                    availability_score = excluded.availability_score,
+                    latency_score = excluded.latency_score,
+                    version_score = excluded.version_score,
+                    credits_score = excluded.credits_score,
+                    tenure_score = excluded.tenure_score,
+                    score = excluded.score
+                "#
+            )
+            .bind(&r.pubkey)
+            .bind(r.first_seen)
+            .bind(r.updated_at)
+            .bind(r.weight)
+            .bind(r.up_weight)
+            .bind(r.latency_weight)
+            .bind(r.latency_sum)
+            .bind(r.latency_sq_sum)
+            .bind(r.version_weight)
+            .bind(r.version_sum)
+            .bind(r.credits_weight)
+            .bind(r.credits_sum)
+            .bind(r.availability_score)
+            .bind(r.latency_score)
+            .bind(r.version_score)
+            .bind(r.credits_score)
+            .bind(r.tenure_score)
+            .bind(r.score)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn get_reputation_page(&self, limit: i64, offset: i64) -> Result<(i64, Vec<ReputationRecord>), sqlx::Error> {
+        let pool = &self.pool;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM node_reputation")
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query_as::<_, ReputationRecord>(
+            "SELECT * FROM node_reputation ORDER BY score DESC, pubkey ASC LIMIT $1 OFFSET $2"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok((total, rows))
+    }
+
+    async fn get_node_events_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeEvent>(
+            "SELECT pubkey, timestamp, event_type, previous, current FROM node_events e WHERE pubkey = $1 AND (timestamp >= $2 \
+             OR (event_type IN ('first_seen', 'version_changed') AND timestamp = (SELECT MAX(timestamp) FROM node_events \
+             WHERE pubkey = e.pubkey AND event_type IN ('first_seen', 'version_changed') AND timestamp < $2))) ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_node_events_since(&self, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeEvent>(
+            "SELECT pubkey, timestamp, event_type, previous, current FROM node_events e WHERE timestamp >= $1 \
+             OR (event_type IN ('first_seen', 'version_changed') AND timestamp = (SELECT MAX(timestamp) FROM node_events \
+             WHERE pubkey = e.pubkey AND event_type IN ('first_seen', 'version_changed') AND timestamp < $1)) ORDER BY pubkey, timestamp ASC"
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_version_first_seen(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            "SELECT current, MIN(timestamp) FROM node_events WHERE event_type IN ('first_seen', 'version_changed') AND current IS NOT NULL GROUP BY current"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn save_prediction_model(&self, trained_at: i64, samples: i64, positives: i64, model: &str) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("INSERT INTO prediction_models (trained_at, samples, positives, model) VALUES ($1, $2, $3, $4)")
+            .bind(trained_at)
+            .bind(samples)
+            .bind(positives)
+            .bind(model)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_latest_prediction_model(&self) -> Result<Option<String>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT model FROM prediction_models ORDER BY trained_at DESC LIMIT 1")
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_hourly_node_storage_since(&self, since: i64) -> Result<Vec<(String, i64, f64, f64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            r#"
+            SELECT pubkey, (timestamp / 3600) * 3600 AS hour, AVG(storage_used)::DOUBLE PRECISION, AVG(storage_committed)::DOUBLE PRECISION
+            FROM node_history
+            WHERE timestamp >= $1 AND storage_used IS NOT NULL AND storage_committed IS NOT NULL
+            GROUP BY pubkey, hour
+            ORDER BY pubkey, hour
+            "#
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2), r.get(3))).collect())
+    }
+
+    async fn get_hourly_network_storage_since(&self, since: i64) -> Result<Vec<(i64, f64, Option<f64>)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            r#"
+            SELECT (timestamp / 3600) * 3600 AS hour, AVG(total_storage)::DOUBLE PRECISION, AVG(total_committed)::DOUBLE PRECISION
+            FROM metrics
+            WHERE timestamp >= $1
+            GROUP BY hour
+            ORDER BY hour
+            "#
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect())
+    }
+
+    async fn get_alert_rules(&self) -> Result<Vec<AlertRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertRule>("SELECT * FROM alert_rules ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alert_rule(&self, id: i64) -> Result<Option<AlertRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertRule>("SELECT * FROM alert_rules WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_alert_rule(&self, rule: &AlertRule) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO alert_rules (id, name, kind, pubkey, threshold, min_version, duration_secs, severity, enabled, created_at, group_by, owner_account_id)
+            VALUES (COALESCE(NULLIF($1, 0), nextval(pg_get_serial_sequence('alert_rules', 'id'))), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                kind = excluded.kind,
+                pubkey = excluded.pubkey,
+                threshold = excluded.threshold,
+                min_version = excluded.min_version,
+                duration_secs = excluded.duration_secs,
+                severity = excluded.severity,
+                enabled = excluded.enabled,
+                group_by = excluded.group_by
+            RETURNING id
+            "#
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.kind)
+        .bind(&rule.pubkey)
+        .bind(rule.threshold)
+        .bind(&rule.min_version)
+        .bind(rule.duration_secs)
+        .bind(&rule.severity)
+        .bind(rule.enabled)
+        .bind(rule.created_at)
+        .bind(&rule.group_by)
+        .bind(rule.owner_account_id)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_alert_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM alerts WHERE rule_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM alert_groups WHERE rule_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM alert_rules WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn get_active_alerts_for_rule(&self, rule_id: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Alert>("SELECT * FROM alerts WHERE rule_id = $1 AND state != 'resolved'")
+            .bind(rule_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alerts(&self, state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        match state {
+            Some(state) => sqlx::query_as::<_, Alert>(
+                "SELECT * FROM alerts WHERE state = $1 AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY updated_at DESC LIMIT $2"
+            )
+                .bind(state)
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, Alert>(
+                "SELECT * FROM alerts WHERE state != 'resolved' AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY updated_at DESC LIMIT $1"
+            )
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+        }
+    }
+
+    async fn insert_alert(&self, alert: &Alert) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar(
+            "INSERT INTO alerts (rule_id, subject, state, value, message, started_at, fired_at, resolved_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id"
+        )
+        .bind(alert.rule_id)
+        .bind(&alert.subject)
+        .bind(&alert.state)
+        .bind(alert.value)
+        .bind(&alert.message)
+        .bind(alert.started_at)
+        .bind(alert.fired_at)
+        .bind(alert.resolved_at)
+        .bind(alert.updated_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn update_alert(&self, alert: &Alert) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "UPDATE alerts SET state = $1, value = $2, message = $3, fired_at = $4, resolved_at = $5, updated_at = $6 WHERE id = $7"
+        )
+        .bind(&alert.state)
+        .bind(alert.value)
+        .bind(&alert.message)
+        .bind(alert.fired_at)
+        .bind(alert.resolved_at)
+        .bind(alert.updated_at)
+        .bind(alert.id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_alert(&self, id: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM alerts WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_peak_online_since(&self, since: i64) -> Result<Option<i64>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT MAX(online_nodes) FROM metrics WHERE timestamp >= $1")
+            .bind(since)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_webhooks(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_webhook(&self, id: i64) -> Result<Option<Webhook>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_webhook(&self, hook: &Webhook) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO webhooks (id, name, url, format, template, secret, enabled, created_at)
+            VALUES (COALESCE(NULLIF($1, 0), nextval(pg_get_serial_sequence('webhooks', 'id'))), $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                url = excluded.url,
+                format = excluded.format,
+                template = excluded.template,
+                secret = excluded.secret,
+                enabled = excluded.enabled
+            RETURNING id
+            "#
+        )
+        .bind(hook.id)
+        .bind(&hook.name)
+        .bind(&hook.url)
+        .bind(&hook.format)
+        .bind(&hook.template)
+        .bind(&hook.secret)
+        .bind(hook.enabled)
+        .bind(hook.created_at)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn save_webhook_delivery(
+        &self,
+        webhook_id: i64,
+        alert_id: Option<i64>,
+        alert_state: &str,
+        attempt: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+        delivered: bool,
+        created_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO webhook_deliveries (webhook_id, alert_id, alert_state, attempt, status_code, error, delivered, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        )
+        .bind(webhook_id)
+        .bind(alert_id)
+        .bind(alert_state)
+        .bind(attempt)
+        .bind(status_code)
+        .bind(error)
+        .bind(delivered)
+        .bind(created_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY id DESC LIMIT $2"
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_email_subscriptions(&self) -> Result<Vec<EmailSubscription>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailSubscription>("SELECT * FROM email_subscriptions ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_pending_email_subscription(
+        &self,
+        email: &str,
+        pubkey: &str,
+        digest: bool,
+        token_hash: &str,
+        created_at: i64,
+    ) -> Result<(i64, bool), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as(
+            r#"
+            INSERT INTO email_subscriptions (email, pubkey, digest, created_at, confirm_token_hash)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT(email, pubkey) DO UPDATE SET
+                digest = excluded.digest,
+                confirm_token_hash = CASE WHEN email_subscriptions.confirmed_at IS NULL
+                    THEN excluded.confirm_token_hash ELSE NULL END
+            RETURNING id, confirmed_at IS NOT NULL
+            "#
+        )
+        .bind(email)
+        .bind(pubkey)
+        .bind(digest)
+        .bind(created_at)
+        .bind(token_hash)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn confirm_email_subscription(&self, token_hash: &str, confirmed_at: i64) -> Result<Option<EmailSubscription>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailSubscription>(
+            r#"
+            UPDATE email_subscriptions SET confirmed_at = $1, confirm_token_hash = NULL
+            WHERE confirm_token_hash = $2 AND confirmed_at IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(confirmed_at)
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_email_subscription(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM email_subscriptions WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn queue_email(&self, item: &QueuedEmail) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO email_queue (email, alert_id, rule_name, severity, subject, state, message, at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        )
+        .bind(&item.email)
+        .bind(item.alert_id)
+        .bind(&item.rule_name)
+        .bind(&item.severity)
+        .bind(&item.subject)
+        .bind(&item.state)
+        .bind(&item.message)
+        .bind(item.at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn take_email_queue(&self) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        let pool = &self.pool;
+        // Deleting exactly the rows returned leaves anything queued meanwhile
+        // for the next digest; RETURNING has no order, so sort here.
+        let rows = sqlx::query("DELETE FROM email_queue RETURNING id, email, alert_id, rule_name, severity, subject, state, message, at")
+            .fetch_all(pool)
+            .await?;
+        let mut items = rows
+            .iter()
+            .map(|r| Ok((r.try_get::<i64, _>("id")?, <QueuedEmail as sqlx::FromRow<_>>::from_row(r)?)))
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        items.sort_by(|a, b| (&a.1.email, a.1.at, a.0).cmp(&(&b.1.email, b.1.at, b.0)));
+        Ok(items.into_iter().map(|(_, item)| item).collect())
+    }
+
+    async fn save_email_log(&self, email: &str, kind: &str, subject_line: &str, notices: i64, error: Option<&str>, sent_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO email_log (email, kind, subject_line, notices, error, sent_at) VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(email)
+        .bind(kind)
+        .bind(subject_line)
+        .bind(notices)
+        .bind(error)
+        .bind(sent_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn count_emails_sent_since(&self, email: &str, since: i64) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT COUNT(*) FROM email_log WHERE email = $1 AND sent_at >= $2 AND error IS NULL")
+            .bind(email)
+            .bind(since)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_email_log(&self, limit: i64) -> Result<Vec<EmailLogEntry>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailLogEntry>("SELECT * FROM email_log ORDER BY id DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alert(&self, id: i64) -> Result<Option<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Alert>("SELECT * FROM alerts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn set_alert_routing(&self, id: i64, group_key: Option<&str>, suppressed_by: Option<&str>) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("UPDATE alerts SET group_key = $1, suppressed_by = $2 WHERE id = $3")
+            .bind(group_key)
+            .bind(suppressed_by)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_firing_alert_labels(&self) -> Result<Vec<FiringAlertLabels>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, FiringAlertLabels>(
+            r#"
+            SELECT a.id, a.rule_id, r.name AS rule_name, r.kind, r.severity, a.subject
+            FROM alerts a
+            JOIN alert_rules r ON r.id = a.rule_id
+            WHERE a.state = 'firing'
+            "#
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn count_notified_firing_in_group(&self, group_key: &str) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT COUNT(*) FROM alerts WHERE group_key = $1 AND state = 'firing' AND suppressed_by IS NULL")
+            .bind(group_key)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_silences(&self, include_expired: bool, now: i64) -> Result<Vec<Silence>, sqlx::Error> {
+        let pool = &self.pool;
+        let min_end = if include_expired { i64::MIN } else { now };
+        sqlx::query_as::<_, Silence>("SELECT * FROM silences WHERE ends_at > $1 ORDER BY id")
+            .bind(min_end)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_silence(&self, id: i64) -> Result<Option<Silence>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Silence>("SELECT * FROM silences WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn insert_silence(&self, silence: &Silence) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar(
+            "INSERT INTO silences (matchers, starts_at, ends_at, comment, created_by, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"
+        )
+        .bind(&silence.matchers)
+        .bind(silence.starts_at)
+        .bind(silence.ends_at)
+        .bind(&silence.comment)
+        .bind(&silence.created_by)
+        .bind(silence.created_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn expire_silence(&self, id: i64, now: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let updated = sqlx::query("UPDATE silences SET ends_at = LEAST(ends_at, $1) WHERE id = $2")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn get_inhibit_rules(&self) -> Result<Vec<InhibitRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, InhibitRule>("SELECT * FROM inhibit_rules ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_inhibit_rule(&self, id: i64) -> Result<Option<InhibitRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, InhibitRule>("SELECT * FROM inhibit_rules WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_inhibit_rule(&self, rule: &InhibitRule) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO inhibit_rules (id, name, source_matchers, target_matchers, equal, enabled, created_at)
+            VALUES (COALESCE(NULLIF($1, 0), nextval(pg_get_serial_sequence('inhibit_rules', 'id'))), $2, $3, $4, $5, $6, $7)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                source_matchers = excluded.source_matchers,
+                target_matchers = excluded.target_matchers,
+                equal = excluded.equal,
+                enabled = excluded.enabled
+            RETURNING id
+            "#
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.source_matchers)
+        .bind(&rule.target_matchers)
+        .bind(&rule.equal)
+        .bind(rule.enabled)
+        .bind(rule.created_at)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_inhibit_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM inhibit_rules WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn get_alert_group(&self, group_key: &str) -> Result<Option<AlertGroup>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertGroup>("SELECT * FROM alert_groups WHERE group_key = $1")
+            .bind(group_key)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_alert_group(&self, group: &AlertGroup) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO alert_groups (group_key, rule_id, state, opened_at, notified_at, resolved_at, alerts) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT(group_key) DO UPDATE SET rule_id = excluded.rule_id, state = excluded.state, opened_at = excluded.opened_at, \
+             notified_at = excluded.notified_at, resolved_at = excluded.resolved_at, alerts = excluded.alerts"
+        )
+        .bind(&group.group_key)
+        .bind(group.rule_id)
+        .bind(&group.state)
+        .bind(group.opened_at)
+        .bind(group.notified_at)
+        .bind(group.resolved_at)
+        .bind(group.alerts)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_alert_groups(&self, state: Option<&str>, limit: i64) -> Result<Vec<AlertGroup>, sqlx::Error> {
+        let pool = &self.pool;
+        match state {
+            Some(state) => sqlx::query_as::<_, AlertGroup>(
+                "SELECT * FROM alert_groups WHERE state = $1 AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY opened_at DESC LIMIT $2"
+            )
+                .bind(state)
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, AlertGroup>(
+                "SELECT * FROM alert_groups WHERE rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY opened_at DESC LIMIT $1"
+            )
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+        }
+    }
+
+    async fn insert_account(&self, username: &str, email: Option<&str>, password_hash: &str, created_at: i64) -> Result<Option<i64>, sqlx::Error> {
+        let pool = &self.pool;
+        let id: Option<i64> = sqlx::query_scalar(
+            "INSERT INTO accounts (username, email, password_hash, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT(lower(username)) DO NOTHING RETURNING id"
+        )
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(created_at)
+        .fetch_optional(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn get_account_by_username(&self, username: &str) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE lower(username) = lower($1)")
+            .bind(username)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn update_account_email(&self, account_id: i64, email: Option<&str>, now: i64) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let old: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        let pubkeys: Vec<String> = sqlx::query_scalar("SELECT pubkey FROM account_pubkeys WHERE account_id = $1 AND verified_at IS NOT NULL")
+            .bind(account_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for pubkey in &pubkeys {
+            if let Some(old) = &old {
+                unsubscribe(&mut tx, old, pubkey).await?;
+            }
+            if let Some(email) = email {
+                subscribe_owner(&mut tx, email, pubkey, false, now).await?;
+            }
+        }
+        sqlx::query("UPDATE accounts SET email = $1 WHERE id = $2")
+            .bind(email)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn insert_session(&self, token_hash: &str, account_id: i64, created_at: i64, expires_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("INSERT INTO sessions (token_hash, account_id, created_at, expires_at) VALUES ($1, $2, $3, $4)")
+            .bind(token_hash)
+            .bind(account_id)
+            .bind(created_at)
+            .bind(expires_at)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_session_account(&self, token_hash: &str, now: i64) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>(
+            r#"
+            SELECT a.* FROM sessions s
+            JOIN accounts a ON a.id = s.account_id
+            WHERE s.token_hash = $1 AND s.expires_at > $2
+            "#
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_session(&self, token_hash: &str, now: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM sessions WHERE token_hash = $1 OR expires_at <= $2")
+            .bind(token_hash)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_claimed_pubkeys(&self, account_id: i64) -> Result<Vec<ClaimedPubkey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ClaimedPubkey>("SELECT pubkey, claimed_at, verified_at FROM account_pubkeys WHERE account_id = $1 ORDER BY claimed_at")
+            .bind(account_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_verified_claim(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Vec<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        let lapsed = sqlx::query_as::<_, Account>(
+            r#"
+            SELECT accounts.* FROM accounts
+            JOIN account_pubkeys ON account_pubkeys.account_id = accounts.id
+            WHERE account_pubkeys.pubkey = $1 AND account_pubkeys.account_id != $2 AND account_pubkeys.verified_at IS NOT NULL
+            "#
+        )
+        .bind(pubkey)
+        .bind(account_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE account_pubkeys SET verified_at = NULL WHERE pubkey = $1 AND account_id != $2")
+            .bind(pubkey)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        revoke_owners(&mut tx, &lapsed, pubkey).await?;
+        for email in lapsed.iter().filter_map(|a| a.email.as_deref()) {
+            unsubscribe(&mut tx, email, pubkey).await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO account_pubkeys (account_id, pubkey, claimed_at, verified_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT(account_id, pubkey) DO UPDATE SET verified_at = excluded.verified_at
+            "#
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM ownership_challenges WHERE account_id = $1 AND pubkey = $2")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(&mut *tx)
+            .await?;
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        if let Some(email) = &email {
+            subscribe_owner(&mut tx, email, pubkey, false, now).await?;
+        }
+        tx.commit().await?;
+        Ok(lapsed)
+    }
+
+    async fn revoke_owners(&self, owners: &[Account], pubkey: &str) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        revoke_owners(&mut tx, owners, pubkey).await?;
+        tx.commit().await
+    }
+
+    async fn is_verified_owner(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM account_pubkeys WHERE account_id = $1 AND pubkey = $2 AND verified_at IS NOT NULL"
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .fetch_one(pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn save_ownership_challenge(&self, account_id: i64, pubkey: &str, message: &str, expires_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO ownership_challenges (account_id, pubkey, message, expires_at) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT(account_id, pubkey) DO UPDATE SET message = excluded.message, expires_at = excluded.expires_at"
+        )
+            .bind(account_id)
+            .bind(pubkey)
+            .bind(message)
+            .bind(expires_at)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_ownership_challenge(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Option<String>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT message FROM ownership_challenges WHERE account_id = $1 AND pubkey = $2 AND expires_at > $3")
+            .bind(account_id)
+            .bind(pubkey)
+            .bind(now)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_account(&self, id: i64) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_node_profile(&self, pubkey: &str) -> Result<Option<NodeProfile>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeProfile>("SELECT * FROM node_profiles WHERE pubkey = $1")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_node_profile(&self, profile: &NodeProfile) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO node_profiles (pubkey, account_id, display_name, website, contact, contact_public, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT(pubkey) DO UPDATE SET account_id = excluded.account_id, display_name = excluded.display_name, website = excluded.website, \
+             contact = excluded.contact, contact_public = excluded.contact_public, updated_at = excluded.updated_at"
+        )
+        .bind(&profile.pubkey)
+        .bind(profile.account_id)
+        .bind(&profile.display_name)
+        .bind(&profile.website)
+        .bind(&profile.contact)
+        .bind(profile.contact_public)
+        .bind(profile.updated_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release_pubkey(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM account_pubkeys WHERE account_id = $1 AND pubkey = $2")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = $1")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        if let (true, Some(email)) = (deleted > 0, &email) {
+            unsubscribe(&mut tx, email, pubkey).await?;
+        }
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn get_watchlist(&self, account_id: i64) -> Result<Vec<WatchlistEntry>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, WatchlistEntry>("SELECT pubkey, label, added_at FROM watchlist WHERE account_id = $1 ORDER BY added_at")
+            .bind(account_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_watchlist_entry(&self, account_id: i64, pubkey: &str, label: Option<&str>, added_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            r#"
+            INSERT INTO watchlist (account_id, pubkey, label, added_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT(account_id, pubkey) DO UPDATE SET label = excluded.label
+            "#
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .bind(label)
+        .bind(added_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_watchlist_entry(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM watchlist WHERE account_id = $1 AND pubkey = $2")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn get_alerts_for_subjects(&self, account_id: i64, subjects: &[String], state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        if subjects.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pool = &self.pool;
+        sqlx::query_as::<_, Alert>(
+            "SELECT * FROM alerts WHERE subject = ANY($1) AND ($2::TEXT IS NULL AND state != 'resolved' OR state = $2) \
+             AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL OR owner_account_id = $3) \
+             ORDER BY updated_at DESC LIMIT $4"
+        )
+        .bind(subjects)
+        .bind(state)
+        .bind(account_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, prefix, scopes, rate_limit_per_min, created_at, last_used_at, revoked_at FROM api_keys ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, prefix, scopes, rate_limit_per_min, created_at, last_used_at, revoked_at FROM api_keys WHERE key_hash = $1"
+        )
+        .bind(key_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn insert_api_key(&self, key: &ApiKey, key_hash: &str) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar(
+            "INSERT INTO api_keys (name, prefix, key_hash, scopes, rate_limit_per_min, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"
+        )
+        .bind(&key.name)
+        .bind(&key.prefix)
+        .bind(key_hash)
+        .bind(&key.scopes)
+        .bind(key.rate_limit_per_min)
+        .bind(key.created_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn touch_api_key(&self, id: i64, now: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("UPDATE api_keys SET last_used_at = $1 WHERE id = $2")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, id: i64, now: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let result = sqlx::query("UPDATE api_keys SET revoked_at = $1 WHERE id = $2 AND revoked_at IS NULL")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_seeds(&self) -> Result<Vec<Seed>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Seed>("SELECT * FROM seeds ORDER BY added_at, address")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_seed(&self, seed: &Seed) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            r#"
+            INSERT INTO seeds
+                (address, source, successes, failures, consecutive_failures, latency_ms, pod_count,
+                 last_success_at, last_failure_at, quarantined_until, added_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT(address) DO UPDATE SET
+                source = excluded.source,
+                successes = excluded.successes,
+                failures = excluded.failures,
+                consecutive_failures = excluded.consecutive_failures,
+                latency_ms = excluded.latency_ms,
+                pod_count = excluded.pod_count,
+                last_success_at = excluded.last_success_at,
+                last_failure_at = excluded.last_failure_at,
+                quarantined_until = excluded.quarantined_until,
+                added_at = excluded.added_at
+            "#
+        )
+        .bind(&seed.address)
+        .bind(&seed.source)
+        .bind(seed.successes)
+        .bind(seed.failures)
+        .bind(seed.consecutive_failures)
+        .bind(seed.latency_ms)
+        .bind(seed.pod_count)
+        .bind(seed.last_success_at)
+        .bind(seed.last_failure_at)
+        .bind(seed.quarantined_until)
+        .bind(seed.added_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_seed(&self, address: &str) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM seeds WHERE address = $1")
+            .bind(address)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,2081 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteLockingMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::sqlite::SqliteConnection;
+use sqlx::{Connection, QueryBuilder, Row, Sqlite};
+
+use super::{latest_per_pubkey, MetricsRecord, Pruned, Snapshot, NODE_BATCH, NODE_COLUMNS, NODE_UPSERT};
+use super::{
+    Account, Alert, AlertGroup, AlertRule, ApiKey, ClaimedPubkey, EmailLogEntry, EmailSubscription,
+    FiringAlertLabels, InhibitRule, NodeEvent, NodeHistoryRecord, NodeProfile, NodeRecord,
+    ObserverCycle, QueuedEmail, ReputationRecord, Seed, Silence, WatchlistEntry, Webhook,
+    WebhookDelivery, Storage,
+};
+
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn open(database_url: &str) -> Result<Self, sqlx::Error> {
+        Ok(SqliteStorage { pool: open(database_url).await? })
+    }
+}
+
+async fn open(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let settings = &crate::config::get().database;
+    // WAL lets API reads carry on while a refresh cycle writes, and makes
+    // NORMAL sync safe, so commits don't each wait on an fsync.
+    let options = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(Duration::from_millis(settings.busy_timeout_ms))
+        .pragma("temp_store", "memory")
+        .pragma("cache_size", "-16000");
+    let pool = SqlitePoolOptions::new()
+        .max_connections(settings.max_connections)
+        .acquire_timeout(Duration::from_secs(settings.acquire_timeout_secs))
+        .connect_with(options)
+        .await?;
+
+    // Create tables
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS nodes (
+            pubkey TEXT PRIMARY KEY,
+            ip TEXT NOT NULL,
+            version TEXT,
+            status TEXT,
+            last_seen INTEGER,
+            storage_used INTEGER,
+            storage_committed INTEGER,
+            storage_usage_percent REAL,
+            credits INTEGER,
+            latency_ms INTEGER,
+            country TEXT,
+            city TEXT,
+            lat REAL,
+            lon REAL,
+            uptime INTEGER
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS metrics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            total_nodes INTEGER,
+            online_nodes INTEGER,
+            total_storage INTEGER,
+            total_committed INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_metrics_timestamp ON metrics(timestamp);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS node_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cycle_id INTEGER,
+            pubkey TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            latency_ms INTEGER,
+            status TEXT,
+            storage_used INTEGER,
+            storage_committed INTEGER,
+            uptime INTEGER,
+            FOREIGN KEY(pubkey) REFERENCES nodes(pubkey)
+        );
+        CREATE INDEX IF NOT EXISTS idx_node_history_pubkey_timestamp ON node_history(pubkey, timestamp);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS observer_cycles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cycle_id INTEGER,
+            started_at INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            outcome TEXT NOT NULL,
+            seed TEXT,
+            pods_received INTEGER NOT NULL DEFAULT 0,
+            error TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_observer_cycles_started_at ON observer_cycles(started_at);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS credits_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pubkey TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            credits INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_credits_history_pubkey_timestamp ON credits_history(pubkey, timestamp);
+        CREATE INDEX IF NOT EXISTS idx_credits_history_timestamp ON credits_history(timestamp);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS node_reputation (
+            pubkey TEXT PRIMARY KEY,
+            first_seen INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            weight REAL NOT NULL,
+            up_weight REAL NOT NULL,
+            latency_weight REAL NOT NULL,
+            latency_sum REAL NOT NULL,
+            latency_sq_sum REAL NOT NULL,
+            version_weight REAL NOT NULL,
+            version_sum REAL NOT NULL,
+            credits_weight REAL NOT NULL,
+            credits_sum REAL NOT NULL,
+            availability_score REAL NOT NULL,
+            latency_score REAL NOT NULL,
+            version_score REAL NOT NULL,
+            credits_score REAL NOT NULL,
+            tenure_score REAL NOT NULL,
+            score REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_node_reputation_score ON node_reputation(score);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS node_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pubkey TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            previous TEXT,
+            current TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_node_events_pubkey_timestamp ON node_events(pubkey, timestamp);
+        CREATE INDEX IF NOT EXISTS idx_node_events_type_timestamp ON node_events(event_type, timestamp);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS prediction_models (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            trained_at INTEGER NOT NULL,
+            samples INTEGER NOT NULL,
+            positives INTEGER NOT NULL,
+            model TEXT NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alert_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            pubkey TEXT,
+            threshold REAL,
+            min_version TEXT,
+            duration_secs INTEGER NOT NULL DEFAULT 0,
+            severity TEXT NOT NULL DEFAULT 'warning',
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL,
+            group_by TEXT NOT NULL DEFAULT 'rule',
+            owner_account_id INTEGER
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alerts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            rule_id INTEGER NOT NULL,
+            subject TEXT NOT NULL,
+            state TEXT NOT NULL,
+            value REAL,
+            message TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            fired_at INTEGER,
+            resolved_at INTEGER,
+            updated_at INTEGER NOT NULL,
+            group_key TEXT,
+            suppressed_by TEXT,
+            FOREIGN KEY(rule_id) REFERENCES alert_rules(id)
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_active ON alerts(rule_id, subject) WHERE state != 'resolved';
+        CREATE INDEX IF NOT EXISTS idx_alerts_state ON alerts(state, updated_at);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            format TEXT NOT NULL DEFAULT 'json',
+            template TEXT,
+            secret TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id INTEGER NOT NULL,
+            alert_id INTEGER,
+            alert_state TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            status_code INTEGER,
+            error TEXT,
+            delivered INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY(webhook_id) REFERENCES webhooks(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, created_at);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS email_subscriptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            email TEXT NOT NULL,
+            pubkey TEXT NOT NULL,
+            digest INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            confirmed_at INTEGER,
+            confirm_token_hash TEXT,
+            UNIQUE(email, pubkey)
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // Notices waiting for the next hourly digest, either because the recipient
+    // asked for digests or because they hit their rate limit.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS email_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            email TEXT NOT NULL,
+            alert_id INTEGER NOT NULL,
+            rule_name TEXT NOT NULL,
+            severity TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            state TEXT NOT NULL,
+            message TEXT NOT NULL,
+            at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS email_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            email TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            subject_line TEXT NOT NULL,
+            notices INTEGER NOT NULL,
+            error TEXT,
+            sent_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_email_log_email ON email_log(email, sent_at);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS silences (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            matchers TEXT NOT NULL,
+            starts_at INTEGER NOT NULL,
+            ends_at INTEGER NOT NULL,
+            comment TEXT NOT NULL,
+            created_by TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_silences_ends_at ON silences(ends_at);
+        "#
+    ).execute(&pool).await?;
+
+    let has_inhibit_rules: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'inhibit_rules'")
+        .fetch_one(&pool)
+        .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS inhibit_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            source_matchers TEXT NOT NULL,
+            target_matchers TEXT NOT NULL,
+            equal TEXT NOT NULL DEFAULT '',
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+    // A network-wide drop explains every node going offline with it, so out of
+    // the box it hides those per-node alerts. Seeded once; it can be edited or
+    // deleted like any other rule.
+    if has_inhibit_rules == 0 {
+        sqlx::query(
+            r#"
+            INSERT INTO inhibit_rules (name, source_matchers, target_matchers, equal, enabled, created_at)
+            VALUES (
+                'Network outage hides per-node offline alerts',
+                '[{"name":"kind","op":"=","value":"network_online_drop"}]',
+                '[{"name":"kind","op":"=","value":"node_offline"}]',
+                '',
+                1,
+                strftime('%s', 'now')
+            )
+            "#
+        ).execute(&pool).await?;
+    }
+
+    // One row per notification group; a group stays open while any of its
+    // alerts fire, so one incident notifies once when it opens and once when
+    // it resolves.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alert_groups (
+            group_key TEXT PRIMARY KEY,
+            rule_id INTEGER NOT NULL,
+            state TEXT NOT NULL,
+            opened_at INTEGER NOT NULL,
+            notified_at INTEGER NOT NULL,
+            resolved_at INTEGER,
+            alerts INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS accounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            email TEXT,
+            password_hash TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // Only a hash of each session token is kept, so a leaked database can't be
+    // used to sign in.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sessions (
+            token_hash TEXT PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            FOREIGN KEY(account_id) REFERENCES accounts(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_sessions_account ON sessions(account_id);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS account_pubkeys (
+            account_id INTEGER NOT NULL,
+            pubkey TEXT NOT NULL,
+            claimed_at INTEGER NOT NULL,
+            verified_at INTEGER,
+            PRIMARY KEY(account_id, pubkey),
+            FOREIGN KEY(account_id) REFERENCES accounts(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_account_pubkeys_pubkey ON account_pubkeys(pubkey);
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS watchlist (
+            account_id INTEGER NOT NULL,
+            pubkey TEXT NOT NULL,
+            label TEXT,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY(account_id, pubkey),
+            FOREIGN KEY(account_id) REFERENCES accounts(id)
+        );
+        "#
+    ).execute(&pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS ownership_challenges (
+            account_id INTEGER NOT NULL,
+            pubkey TEXT NOT NULL,
+            message TEXT NOT NULL,
+            expires_at INTEGER NOT NULL,
+            PRIMARY KEY(account_id, pubkey)
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // Public details a verified owner publishes about their node. The contact
+    // is only served when `contact_public` is set.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS node_profiles (
+            pubkey TEXT PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            display_name TEXT,
+            website TEXT,
+            contact TEXT,
+            contact_public INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // API keys are stored hashed like session tokens; `prefix` is the start of
+    // the key so operators can tell their keys apart.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            prefix TEXT NOT NULL,
+            key_hash TEXT NOT NULL UNIQUE,
+            scopes TEXT NOT NULL,
+            rate_limit_per_min INTEGER,
+            created_at INTEGER NOT NULL,
+            last_used_at INTEGER,
+            revoked_at INTEGER
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // Seeds in use with their track record, so scores and quarantines survive
+    // a restart. `source` is `config` or `discovered`.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS seeds (
+            address TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            successes INTEGER NOT NULL DEFAULT 0,
+            failures INTEGER NOT NULL DEFAULT 0,
+            consecutive_failures INTEGER NOT NULL DEFAULT 0,
+            latency_ms REAL,
+            pod_count INTEGER,
+            last_success_at INTEGER,
+            last_failure_at INTEGER,
+            quarantined_until INTEGER,
+            added_at INTEGER NOT NULL
+        );
+        "#
+    ).execute(&pool).await?;
+
+    // Columns added to the first release's tables; older databases need them
+    // altered in.
+    ensure_column(&pool, "nodes", "uptime", "INTEGER").await?;
+    ensure_column(&pool, "metrics", "total_committed", "INTEGER").await?;
+    ensure_column(&pool, "node_history", "storage_used", "INTEGER").await?;
+    ensure_column(&pool, "node_history", "storage_committed", "INTEGER").await?;
+    ensure_column(&pool, "node_history", "uptime", "INTEGER").await?;
+    ensure_column(&pool, "node_history", "cycle_id", "INTEGER").await?;
+
+    Ok(pool)
+}
+
+/// What `Storage::revoke_owners` deletes, on the caller's transaction.
+async fn revoke_owners(conn: &mut SqliteConnection, owners: &[Account], pubkey: &str) -> Result<(), sqlx::Error> {
+    for owner in owners {
+        let rules = "SELECT id FROM alert_rules WHERE owner_account_id = ? AND pubkey = ?";
+        for table in ["alerts", "alert_groups"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE rule_id IN ({})", table, rules))
+                .bind(owner.id)
+                .bind(pubkey)
+                .execute(&mut *conn)
+                .await?;
+        }
+        sqlx::query("DELETE FROM alert_rules WHERE owner_account_id = ? AND pubkey = ?")
+            .bind(owner.id)
+            .bind(pubkey)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Subscribes an owner's own address to a pubkey, confirmed from the start,
+/// or confirms an existing subscription, on the caller's transaction.
+async fn subscribe_owner(conn: &mut SqliteConnection, email: &str, pubkey: &str, digest: bool, created_at: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO email_subscriptions (email, pubkey, digest, created_at, confirmed_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(email, pubkey) DO UPDATE SET
+            digest = excluded.digest,
+            confirmed_at = COALESCE(email_subscriptions.confirmed_at, excluded.confirmed_at),
+            confirm_token_hash = NULL
+        "#
+    )
+    .bind(email)
+    .bind(pubkey)
+    .bind(digest)
+    .bind(created_at)
+    .bind(created_at)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn unsubscribe(conn: &mut SqliteConnection, email: &str, pubkey: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM email_subscriptions WHERE email = ? AND pubkey = ?")
+        .bind(email)
+        .bind(pubkey)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Fails unless SQLite's own integrity check passes on the file at `path`.
+async fn check_integrity(path: &Path) -> Result<(), sqlx::Error> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check").fetch_all(&mut conn).await?;
+    conn.close().await?;
+    if problems == ["ok"] {
+        Ok(())
+    } else {
+        Err(sqlx::Error::Protocol(format!("{} failed its integrity check: {}", path.display(), problems.join("; "))))
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub async fn restore(database_url: &str, backup: &Path) -> Result<Option<PathBuf>, sqlx::Error> {
+    check_integrity(backup).await?;
+    let target = SqliteConnectOptions::from_str(database_url)?.get_filename().into_owned();
+
+    let mut kept = None;
+    if target.exists() {
+        // Every open connection holds a shared lock on a WAL database, so an
+        // exclusive one is only granted when no server is using it.
+        let options = SqliteConnectOptions::new()
+            .filename(&target)
+            .locking_mode(SqliteLockingMode::Exclusive)
+            .busy_timeout(Duration::ZERO);
+        let mut conn = SqliteConnection::connect_with(&options).await?;
+        if let Err(e) = sqlx::query("BEGIN EXCLUSIVE; COMMIT;").execute(&mut conn).await {
+            return Err(sqlx::Error::Configuration(
+                format!("{} is in use ({}); stop the server before restoring", target.display(), e).into(),
+            ));
+        }
+        // Fold the WAL back in first so the copy kept aside is complete.
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut conn).await?;
+        conn.close().await?;
+
+        let previous = with_suffix(&target, &format!(".pre-restore-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ")));
+        if previous.exists() {
+            return Err(sqlx::Error::Configuration(format!("{} already exists; try again in a second", previous.display()).into()));
+        }
+        std::fs::rename(&target, &previous)?;
+        kept = Some(previous);
+    }
+    // A leftover WAL would be replayed onto the restored file.
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(&target, suffix));
+    }
+
+    let staging = with_suffix(&target, ".restoring");
+    std::fs::copy(backup, &staging)?;
+    std::fs::rename(&staging, &target)?;
+    Ok(kept)
+}
+
+async fn ensure_column(pool: &SqlitePool, table: &str, column: &str, decl: &str) -> Result<(), sqlx::Error> {
+    let existing: Option<String> = sqlx::query_scalar(&format!("SELECT name FROM pragma_table_info('{}') WHERE name = ?", table))
+        .bind(column)
+        .fetch_optional(pool)
+        .await?;
+
+    if existing.is_none() {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn get_all_nodes(&self) -> Result<Vec<NodeRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_node_by_id(&self, pubkey: &str) -> Result<Option<NodeRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeRecord>("SELECT * FROM nodes WHERE pubkey = ?")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_cycle(
+        &self,
+        cycle_at: i64,
+        snapshot: &Snapshot,
+        nodes: &[NodeRecord],
+        events: &[NodeEvent],
+        gone: &[String],
+    ) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        let cycle_id = sqlx::query(
+            "INSERT INTO metrics (timestamp, total_nodes, online_nodes, total_storage, total_committed) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(cycle_at)
+        .bind(snapshot.total_nodes)
+        .bind(snapshot.online_nodes)
+        .bind(snapshot.total_storage)
+        .bind(snapshot.total_committed)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for batch in latest_per_pubkey(nodes).chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Sqlite>::new(NODE_COLUMNS);
+            query.push_values(batch, |mut row, node| {
+                row.push_bind(&node.pubkey)
+                    .push_bind(&node.ip)
+                    .push_bind(&node.version)
+                    .push_bind(&node.status)
+                    .push_bind(node.last_seen)
+                    .push_bind(node.storage_used)
+                    .push_bind(node.storage_committed)
+                    .push_bind(node.storage_usage_percent)
+                    .push_bind(node.credits)
+                    .push_bind(node.latency_ms)
+                    .push_bind(&node.country)
+                    .push_bind(&node.city)
+                    .push_bind(node.lat)
+                    .push_bind(node.lon)
+                    .push_bind(node.uptime);
+            });
+            query.push(NODE_UPSERT);
+            query.build().execute(&mut *tx).await?;
+        }
+
+        for batch in nodes.chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO node_history (cycle_id, pubkey, timestamp, latency_ms, status, storage_used, storage_committed, uptime) "
+            );
+            query.push_values(batch, |mut row, node| {
+                row.push_bind(cycle_id)
+                    .push_bind(&node.pubkey)
+                    .push_bind(cycle_at)
+                    .push_bind(node.latency_ms)
+                    .push_bind(&node.status)
+                    .push_bind(node.storage_used)
+                    .push_bind(node.storage_committed)
+                    .push_bind(node.uptime);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        for batch in gone.chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Sqlite>::new("UPDATE nodes SET status = 'offline' WHERE pubkey IN (");
+            let mut pubkeys = query.separated(", ");
+            for pubkey in batch {
+                pubkeys.push_bind(pubkey);
+            }
+            query.push(")");
+            query.build().execute(&mut *tx).await?;
+        }
+
+        for batch in events.chunks(NODE_BATCH) {
+            let mut query = QueryBuilder::<Sqlite>::new("INSERT INTO node_events (pubkey, timestamp, event_type, previous, current) ");
+            query.push_values(batch, |mut row, event| {
+                row.push_bind(&event.pubkey)
+                    .push_bind(event.timestamp)
+                    .push_bind(&event.event_type)
+                    .push_bind(&event.previous)
+                    .push_bind(&event.current);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(cycle_id)
+    }
+
+    async fn get_history(&self, limit: i64) -> Result<Vec<(i64, i64, i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT timestamp, total_nodes, online_nodes, total_storage FROM metrics ORDER BY timestamp DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (
+            r.get(0), r.get(1), r.get(2), r.get(3)
+        )).collect())
+    }
+
+    async fn get_node_history(&self, pubkey: &str, limit: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = ? ORDER BY timestamp DESC LIMIT ?"
+        )
+        .bind(pubkey)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_node_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = ? AND timestamp >= ? ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_node_history_since(&self, since: i64) -> Result<Vec<(String, NodeHistoryRecord)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            "SELECT pubkey, timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE timestamp >= ? ORDER BY pubkey, timestamp ASC"
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                let record = NodeHistoryRecord {
+                    timestamp: r.get(1),
+                    latency_ms: r.get(2),
+                    status: r.get(3),
+                    storage_used: r.get(4),
+                    storage_committed: r.get(5),
+                    uptime: r.get(6),
+                };
+                (r.get(0), record)
+            })
+            .collect())
+    }
+
+    fn stream_nodes(&self, from: i64, to: i64) -> BoxStream<'_, Result<NodeRecord, sqlx::Error>> {
+        sqlx::query_as::<_, NodeRecord>(
+            "SELECT * FROM nodes WHERE COALESCE(last_seen, 0) >= ? AND COALESCE(last_seen, 0) < ? ORDER BY pubkey"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    fn stream_metrics(&self, from: i64, to: i64) -> BoxStream<'_, Result<MetricsRecord, sqlx::Error>> {
+        sqlx::query_as::<_, MetricsRecord>(
+            "SELECT id AS cycle_id, timestamp, total_nodes, online_nodes, total_storage, total_committed FROM metrics \
+             WHERE timestamp >= ? AND timestamp < ? ORDER BY timestamp ASC"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    fn stream_node_history(&self, pubkey: &str, from: i64, to: i64) -> BoxStream<'_, Result<NodeHistoryRecord, sqlx::Error>> {
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history \
+             WHERE pubkey = ? AND timestamp >= ? AND timestamp < ? ORDER BY timestamp ASC"
+        )
+        .bind(pubkey.to_string())
+        .bind(from)
+        .bind(to)
+        .fetch(&self.pool)
+    }
+
+    async fn backup(&self, path: &Path) -> Result<(), sqlx::Error> {
+        // VACUUM INTO reads one snapshot of the database, so writers carry on
+        // and the copy is still consistent.
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await?;
+        if let Err(e) = check_integrity(path).await {
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn prune(&self, before: i64) -> Result<Pruned, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut pruned = Pruned::default();
+        for (table, column, count) in [
+            ("node_history", "timestamp", &mut pruned.node_history),
+            ("metrics", "timestamp", &mut pruned.metrics),
+            ("observer_cycles", "started_at", &mut pruned.observer_cycles),
+            ("credits_history", "timestamp", &mut pruned.credits_history),
+        ] {
+            let sql = format!("DELETE FROM {} WHERE {} < ?", table, column);
+            *count = sqlx::query(&sql).bind(before).execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(pruned)
+    }
+
+    async fn save_observer_cycle(
+        &self,
+        cycle_id: Option<i64>,
+        started_at: i64,
+        duration_ms: i64,
+        outcome: &str,
+        seed: Option<&str>,
+        pods_received: i64,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO observer_cycles (cycle_id, started_at, duration_ms, outcome, seed, pods_received, error) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(cycle_id)
+        .bind(started_at)
+        .bind(duration_ms)
+        .bind(outcome)
+        .bind(seed)
+        .bind(pods_received)
+        .bind(error)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_observer_cycles(&self, limit: i64) -> Result<Vec<ObserverCycle>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ObserverCycle>("SELECT * FROM observer_cycles ORDER BY started_at DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_cycles_since(&self, since: i64) -> Result<Vec<(i64, bool)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT started_at, outcome FROM observer_cycles WHERE started_at >= ? ORDER BY started_at ASC")
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get::<String, _>(1) == "ok")).collect())
+    }
+
+    async fn save_credits(&self, timestamp: i64, credits: &[(String, i64)]) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        for (pubkey, value) in credits {
+            sqlx::query("INSERT INTO credits_history (pubkey, timestamp, credits) VALUES (?, ?, ?)")
+                .bind(pubkey)
+                .bind(timestamp)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("UPDATE nodes SET credits = ? WHERE pubkey = ?")
+                .bind(value)
+                .bind(pubkey)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn get_latest_credits(&self) -> Result<Option<(i64, Vec<(String, i64)>)>, sqlx::Error> {
+        let pool = &self.pool;
+        let latest: Option<i64> = sqlx::query_scalar("SELECT MAX(timestamp) FROM credits_history")
+            .fetch_one(pool)
+            .await?;
+
+        let Some(timestamp) = latest else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query("SELECT pubkey, credits FROM credits_history WHERE timestamp = ?")
+            .bind(timestamp)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(Some((timestamp, rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())))
+    }
+
+    async fn get_node_credits_history_since(&self, pubkey: &str, since: i64) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT timestamp, credits FROM credits_history WHERE pubkey = ? AND timestamp >= ? ORDER BY timestamp ASC")
+            .bind(pubkey)
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn get_credits_history_since(&self, since: i64) -> Result<Vec<(String, i64, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query("SELECT pubkey, timestamp, credits FROM credits_history WHERE timestamp >= ? ORDER BY pubkey, timestamp ASC")
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect())
+    }
+
+    async fn get_node_history_before(&self, pubkey: &str, before: i64) -> Result<Vec<NodeHistoryRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeHistoryRecord>(
+            "SELECT timestamp, latency_ms, status, storage_used, storage_committed, uptime FROM node_history WHERE pubkey = ? AND timestamp < ? ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(before)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_reputations(&self) -> Result<Vec<ReputationRecord>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ReputationRecord>("SELECT * FROM node_reputation")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_reputations(&self, records: &[&ReputationRecord]) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+
+        for r in records {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO node_reputation (
+                    pubkey, first_seen, updated_at, weight, up_weight, latency_weight, latency_sum, latency_sq_sum,
+                    version_weight, version_sum, credits_weight, credits_sum, availability_score, latency_score,
+                    version_score, credits_score, tenure_score, score
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(&r.pubkey)
+            .bind(r.first_seen)
+            .bind(r.updated_at)
+            .bind(r.weight)
+            .bind(r.up_weight)
+            .bind(r.latency_weight)
+            .bind(r.latency_sum)
+            .bind(r.latency_sq_sum)
+            .bind(r.version_weight)
+            .bind(r.version_sum)
+            .bind(r.credits_weight)
+            .bind(r.credits_sum)
+            .bind(r.availability_score)
+            .bind(r.latency_score)
+            .bind(r.version_score)
+            .bind(r.credits_score)
+            .bind(r.tenure_score)
+            .bind(r.score)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn get_reputation_page(&self, limit: i64, offset: i64) -> Result<(i64, Vec<ReputationRecord>), sqlx::Error> {
+        let pool = &self.pool;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM node_reputation")
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query_as::<_, ReputationRecord>(
+            "SELECT * FROM node_reputation ORDER BY score DESC, pubkey ASC LIMIT ? OFFSET ?"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok((total, rows))
+    }
+
+    async fn get_node_events_since(&self, pubkey: &str, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeEvent>(
+            "SELECT pubkey, timestamp, event_type, previous, current FROM node_events e WHERE pubkey = ? AND (timestamp >= ? \
+             OR (event_type IN ('first_seen', 'version_changed') AND timestamp = (SELECT MAX(timestamp) FROM node_events \
+             WHERE pubkey = e.pubkey AND event_type IN ('first_seen', 'version_changed') AND timestamp < ?))) ORDER BY timestamp ASC"
+        )
+        .bind(pubkey)
+        .bind(since)
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_all_node_events_since(&self, since: i64) -> Result<Vec<NodeEvent>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeEvent>(
+            "SELECT pubkey, timestamp, event_type, previous, current FROM node_events e WHERE timestamp >= ? \
+             OR (event_type IN ('first_seen', 'version_changed') AND timestamp = (SELECT MAX(timestamp) FROM node_events \
+             WHERE pubkey = e.pubkey AND event_type IN ('first_seen', 'version_changed') AND timestamp < ?)) ORDER BY pubkey, timestamp ASC"
+        )
+        .bind(since)
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_version_first_seen(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            "SELECT current, MIN(timestamp) FROM node_events WHERE event_type IN ('first_seen', 'version_changed') AND current IS NOT NULL GROUP BY current"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn save_prediction_model(&self, trained_at: i64, samples: i64, positives: i64, model: &str) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("INSERT INTO prediction_models (trained_at, samples, positives, model) VALUES (?, ?, ?, ?)")
+            .bind(trained_at)
+            .bind(samples)
+            .bind(positives)
+            .bind(model)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_latest_prediction_model(&self) -> Result<Option<String>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT model FROM prediction_models ORDER BY trained_at DESC LIMIT 1")
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_hourly_node_storage_since(&self, since: i64) -> Result<Vec<(String, i64, f64, f64)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            r#"
+            SELECT pubkey, (timestamp / 3600) * 3600 AS hour, AVG(storage_used), AVG(storage_committed)
+            FROM node_history
+            WHERE timestamp >= ? AND storage_used IS NOT NULL AND storage_committed IS NOT NULL
+            GROUP BY pubkey, hour
+            ORDER BY pubkey, hour
+            "#
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2), r.get(3))).collect())
+    }
+
+    async fn get_hourly_network_storage_since(&self, since: i64) -> Result<Vec<(i64, f64, Option<f64>)>, sqlx::Error> {
+        let pool = &self.pool;
+        let rows = sqlx::query(
+            r#"
+            SELECT (timestamp / 3600) * 3600 AS hour, AVG(total_storage), AVG(total_committed)
+            FROM metrics
+            WHERE timestamp >= ?
+            GROUP BY hour
+            ORDER BY hour
+            "#
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect())
+    }
+
+    async fn get_alert_rules(&self) -> Result<Vec<AlertRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertRule>("SELECT * FROM alert_rules ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alert_rule(&self, id: i64) -> Result<Option<AlertRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertRule>("SELECT * FROM alert_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_alert_rule(&self, rule: &AlertRule) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO alert_rules (id, name, kind, pubkey, threshold, min_version, duration_secs, severity, enabled, created_at, group_by, owner_account_id)
+            VALUES (NULLIF(?, 0), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                kind = excluded.kind,
+                pubkey = excluded.pubkey,
+                threshold = excluded.threshold,
+                min_version = excluded.min_version,
+                duration_secs = excluded.duration_secs,
+                severity = excluded.severity,
+                enabled = excluded.enabled,
+                group_by = excluded.group_by
+            RETURNING id
+            "#
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.kind)
+        .bind(&rule.pubkey)
+        .bind(rule.threshold)
+        .bind(&rule.min_version)
+        .bind(rule.duration_secs)
+        .bind(&rule.severity)
+        .bind(rule.enabled)
+        .bind(rule.created_at)
+        .bind(&rule.group_by)
+        .bind(rule.owner_account_id)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_alert_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM alerts WHERE rule_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM alert_groups WHERE rule_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM alert_rules WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn get_active_alerts_for_rule(&self, rule_id: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Alert>("SELECT * FROM alerts WHERE rule_id = ? AND state != 'resolved'")
+            .bind(rule_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alerts(&self, state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        match state {
+            Some(state) => sqlx::query_as::<_, Alert>(
+                "SELECT * FROM alerts WHERE state = ? AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY updated_at DESC LIMIT ?"
+            )
+                .bind(state)
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, Alert>(
+                "SELECT * FROM alerts WHERE state != 'resolved' AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY updated_at DESC LIMIT ?"
+            )
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+        }
+    }
+
+    async fn insert_alert(&self, alert: &Alert) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id = sqlx::query(
+            "INSERT INTO alerts (rule_id, subject, state, value, message, started_at, fired_at, resolved_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(alert.rule_id)
+        .bind(&alert.subject)
+        .bind(&alert.state)
+        .bind(alert.value)
+        .bind(&alert.message)
+        .bind(alert.started_at)
+        .bind(alert.fired_at)
+        .bind(alert.resolved_at)
+        .bind(alert.updated_at)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn update_alert(&self, alert: &Alert) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "UPDATE alerts SET state = ?, value = ?, message = ?, fired_at = ?, resolved_at = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(&alert.state)
+        .bind(alert.value)
+        .bind(&alert.message)
+        .bind(alert.fired_at)
+        .bind(alert.resolved_at)
+        .bind(alert.updated_at)
+        .bind(alert.id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_alert(&self, id: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM alerts WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_peak_online_since(&self, since: i64) -> Result<Option<i64>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT MAX(online_nodes) FROM metrics WHERE timestamp >= ?")
+            .bind(since)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_webhooks(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_webhook(&self, id: i64) -> Result<Option<Webhook>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_webhook(&self, hook: &Webhook) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO webhooks (id, name, url, format, template, secret, enabled, created_at)
+            VALUES (NULLIF(?, 0), ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                url = excluded.url,
+                format = excluded.format,
+                template = excluded.template,
+                secret = excluded.secret,
+                enabled = excluded.enabled
+            RETURNING id
+            "#
+        )
+        .bind(hook.id)
+        .bind(&hook.name)
+        .bind(&hook.url)
+        .bind(&hook.format)
+        .bind(&hook.template)
+        .bind(&hook.secret)
+        .bind(hook.enabled)
+        .bind(hook.created_at)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn save_webhook_delivery(
+        &self,
+        webhook_id: i64,
+        alert_id: Option<i64>,
+        alert_state: &str,
+        attempt: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+        delivered: bool,
+        created_at: i64,
+    ) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO webhook_deliveries (webhook_id, alert_id, alert_state, attempt, status_code, error, delivered, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(webhook_id)
+        .bind(alert_id)
+        .bind(alert_state)
+        .bind(attempt)
+        .bind(status_code)
+        .bind(error)
+        .bind(delivered)
+        .bind(created_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE webhook_id = ? ORDER BY id DESC LIMIT ?"
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_email_subscriptions(&self) -> Result<Vec<EmailSubscription>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailSubscription>("SELECT * FROM email_subscriptions ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_pending_email_subscription(
+        &self,
+        email: &str,
+        pubkey: &str,
+        digest: bool,
+        token_hash: &str,
+        created_at: i64,
+    ) -> Result<(i64, bool), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as(
+            r#"
+            INSERT INTO email_subscriptions (email, pubkey, digest, created_at, confirm_token_hash)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(email, pubkey) DO UPDATE SET
+                digest = excluded.digest,
+                confirm_token_hash = CASE WHEN email_subscriptions.confirmed_at IS NULL
+                    THEN excluded.confirm_token_hash ELSE NULL END
+            RETURNING id, confirmed_at IS NOT NULL
+            "#
+        )
+        .bind(email)
+        .bind(pubkey)
+        .bind(digest)
+        .bind(created_at)
+        .bind(token_hash)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn confirm_email_subscription(&self, token_hash: &str, confirmed_at: i64) -> Result<Option<EmailSubscription>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailSubscription>(
+            r#"
+            UPDATE email_subscriptions SET confirmed_at = ?, confirm_token_hash = NULL
+            WHERE confirm_token_hash = ? AND confirmed_at IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(confirmed_at)
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_email_subscription(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM email_subscriptions WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn queue_email(&self, item: &QueuedEmail) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO email_queue (email, alert_id, rule_name, severity, subject, state, message, at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&item.email)
+        .bind(item.alert_id)
+        .bind(&item.rule_name)
+        .bind(&item.severity)
+        .bind(&item.subject)
+        .bind(&item.state)
+        .bind(&item.message)
+        .bind(item.at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn take_email_queue(&self) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        let pool = &self.pool;
+        // Deleting exactly the rows returned leaves anything queued meanwhile
+        // for the next digest; RETURNING has no order, so sort here.
+        let rows = sqlx::query("DELETE FROM email_queue RETURNING id, email, alert_id, rule_name, severity, subject, state, message, at")
+            .fetch_all(pool)
+            .await?;
+        let mut items = rows
+            .iter()
+            .map(|r| Ok((r.try_get::<i64, _>("id")?, <QueuedEmail as sqlx::FromRow<_>>::from_row(r)?)))
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        items.sort_by(|a, b| (&a.1.email, a.1.at, a.0).cmp(&(&b.1.email, b.1.at, b.0)));
+        Ok(items.into_iter().map(|(_, item)| item).collect())
+    }
+
+    async fn save_email_log(&self, email: &str, kind: &str, subject_line: &str, notices: i64, error: Option<&str>, sent_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT INTO email_log (email, kind, subject_line, notices, error, sent_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(email)
+        .bind(kind)
+        .bind(subject_line)
+        .bind(notices)
+        .bind(error)
+        .bind(sent_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn count_emails_sent_since(&self, email: &str, since: i64) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT COUNT(*) FROM email_log WHERE email = ? AND sent_at >= ? AND error IS NULL")
+            .bind(email)
+            .bind(since)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_email_log(&self, limit: i64) -> Result<Vec<EmailLogEntry>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, EmailLogEntry>("SELECT * FROM email_log ORDER BY id DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_alert(&self, id: i64) -> Result<Option<Alert>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Alert>("SELECT * FROM alerts WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn set_alert_routing(&self, id: i64, group_key: Option<&str>, suppressed_by: Option<&str>) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("UPDATE alerts SET group_key = ?, suppressed_by = ? WHERE id = ?")
+            .bind(group_key)
+            .bind(suppressed_by)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_firing_alert_labels(&self) -> Result<Vec<FiringAlertLabels>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, FiringAlertLabels>(
+            r#"
+            SELECT a.id, a.rule_id, r.name AS rule_name, r.kind, r.severity, a.subject
+            FROM alerts a
+            JOIN alert_rules r ON r.id = a.rule_id
+            WHERE a.state = 'firing'
+            "#
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn count_notified_firing_in_group(&self, group_key: &str) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT COUNT(*) FROM alerts WHERE group_key = ? AND state = 'firing' AND suppressed_by IS NULL")
+            .bind(group_key)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn get_silences(&self, include_expired: bool, now: i64) -> Result<Vec<Silence>, sqlx::Error> {
+        let pool = &self.pool;
+        let min_end = if include_expired { i64::MIN } else { now };
+        sqlx::query_as::<_, Silence>("SELECT * FROM silences WHERE ends_at > ? ORDER BY id")
+            .bind(min_end)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_silence(&self, id: i64) -> Result<Option<Silence>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Silence>("SELECT * FROM silences WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn insert_silence(&self, silence: &Silence) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id = sqlx::query(
+            "INSERT INTO silences (matchers, starts_at, ends_at, comment, created_by, created_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&silence.matchers)
+        .bind(silence.starts_at)
+        .bind(silence.ends_at)
+        .bind(&silence.comment)
+        .bind(&silence.created_by)
+        .bind(silence.created_at)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    async fn expire_silence(&self, id: i64, now: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let updated = sqlx::query("UPDATE silences SET ends_at = MIN(ends_at, ?) WHERE id = ?")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn get_inhibit_rules(&self) -> Result<Vec<InhibitRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, InhibitRule>("SELECT * FROM inhibit_rules ORDER BY id")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn get_inhibit_rule(&self, id: i64) -> Result<Option<InhibitRule>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, InhibitRule>("SELECT * FROM inhibit_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_inhibit_rule(&self, rule: &InhibitRule) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO inhibit_rules (id, name, source_matchers, target_matchers, equal, enabled, created_at)
+            VALUES (NULLIF(?, 0), ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                source_matchers = excluded.source_matchers,
+                target_matchers = excluded.target_matchers,
+                equal = excluded.equal,
+                enabled = excluded.enabled
+            RETURNING id
+            "#
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.source_matchers)
+        .bind(&rule.target_matchers)
+        .bind(&rule.equal)
+        .bind(rule.enabled)
+        .bind(rule.created_at)
+        .fetch_one(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn delete_inhibit_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM inhibit_rules WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn get_alert_group(&self, group_key: &str) -> Result<Option<AlertGroup>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, AlertGroup>("SELECT * FROM alert_groups WHERE group_key = ?")
+            .bind(group_key)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_alert_group(&self, group: &AlertGroup) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT OR REPLACE INTO alert_groups (group_key, rule_id, state, opened_at, notified_at, resolved_at, alerts) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&group.group_key)
+        .bind(group.rule_id)
+        .bind(&group.state)
+        .bind(group.opened_at)
+        .bind(group.notified_at)
+        .bind(group.resolved_at)
+        .bind(group.alerts)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_alert_groups(&self, state: Option<&str>, limit: i64) -> Result<Vec<AlertGroup>, sqlx::Error> {
+        let pool = &self.pool;
+        match state {
+            Some(state) => sqlx::query_as::<_, AlertGroup>(
+                "SELECT * FROM alert_groups WHERE state = ? AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY opened_at DESC LIMIT ?"
+            )
+                .bind(state)
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+            None => sqlx::query_as::<_, AlertGroup>(
+                "SELECT * FROM alert_groups WHERE rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL) ORDER BY opened_at DESC LIMIT ?"
+            )
+                .bind(limit)
+                .fetch_all(pool)
+                .await,
+        }
+    }
+
+    async fn insert_account(&self, username: &str, email: Option<&str>, password_hash: &str, created_at: i64) -> Result<Option<i64>, sqlx::Error> {
+        let pool = &self.pool;
+        let id: Option<i64> = sqlx::query_scalar(
+            "INSERT INTO accounts (username, email, password_hash, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(username) DO NOTHING RETURNING id"
+        )
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(created_at)
+        .fetch_optional(pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn get_account_by_username(&self, username: &str) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE username = ?")
+            .bind(username)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn update_account_email(&self, account_id: i64, email: Option<&str>, now: i64) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let old: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        let pubkeys: Vec<String> = sqlx::query_scalar("SELECT pubkey FROM account_pubkeys WHERE account_id = ? AND verified_at IS NOT NULL")
+            .bind(account_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for pubkey in &pubkeys {
+            if let Some(old) = &old {
+                unsubscribe(&mut tx, old, pubkey).await?;
+            }
+            if let Some(email) = email {
+                subscribe_owner(&mut tx, email, pubkey, false, now).await?;
+            }
+        }
+        sqlx::query("UPDATE accounts SET email = ? WHERE id = ?")
+            .bind(email)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn insert_session(&self, token_hash: &str, account_id: i64, created_at: i64, expires_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("INSERT INTO sessions (token_hash, account_id, created_at, expires_at) VALUES (?, ?, ?, ?)")
+            .bind(token_hash)
+            .bind(account_id)
+            .bind(created_at)
+            .bind(expires_at)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_session_account(&self, token_hash: &str, now: i64) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>(
+            r#"
+            SELECT a.* FROM sessions s
+            JOIN accounts a ON a.id = s.account_id
+            WHERE s.token_hash = ? AND s.expires_at > ?
+            "#
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_session(&self, token_hash: &str, now: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM sessions WHERE token_hash = ? OR expires_at <= ?")
+            .bind(token_hash)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_claimed_pubkeys(&self, account_id: i64) -> Result<Vec<ClaimedPubkey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ClaimedPubkey>("SELECT pubkey, claimed_at, verified_at FROM account_pubkeys WHERE account_id = ? ORDER BY claimed_at")
+            .bind(account_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_verified_claim(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Vec<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        let mut tx = pool.begin().await?;
+        let lapsed = sqlx::query_as::<_, Account>(
+            r#"
+            SELECT accounts.* FROM accounts
+            JOIN account_pubkeys ON account_pubkeys.account_id = accounts.id
+            WHERE account_pubkeys.pubkey = ? AND account_pubkeys.account_id != ? AND account_pubkeys.verified_at IS NOT NULL
+            "#
+        )
+        .bind(pubkey)
+        .bind(account_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE account_pubkeys SET verified_at = NULL WHERE pubkey = ? AND account_id != ?")
+            .bind(pubkey)
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        revoke_owners(&mut tx, &lapsed, pubkey).await?;
+        for email in lapsed.iter().filter_map(|a| a.email.as_deref()) {
+            unsubscribe(&mut tx, email, pubkey).await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO account_pubkeys (account_id, pubkey, claimed_at, verified_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(account_id, pubkey) DO UPDATE SET verified_at = excluded.verified_at
+            "#
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM ownership_challenges WHERE account_id = ? AND pubkey = ?")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(&mut *tx)
+            .await?;
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        if let Some(email) = &email {
+            subscribe_owner(&mut tx, email, pubkey, false, now).await?;
+        }
+        tx.commit().await?;
+        Ok(lapsed)
+    }
+
+    async fn revoke_owners(&self, owners: &[Account], pubkey: &str) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        revoke_owners(&mut tx, owners, pubkey).await?;
+        tx.commit().await
+    }
+
+    async fn is_verified_owner(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM account_pubkeys WHERE account_id = ? AND pubkey = ? AND verified_at IS NOT NULL"
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .fetch_one(pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn save_ownership_challenge(&self, account_id: i64, pubkey: &str, message: &str, expires_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("INSERT OR REPLACE INTO ownership_challenges (account_id, pubkey, message, expires_at) VALUES (?, ?, ?, ?)")
+            .bind(account_id)
+            .bind(pubkey)
+            .bind(message)
+            .bind(expires_at)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_ownership_challenge(&self, account_id: i64, pubkey: &str, now: i64) -> Result<Option<String>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_scalar("SELECT message FROM ownership_challenges WHERE account_id = ? AND pubkey = ? AND expires_at > ?")
+            .bind(account_id)
+            .bind(pubkey)
+            .bind(now)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_account(&self, id: i64) -> Result<Option<Account>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Account>("SELECT * FROM accounts WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn get_node_profile(&self, pubkey: &str) -> Result<Option<NodeProfile>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, NodeProfile>("SELECT * FROM node_profiles WHERE pubkey = ?")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn save_node_profile(&self, profile: &NodeProfile) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            "INSERT OR REPLACE INTO node_profiles (pubkey, account_id, display_name, website, contact, contact_public, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&profile.pubkey)
+        .bind(profile.account_id)
+        .bind(&profile.display_name)
+        .bind(&profile.website)
+        .bind(&profile.contact)
+        .bind(profile.contact_public)
+        .bind(profile.updated_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release_pubkey(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM account_pubkeys WHERE account_id = ? AND pubkey = ?")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+        if let (true, Some(email)) = (deleted > 0, &email) {
+            unsubscribe(&mut tx, email, pubkey).await?;
+        }
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn get_watchlist(&self, account_id: i64) -> Result<Vec<WatchlistEntry>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, WatchlistEntry>("SELECT pubkey, label, added_at FROM watchlist WHERE account_id = ? ORDER BY added_at")
+            .bind(account_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_watchlist_entry(&self, account_id: i64, pubkey: &str, label: Option<&str>, added_at: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            r#"
+            INSERT INTO watchlist (account_id, pubkey, label, added_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(account_id, pubkey) DO UPDATE SET label = excluded.label
+            "#
+        )
+        .bind(account_id)
+        .bind(pubkey)
+        .bind(label)
+        .bind(added_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_watchlist_entry(&self, account_id: i64, pubkey: &str) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let deleted = sqlx::query("DELETE FROM watchlist WHERE account_id = ? AND pubkey = ?")
+            .bind(account_id)
+            .bind(pubkey)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn get_alerts_for_subjects(&self, account_id: i64, subjects: &[String], state: Option<&str>, limit: i64) -> Result<Vec<Alert>, sqlx::Error> {
+        if subjects.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pool = &self.pool;
+        let placeholders = vec!["?"; subjects.len()].join(", ");
+        let filter = if state.is_some() { "state = ?" } else { "state != 'resolved'" };
+        let sql = format!(
+            "SELECT * FROM alerts WHERE subject IN ({}) AND {} \
+             AND rule_id IN (SELECT id FROM alert_rules WHERE owner_account_id IS NULL OR owner_account_id = ?) \
+             ORDER BY updated_at DESC LIMIT ?",
+            placeholders, filter
+        );
+        let mut query = sqlx::query_as::<_, Alert>(&sql);
+        for subject in subjects {
+            query = query.bind(subject);
+        }
+        if let Some(state) = state {
+            query = query.bind(state);
+        }
+        query.bind(account_id).bind(limit).fetch_all(pool).await
+    }
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, prefix, scopes, rate_limit_per_min, created_at, last_used_at, revoked_at FROM api_keys ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, prefix, scopes, rate_limit_per_min, created_at, last_used_at, revoked_at FROM api_keys WHERE key_hash = ?"
+        )
+        .bind(key_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn insert_api_key(&self, key: &ApiKey, key_hash: &str) -> Result<i64, sqlx::Error> {
+        let pool = &self.pool;
+        let result = sqlx::query(
+            "INSERT INTO api_keys (name, prefix, key_hash, scopes, rate_limit_per_min, created_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&key.name)
+        .bind(&key.prefix)
+        .bind(key_hash)
+        .bind(&key.scopes)
+        .bind(key.rate_limit_per_min)
+        .bind(key.created_at)
+        .execute(pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn touch_api_key(&self, id: i64, now: i64) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("UPDATE api_keys SET last_used_at = ? WHERE id = ?")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, id: i64, now: i64) -> Result<bool, sqlx::Error> {
+        let pool = &self.pool;
+        let result = sqlx::query("UPDATE api_keys SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_seeds(&self) -> Result<Vec<Seed>, sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query_as::<_, Seed>("SELECT * FROM seeds ORDER BY added_at, address")
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn save_seed(&self, seed: &Seed) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO seeds
+                (address, source, successes, failures, consecutive_failures, latency_ms, pod_count,
+                 last_success_at, last_failure_at, quarantined_until, added_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&seed.address)
+        .bind(&seed.source)
+        .bind(seed.successes)
+        .bind(seed.failures)
+        .bind(seed.consecutive_failures)
+        .bind(seed.latency_ms)
+        .bind(seed.pod_count)
+        .bind(seed.last_success_at)
+        .bind(seed.last_failure_at)
+        .bind(seed.quarantined_until)
+        .bind(seed.added_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_seed(&self, address: &str) -> Result<(), sqlx::Error> {
+        let pool = &self.pool;
+        sqlx::query("DELETE FROM seeds WHERE address = ?")
+            .bind(address)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{credits, db};
+
+// Accrual is measured over the last week of stored credits so a single late or
+// missed credits fetch barely moves the rate.
+const ACCRUAL_WINDOW_SECS: i64 = 7 * 24 * 3600;
+
+pub const DEFAULT_HORIZONS: &str = "24h,7d,30d";
+
+// A week of accrual says little about anything past a few years out.
+const MAX_HORIZON_HOURS: i64 = 5 * 365 * 24;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Accrual {
+    pub per_hour: f64,
+    pub per_day: f64,
+    pub window_secs: i64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Projection {
+    pub horizon: String,
+    pub hours: i64,
+    pub projected_earned: f64,
+    pub projected_total: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeEarnings {
+    pub pubkey: String,
+    pub credits: i64,
+    pub rank: usize,
+    pub percentile: f64,
+    pub ranked_nodes: usize,
+    pub fetched_at: Option<i64>,
+    pub accrual: Option<Accrual>,
+    pub projections: Vec<Projection>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreditsLeaderboardEntry {
+    pub rank: usize,
+    pub pubkey: String,
+    pub credits: i64,
+    pub percentile: f64,
+    pub credits_per_day: Option<f64>,
+}
+
+/// Parses a comma separated list of horizons such as `12h,7d`.
+pub fn parse_horizons(raw: &str) -> Result<Vec<(String, i64)>, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .map(|h| {
+            let hours = match (h.strip_suffix('h'), h.strip_suffix('d')) {
+                (Some(n), _) => n.parse::<i64>().ok(),
+                (_, Some(n)) => n.parse::<i64>().ok().and_then(|d| d.checked_mul(24)),
+                _ => None,
+            }
+            .ok_or_else(|| format!("invalid horizon '{}', use e.g. 12h or 7d", h))?;
+            if hours <= 0 {
+                return Err(format!("horizon '{}' must be positive", h));
+            }
+            if hours > MAX_HORIZON_HOURS {
+                return Err(format!("horizon '{}' is too far out, the limit is {}d", h, MAX_HORIZON_HOURS / 24));
+            }
+            Ok((h.to_string(), hours))
+        })
+        .collect()
+}
+
+/// Accrual rate over the given samples, counting only increases so that a reset
+/// of the counter upstream doesn't show up as negative earnings.
+fn accrual(samples: &[(i64, i64)]) -> Option<Accrual> {
+    let (first, last) = (samples.first()?, samples.last()?);
+    let elapsed = last.0 - first.0;
+    if elapsed <= 0 {
+        return None;
+    }
+
+    let earned: i64 = samples.windows(2).map(|w| (w[1].1 - w[0].1).max(0)).sum();
+    let per_hour = earned as f64 / elapsed as f64 * 3600.0;
+    Some(Accrual {
+        per_hour,
+        per_day: per_hour * 24.0,
+        window_secs: elapsed,
+        samples: samples.len(),
+    })
+}
+
+/// Competition ranking (ties share a rank) over the latest cached credits.
+/// Percentile is the share of ranked nodes holding fewer credits.
+fn rankings(latest: &HashMap<String, i64>) -> Vec<(usize, String, i64, f64)> {
+    let mut entries: Vec<(&String, &i64)> = latest.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let total = entries.len();
+    let mut ranked = Vec::with_capacity(total);
+    let mut rank = 0;
+    for (i, (pubkey, credits)) in entries.iter().enumerate() {
+        if i == 0 || *credits != entries[i - 1].1 {
+            rank = i + 1;
+        }
+        let below = total - entries.partition_point(|(_, c)| *c >= *credits);
+        let percentile = if total > 1 { below as f64 / (total - 1) as f64 * 100.0 } else { 100.0 };
+        ranked.push((rank, (*pubkey).clone(), **credits, percentile));
+    }
+    ranked
+}
+
+pub async fn node_earnings(pubkey: &str, horizons: &[(String, i64)], now: i64) -> Result<Option<NodeEarnings>, sqlx::Error> {
+    let cache = credits::snapshot();
+    let Some((rank, _, current, percentile)) = rankings(&cache.credits)
+        .into_iter()
+        .find(|(_, p, _, _)| p == pubkey)
+    else {
+        return Ok(None);
+    };
+
+    let samples = db::store().get_node_credits_history_since(pubkey, now - ACCRUAL_WINDOW_SECS).await?;
+    let accrual = accrual(&samples);
+    let per_hour = accrual.as_ref().map(|a| a.per_hour).unwrap_or(0.0);
+
+    let projections = horizons
+        .iter()
+        .map(|(horizon, hours)| Projection {
+            horizon: horizon.clone(),
+            hours: *hours,
+            projected_earned: per_hour * *hours as f64,
+            projected_total: current as f64 + per_hour * *hours as f64,
+        })
+        .collect();
+
+    Ok(Some(NodeEarnings {
+        pubkey: pubkey.to_string(),
+        credits: current,
+        rank,
+        percentile,
+        ranked_nodes: cache.credits.len(),
+        fetched_at: cache.fetched_at,
+        accrual,
+        projections,
+    }))
+}
+
+pub async fn credits_leaderboard(limit: usize, now: i64) -> Result<Vec<CreditsLeaderboardEntry>, sqlx::Error> {
+    let cache = credits::snapshot();
+
+    let mut history: HashMap<String, Vec<(i64, i64)>> = HashMap::new();
+    for (pubkey, timestamp, value) in db::store().get_credits_history_since(now - ACCRUAL_WINDOW_SECS).await? {
+        history.entry(pubkey).or_default().push((timestamp, value));
+    }
+
+    Ok(rankings(&cache.credits)
+        .into_iter()
+        .take(limit)
+        .map(|(rank, pubkey, credits, percentile)| CreditsLeaderboardEntry {
+            credits_per_day: history.get(&pubkey).and_then(|s| accrual(s)).map(|a| a.per_day),
+            rank,
+            pubkey,
+            credits,
+            percentile,
+        })
+        .collect())
+}
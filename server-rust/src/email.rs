@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+use crate::alerts::AlertNotice;
+use crate::db::{self, QueuedEmail};
+
+pub const DIGEST_INTERVAL_SECS: u64 = 3600;
+const RATE_WINDOW_SECS: i64 = 3600;
+
+/// SMTP settings come from the environment. Without `SMTP_HOST` email is off
+/// and subscriptions are stored but never mailed.
+struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    rate_limit_per_hour: i64,
+    /// Where the observer is reachable, for the link in confirmation mails.
+    public_url: Option<String>,
+}
+
+static MAILER: Lazy<Option<Mailer>> = Lazy::new(|| match configure() {
+    Ok(mailer) => mailer,
+    Err(e) => {
+        eprintln!("Email notifications disabled: {}", e);
+        None
+    }
+});
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn configure() -> Result<Option<Mailer>, String> {
+    let Some(host) = env("SMTP_HOST") else {
+        return Ok(None);
+    };
+    let from: Mailbox = env("SMTP_FROM")
+        .ok_or("SMTP_FROM is required when SMTP_HOST is set")?
+        .parse()
+        .map_err(|e| format!("invalid SMTP_FROM: {}", e))?;
+
+    // STARTTLS on the submission port unless told otherwise; `tls` is implicit
+    // TLS (usually port 465) and `none` is only meant for a local sink.
+    let tls = env("SMTP_TLS").unwrap_or_else(|| "starttls".to_string());
+    let mut builder = match tls.as_str() {
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).map_err(|e| e.to_string())?,
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).map_err(|e| e.to_string())?,
+        "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+        other => return Err(format!("unknown SMTP_TLS '{}', expected starttls, tls or none", other)),
+    };
+    if let Some(port) = env("SMTP_PORT") {
+        builder = builder.port(port.parse().map_err(|_| format!("invalid SMTP_PORT '{}'", port))?);
+    }
+    if let Some(username) = env("SMTP_USERNAME") {
+        builder = builder.credentials(Credentials::new(username, env("SMTP_PASSWORD").unwrap_or_default()));
+    }
+
+    let rate_limit_per_hour = env("EMAIL_RATE_LIMIT_PER_HOUR")
+        .map(|v| v.parse().map_err(|_| format!("invalid EMAIL_RATE_LIMIT_PER_HOUR '{}'", v)))
+        .transpose()?
+        .unwrap_or(6);
+
+    let public_url = env("PUBLIC_URL").map(|url| url.trim_end_matches('/').to_string());
+
+    println!("Email notifications enabled via {} ({})", host, tls);
+    Ok(Some(Mailer { transport: builder.build(), from, rate_limit_per_hour, public_url }))
+}
+
+/// Reads the SMTP settings now so a bad configuration shows up at startup
+/// rather than with the first alert.
+pub fn init() {
+    Lazy::force(&MAILER);
+}
+
+/// Body accepted when subscribing an address to a pubkey's alerts.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionInput {
+    pub email: String,
+    pub pubkey: String,
+    pub digest: Option<bool>,
+}
+
+pub fn validate_address(email: &str) -> Result<(), String> {
+    email
+        .parse::<lettre::Address>()
+        .map(|_| ())
+        .map_err(|e| format!("invalid email address: {}", e))
+}
+
+fn queued(email: &str, notice: &AlertNotice) -> QueuedEmail {
+    QueuedEmail {
+        email: email.to_string(),
+        alert_id: notice.alert_id,
+        rule_name: notice.rule_name.clone(),
+        severity: notice.severity.clone(),
+        subject: notice.subject.clone(),
+        state: notice.state.clone(),
+        message: notice.message.clone(),
+        at: notice.at,
+    }
+}
+
+fn format_time(at: i64) -> String {
+    chrono::DateTime::from_timestamp(at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| at.to_string())
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render(intro: &str, items: &[QueuedEmail]) -> (String, String) {
+    let mut text = format!("{}\n\n", intro);
+    let mut rows = String::new();
+    for item in items {
+        text.push_str(&format!(
+            "- {} [{}] {} {}: {}\n",
+            format_time(item.at),
+            item.severity,
+            item.rule_name,
+            item.state,
+            item.message
+        ));
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            format_time(item.at),
+            escape_html(&item.severity),
+            escape_html(&item.rule_name),
+            escape_html(&item.state),
+            escape_html(&item.message)
+        ));
+    }
+    text.push_str("\nYou receive this because you subscribed to alerts for these nodes on the Xandeum observer.\n");
+
+    let html = format!(
+        "<html><body><p>{}</p><table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">\
+         <tr><th>Time</th><th>Severity</th><th>Rule</th><th>State</th><th>Message</th></tr>{}</table>\
+         <p style=\"color:#666\">You receive this because you subscribed to alerts for these nodes on the Xandeum observer.</p>\
+         </body></html>",
+        escape_html(intro),
+        rows
+    );
+    (text, html)
+}
+
+/// Sends one mail and records it in the email log, which also drives rate
+/// limiting.
+async fn send(mailer: &Mailer, to: &str, kind: &str, subject_line: &str, intro: &str, items: &[QueuedEmail]) -> Result<(), String> {
+    let (text, html) = render(intro, items);
+    deliver(mailer, to, kind, subject_line, text, html, items.len()).await
+}
+
+async fn deliver(mailer: &Mailer, to: &str, kind: &str, subject_line: &str, text: String, html: String, count: usize) -> Result<(), String> {
+    let result = async {
+        let message = Message::builder()
+            .from(mailer.from.clone())
+            .to(to.parse::<Mailbox>().map_err(|e| e.to_string())?)
+            .subject(subject_line)
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .map_err(|e| e.to_string())?;
+        mailer.transport.send(message).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+    .await;
+
+    if let Err(e) = db::shared().save_email_log(to, kind, subject_line, count as i64, result.as_ref().err().map(String::as_str), crate::now_secs()).await {
+        eprintln!("Failed to save email log: {}", e);
+    }
+    result
+}
+
+/// Mails alert notices to the operators subscribed to the affected pubkeys.
+/// Notices from private rules reach only the owner's subscription.
+/// Each recipient gets at most one mail per refresh cycle; digest subscribers,
+/// and anyone over their hourly limit, get the notices in the next digest
+/// instead. Returns the sending tasks for callers that must not exit before
+/// they finish.
+pub async fn dispatch(notices: &[AlertNotice]) -> Vec<JoinHandle<()>> {
+    match MAILER.as_ref() {
+        Some(mailer) => dispatch_via(mailer, notices).await,
+        None => Vec::new(),
+    }
+}
+
+async fn dispatch_via(mailer: &'static Mailer, notices: &[AlertNotice]) -> Vec<JoinHandle<()>> {
+    if notices.is_empty() {
+        return Vec::new();
+    }
+    let subscriptions = match db::shared().get_email_subscriptions().await {
+        Ok(subs) => subs,
+        Err(e) => {
+            eprintln!("Failed to load email subscriptions: {}", e);
+            return Vec::new();
+        }
+    };
+
+    // A private rule's alerts only go to its owner's own address.
+    let mut owner_emails: HashMap<i64, Option<String>> = HashMap::new();
+    for owner in notices.iter().filter_map(|n| n.owner_account_id) {
+        if let std::collections::hash_map::Entry::Vacant(entry) = owner_emails.entry(owner) {
+            entry.insert(db::shared().get_account(owner).await.ok().flatten().and_then(|a| a.email));
+        }
+    }
+
+    let mut immediate: HashMap<String, Vec<QueuedEmail>> = HashMap::new();
+    for notice in notices {
+        let owner_email = notice.owner_account_id.map(|id| owner_emails.get(&id).cloned().flatten());
+        let recipients = subscriptions.iter().filter(|s| {
+            s.confirmed_at.is_some()
+                && s.pubkey == notice.subject
+                && owner_email.as_ref().is_none_or(|owner| owner.as_deref() == Some(s.email.as_str()))
+        });
+        for sub in recipients {
+            let item = queued(&sub.email, notice);
+            if sub.digest {
+                if let Err(e) = db::store().queue_email(&item).await {
+                    eprintln!("Failed to queue email: {}", e);
+                }
+            } else {
+                immediate.entry(sub.email.clone()).or_default().push(item);
+            }
+        }
+    }
+
+    let since = crate::now_secs() - RATE_WINDOW_SECS;
+    let mut sending = Vec::new();
+    for (email, items) in immediate {
+        let sent = db::shared().count_emails_sent_since(&email, since).await.unwrap_or(0);
+        if sent >= mailer.rate_limit_per_hour {
+            for item in &items {
+                if let Err(e) = db::store().queue_email(item).await {
+                    eprintln!("Failed to queue email: {}", e);
+                }
+            }
+            continue;
+        }
+
+        sending.push(crate::networks::spawn(async move {
+            let subject_line = match items.as_slice() {
+                [one] => format!("[{}] {} {}: {}", one.severity, one.rule_name, one.state, one.subject),
+                many => format!("{} alert updates for your nodes", many.len()),
+            };
+            if let Err(e) = send(mailer, &email, "alert", &subject_line, "Alert updates for nodes you follow:", &items).await {
+                eprintln!("Failed to email {}: {}", email, e);
+            }
+        }));
+    }
+    sending
+}
+
+/// Sends each recipient one summary of everything queued since the last
+/// digest. Notices whose mail fails are queued again for the next run.
+pub async fn send_digests() {
+    if let Some(mailer) = MAILER.as_ref() {
+        send_digests_via(mailer).await;
+    }
+}
+
+async fn send_digests_via(mailer: &Mailer) {
+    let items = match db::store().take_email_queue().await {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Failed to read email queue: {}", e);
+            return;
+        }
+    };
+
+    for batch in items.chunk_by(|a, b| a.email == b.email) {
+        let email = &batch[0].email;
+        let subject_line = format!("Hourly digest: {} alert update(s) for your nodes", batch.len());
+        if let Err(e) = send(mailer, email, "digest", &subject_line, "Alert updates from the past hour:", batch).await {
+            eprintln!("Failed to send digest to {}: {}", email, e);
+            for item in batch {
+                if let Err(e) = db::store().queue_email(item).await {
+                    eprintln!("Failed to queue email: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// A new random confirmation token and the hash stored for it.
+pub fn confirmation_token() -> (String, String) {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let hash = hash_token(&token);
+    (token, hash)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn confirmation_text(public_url: Option<&str>, pubkey: &str, token: &str) -> String {
+    let path = format!("/email/subscriptions/confirm?token={}", token);
+    let how = match public_url {
+        Some(base) => format!("To start receiving them, open this link:\n\n{}{}", base, path),
+        None => format!("To start receiving them, request GET {} on the observer.", path),
+    };
+    format!(
+        "Someone asked for alerts about node {} to be emailed to this address.\n\n{}\n\nIf this wasn't you, ignore this mail and nothing will be sent.\n",
+        pubkey, how
+    )
+}
+
+/// Mails the address the token that activates its pending subscription.
+/// Counts towards the address's hourly limit, so repeated subscribe requests
+/// can't be used to flood it.
+pub async fn send_confirmation(email: &str, pubkey: &str, token: &str) -> Result<(), String> {
+    let mailer = MAILER.as_ref().ok_or("email is not configured, set SMTP_HOST and SMTP_FROM")?;
+    let since = crate::now_secs() - RATE_WINDOW_SECS;
+    let sent = db::shared().count_emails_sent_since(email, since).await.map_err(|e| e.to_string())?;
+    if sent >= mailer.rate_limit_per_hour {
+        return Err("too many emails to this address in the past hour, try again later".to_string());
+    }
+
+    let text = confirmation_text(mailer.public_url.as_deref(), pubkey, token);
+    let html = format!("<html><body><pre>{}</pre></body></html>", escape_html(&text));
+    deliver(mailer, email, "confirmation", "Confirm your Xandeum observer alert subscription", text, html, 0).await
+}
+
+pub async fn send_test(email: &str) -> Result<(), String> {
+    let mailer = MAILER.as_ref().ok_or("email is not configured, set SMTP_HOST and SMTP_FROM")?;
+    let item = QueuedEmail {
+        email: email.to_string(),
+        alert_id: 0,
+        rule_name: "Test alert".to_string(),
+        severity: "info".to_string(),
+        subject: "test".to_string(),
+        state: "firing".to_string(),
+        message: "Test email from the Xandeum observer".to_string(),
+        at: crate::now_secs(),
+    };
+    send(mailer, email, "test", "Test email from the Xandeum observer", "This is a test of your alert email settings.", &[item]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use crate::testing;
+
+    // The digest queue is shared, so tests that fill or drain it take turns.
+    static QUEUE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// `(recipient, message)` for every mail the sink accepted.
+    type Inbox = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// A local SMTP server that accepts everything.
+    async fn sink() -> (u16, Inbox) {
+        let inbox: Inbox = Arc::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = inbox.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let inbox = accepted.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 sink\r\n").await.unwrap();
+                    let mut rcpt = String::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let command = line.to_ascii_uppercase();
+                        let reply: &[u8] = if command.starts_with("RCPT TO:") {
+                            rcpt = line[8..].trim_matches(|c| c == '<' || c == '>' || c == ' ').to_string();
+                            b"250 ok\r\n"
+                        } else if command == "DATA" {
+                            write.write_all(b"354 go on\r\n").await.unwrap();
+                            let mut message = String::new();
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                if line == "." {
+                                    break;
+                                }
+                                message.push_str(&line);
+                                message.push('\n');
+                            }
+                            inbox.lock().unwrap().push((rcpt.clone(), message));
+                            b"250 queued\r\n"
+                        } else if command == "QUIT" {
+                            let _ = write.write_all(b"221 bye\r\n").await;
+                            return;
+                        } else {
+                            b"250 ok\r\n"
+                        };
+                        write.write_all(reply).await.unwrap();
+                    }
+                });
+            }
+        });
+        (port, inbox)
+    }
+
+    fn mailer(port: u16, rate_limit_per_hour: i64) -> &'static Mailer {
+        Box::leak(Box::new(Mailer {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1").port(port).build(),
+            from: "observer@test.example".parse().unwrap(),
+            rate_limit_per_hour,
+            public_url: None,
+        }))
+    }
+
+    /// A port nothing listens on.
+    async fn closed_port() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    async fn wait_for(inbox: &Inbox, count: usize) -> Vec<(String, String)> {
+        for _ in 0..50 {
+            if inbox.lock().unwrap().len() >= count {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // Give a mail that shouldn't arrive the chance to.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        inbox.lock().unwrap().clone()
+    }
+
+    fn notice(subject: &str, owner_account_id: Option<i64>) -> AlertNotice {
+        AlertNotice {
+            alert_id: 1,
+            rule_id: 1,
+            rule_name: "Node offline".to_string(),
+            kind: "node_offline".to_string(),
+            severity: "critical".to_string(),
+            subject: subject.to_string(),
+            state: "firing".to_string(),
+            value: None,
+            message: format!("{} is offline", subject),
+            at: crate::now_secs(),
+            group_key: subject.to_string(),
+            owner_account_id,
+        }
+    }
+
+    async fn subscribe(email: &str, pubkey: &str, digest: bool) {
+        let (_, token_hash) = confirmation_token();
+        db::shared().save_pending_email_subscription(email, pubkey, digest, &token_hash, 0).await.unwrap();
+        db::shared().confirm_email_subscription(&token_hash, crate::now_secs()).await.unwrap();
+    }
+
+    async fn queued_for(email: &str) -> Vec<QueuedEmail> {
+        db::store().take_email_queue().await.unwrap().into_iter().filter(|q| q.email == email).collect()
+    }
+
+    #[test]
+    fn only_confirmed_subscriptions_are_mailed() {
+        let received = testing::run(async {
+            let _queue = QUEUE.lock().await;
+            let (port, inbox) = sink().await;
+            subscribe("confirmed@routing.test", "pk-routing", false).await;
+            let (_, token_hash) = confirmation_token();
+            db::shared().save_pending_email_subscription("pending@routing.test", "pk-routing", false, &token_hash, 0).await.unwrap();
+
+            dispatch_via(mailer(port, 6), &[notice("pk-routing", None)]).await;
+            wait_for(&inbox, 1).await
+        });
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, "confirmed@routing.test");
+        assert!(received[0].1.contains("Subject: [critical] Node offline firing: pk-routing"));
+    }
+
+    #[test]
+    fn private_rule_notices_reach_only_the_owner() {
+        let received = testing::run(async {
+            let _queue = QUEUE.lock().await;
+            let (port, inbox) = sink().await;
+            let owner = db::shared().insert_account("private-owner", Some("owner@private.test"), "x", 0).await.unwrap().unwrap();
+            subscribe("owner@private.test", "pk-private", false).await;
+            subscribe("other@private.test", "pk-private", false).await;
+
+            dispatch_via(mailer(port, 6), &[notice("pk-private", Some(owner))]).await;
+            wait_for(&inbox, 1).await
+        });
+
+        let recipients: Vec<&str> = received.iter().map(|(to, _)| to.as_str()).collect();
+        assert_eq!(recipients, ["owner@private.test"]);
+    }
+
+    #[test]
+    fn owner_subscriptions_follow_claims_and_email_changes() {
+        let subscribed = |subs: &[db::EmailSubscription]| -> Vec<String> {
+            let mut emails: Vec<String> = subs
+                .iter()
+                .filter(|s| s.pubkey.starts_with("pk-follow") && s.confirmed_at.is_some())
+                .map(|s| format!("{} {}", s.email, s.pubkey))
+                .collect();
+            emails.sort();
+            emails
+        };
+        let (claimed, moved, released, lapsed) = testing::run(async {
+            let store = db::shared();
+            let first = store.insert_account("follow-first", Some("first@follow.test"), "x", 0).await.unwrap().unwrap();
+            let second = store.insert_account("follow-second", Some("second@follow.test"), "x", 0).await.unwrap().unwrap();
+            store.save_verified_claim(first, "pk-follow-1", 10).await.unwrap();
+            store.save_verified_claim(first, "pk-follow-2", 10).await.unwrap();
+            let claimed = subscribed(&store.get_email_subscriptions().await.unwrap());
+
+            store.update_account_email(first, Some("new@follow.test"), 20).await.unwrap();
+            let moved = subscribed(&store.get_email_subscriptions().await.unwrap());
+
+            store.release_pubkey(first, "pk-follow-1").await.unwrap();
+            let released = subscribed(&store.get_email_subscriptions().await.unwrap());
+
+            store.save_verified_claim(second, "pk-follow-2", 30).await.unwrap();
+            let lapsed = subscribed(&store.get_email_subscriptions().await.unwrap());
+            (claimed, moved, released, lapsed)
+        });
+
+        assert_eq!(claimed, ["first@follow.test pk-follow-1", "first@follow.test pk-follow-2"]);
+        assert_eq!(moved, ["new@follow.test pk-follow-1", "new@follow.test pk-follow-2"]);
+        assert_eq!(released, ["new@follow.test pk-follow-2"]);
+        assert_eq!(lapsed, ["second@follow.test pk-follow-2"]);
+    }
+
+    #[test]
+    fn digest_subscribers_get_one_summary() {
+        let (immediate, digest) = testing::run(async {
+            let _queue = QUEUE.lock().await;
+            let (port, inbox) = sink().await;
+            let mailer = mailer(port, 6);
+            subscribe("hourly@digest.test", "pk-digest-1", true).await;
+            subscribe("hourly@digest.test", "pk-digest-2", true).await;
+
+            dispatch_via(mailer, &[notice("pk-digest-1", None), notice("pk-digest-2", None)]).await;
+            let immediate = wait_for(&inbox, 1).await;
+            send_digests_via(mailer).await;
+            (immediate, wait_for(&inbox, 1).await)
+        });
+
+        assert!(immediate.is_empty());
+        assert_eq!(digest.len(), 1);
+        assert_eq!(digest[0].0, "hourly@digest.test");
+        assert!(digest[0].1.contains("Subject: Hourly digest: 2 alert update(s) for your nodes"));
+    }
+
+    #[test]
+    fn recipients_over_their_hourly_limit_are_queued_for_the_digest() {
+        let (received, queued) = testing::run(async {
+            let _queue = QUEUE.lock().await;
+            let (port, inbox) = sink().await;
+            subscribe("busy@limit.test", "pk-limit", false).await;
+            db::shared().save_email_log("busy@limit.test", "alert", "earlier", 1, None, crate::now_secs()).await.unwrap();
+
+            dispatch_via(mailer(port, 1), &[notice("pk-limit", None)]).await;
+            (wait_for(&inbox, 1).await, queued_for("busy@limit.test").await)
+        });
+
+        assert!(received.is_empty());
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].subject, "pk-limit");
+    }
+
+    #[test]
+    fn failed_digests_are_queued_again() {
+        let (requeued, logged_error) = testing::run(async {
+            let _queue = QUEUE.lock().await;
+            let item = queued("down@fallback.test", &notice("pk-fallback", None));
+            db::store().queue_email(&item).await.unwrap();
+
+            send_digests_via(mailer(closed_port().await, 6)).await;
+            let log = db::shared().get_email_log(100).await.unwrap();
+            let logged_error = log.into_iter().find(|e| e.email == "down@fallback.test").and_then(|e| e.error);
+            (queued_for("down@fallback.test").await, logged_error)
+        });
+
+        assert_eq!(requeued.len(), 1);
+        assert_eq!(requeued[0].subject, "pk-fallback");
+        assert!(logged_error.is_some());
+    }
+
+    #[test]
+    fn confirmation_links_to_the_public_url_when_set() {
+        let text = confirmation_text(Some("https://observer.example.com"), "pk", "abc");
+        assert!(text.contains("https://observer.example.com/email/subscriptions/confirm?token=abc"));
+
+        let text = confirmation_text(None, "pk", "abc");
+        assert!(text.contains("GET /email/subscriptions/confirm?token=abc"));
+    }
+
+    #[test]
+    fn html_body_escapes_alert_text() {
+        let item = queued("a@b.test", &AlertNotice { message: "<script>&</script>".to_string(), ..notice("pk", None) });
+        let (text, html) = render("Intro", &[item]);
+
+        assert!(text.contains("<script>&</script>"));
+        assert!(html.contains("&lt;script&gt;&amp;&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+}
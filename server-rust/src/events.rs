@@ -0,0 +1,61 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::db::{NodeEvent, NodeRecord};
+
+/// Compares a node's stored row from the previous cycle with what this cycle
+/// saw and returns the events to record as `(event_type, previous, current)`.
+fn detect(previous: Option<&NodeRecord>, current: &NodeRecord) -> Vec<(&'static str, Option<String>, Option<String>)> {
+    let Some(prev) = previous else {
+        return vec![("first_seen", None, current.version.clone())];
+    };
+
+    let mut events = Vec::new();
+    if prev.status != current.status {
+        let event_type = if current.status.as_deref() == Some("online") { "came_online" } else { "went_offline" };
+        events.push((event_type, prev.status.clone(), current.status.clone()));
+    }
+    if prev.version.is_some() && current.version.is_some() && prev.version != current.version {
+        events.push(("version_changed", prev.version.clone(), current.version.clone()));
+    }
+    // Uptime counts up from process start, so any drop means the node restarted.
+    if let (Some(before), Some(now)) = (prev.uptime, current.uptime) {
+        if now < before {
+            events.push(("restarted", Some(before.to_string()), Some(now.to_string())));
+        }
+    }
+    events
+}
+
+/// The cycle's events for every node: changes in the nodes seen, plus
+/// `went_offline` for nodes last stored as online that dropped out of gossip,
+/// which is how most nodes fail. Also returns the dropped nodes' pubkeys so
+/// the cycle stores them as offline and the event isn't raised again.
+///
+/// An empty `current` means the seed answered with nothing, not that every
+/// node left, so no drop-outs are reported for it.
+pub fn detect_cycle(previous: &HashMap<String, NodeRecord>, current: &[NodeRecord], at: i64) -> (Vec<NodeEvent>, Vec<String>) {
+    let event = |pubkey: &str, (event_type, previous, current): (&str, Option<String>, Option<String>)| NodeEvent {
+        pubkey: pubkey.to_string(),
+        timestamp: at,
+        event_type: event_type.to_string(),
+        previous,
+        current,
+    };
+
+    let mut events: Vec<NodeEvent> = current
+        .iter()
+        .flat_map(|n| detect(previous.get(&n.pubkey), n).into_iter().map(|e| event(&n.pubkey, e)))
+        .collect();
+
+    let mut gone = Vec::new();
+    if !current.is_empty() {
+        let seen: HashSet<&str> = current.iter().map(|n| n.pubkey.as_str()).collect();
+        for prev in previous.values() {
+            if !seen.contains(prev.pubkey.as_str()) && prev.status.as_deref() == Some("online") {
+                events.push(event(&prev.pubkey, ("went_offline", prev.status.clone(), Some("missing".to_string()))));
+                gone.push(prev.pubkey.clone());
+            }
+        }
+    }
+    (events, gone)
+}
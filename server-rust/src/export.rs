@@ -0,0 +1,305 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use arrow_array::builder::{Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use futures::stream::{BoxStream, StreamExt};
+use parquet::arrow::ArrowWriter;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::db::{MetricsRecord, NodeHistoryRecord, NodeRecord};
+
+// Rows per Parquet row group; also how many rows are held in memory at once.
+const PARQUET_BATCH: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "ndjson" => Ok(Format::Ndjson),
+            "parquet" => Ok(Format::Parquet),
+            other => Err(format!("unknown format '{}', expected csv, ndjson or parquet", other)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+            Format::Parquet => "parquet",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => "application/x-ndjson",
+            Format::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// `?format=csv|ndjson|parquet&from=<unix secs>&to=<unix secs>`; `from` is
+/// inclusive, `to` exclusive, and both default to unbounded.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl ExportQuery {
+    pub fn parse(&self) -> Result<(Format, i64, i64), String> {
+        let format = Format::parse(self.format.as_deref().unwrap_or("csv"))?;
+        let from = self.from.unwrap_or(i64::MIN);
+        let to = self.to.unwrap_or(i64::MAX);
+        if from >= to {
+            return Err("from must be before to".to_string());
+        }
+        Ok((format, from, to))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    Int,
+    Float,
+    Text,
+}
+
+/// A row type that can be exported. `COLUMNS` fixes the column order and the
+/// type of each serialized field, which CSV headers and Parquet schemas need
+/// before the first row arrives.
+pub trait Exportable: Serialize + Send + 'static {
+    const COLUMNS: &'static [(&'static str, Kind)];
+}
+
+impl Exportable for NodeRecord {
+    const COLUMNS: &'static [(&'static str, Kind)] = &[
+        ("pubkey", Kind::Text),
+        ("ip", Kind::Text),
+        ("version", Kind::Text),
+        ("status", Kind::Text),
+        ("last_seen", Kind::Int),
+        ("storage_used", Kind::Int),
+        ("storage_committed", Kind::Int),
+        ("storage_usage_percent", Kind::Float),
+        ("credits", Kind::Int),
+        ("latency_ms", Kind::Int),
+        ("country", Kind::Text),
+        ("city", Kind::Text),
+        ("lat", Kind::Float),
+        ("lon", Kind::Float),
+        ("uptime", Kind::Int),
+    ];
+}
+
+impl Exportable for MetricsRecord {
+    const COLUMNS: &'static [(&'static str, Kind)] = &[
+        ("cycle_id", Kind::Int),
+        ("timestamp", Kind::Int),
+        ("total_nodes", Kind::Int),
+        ("online_nodes", Kind::Int),
+        ("total_storage", Kind::Int),
+        ("total_committed", Kind::Int),
+    ];
+}
+
+impl Exportable for NodeHistoryRecord {
+    const COLUMNS: &'static [(&'static str, Kind)] = &[
+        ("timestamp", Kind::Int),
+        ("latency_ms", Kind::Int),
+        ("status", Kind::Text),
+        ("storage_used", Kind::Int),
+        ("storage_committed", Kind::Int),
+        ("uptime", Kind::Int),
+    ];
+}
+
+pub type Chunk = Result<Bytes, std::io::Error>;
+
+/// Encodes `rows` on a background task, handing back the output a chunk at a
+/// time. Rows are encoded as they come off the database, so memory stays flat
+/// however large the export; an error ends the output with an `Err` chunk.
+pub fn encode<T: Exportable>(rows: BoxStream<'static, Result<T, sqlx::Error>>, format: Format) -> mpsc::Receiver<Chunk> {
+    let (tx, rx) = mpsc::channel::<Chunk>(16);
+    crate::networks::spawn(async move {
+        let result = match format {
+            Format::Csv => write_csv(rows, &tx).await,
+            Format::Ndjson => write_ndjson(rows, &tx).await,
+            Format::Parquet => write_parquet(rows, &tx).await,
+        };
+        if let Err(e) = result {
+            eprintln!("Export failed: {}", e);
+            let _ = tx.send(Err(std::io::Error::other(e))).await;
+        }
+    });
+    rx
+}
+
+/// Streams `rows` to the client as a `<name>.<ext>` download; an error
+/// halfway through cuts the download short.
+pub fn response<T: Exportable>(rows: BoxStream<'static, Result<T, sqlx::Error>>, format: Format, name: &str) -> Response {
+    let rx = encode(rows, format);
+    let body = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) });
+    let disposition = format!("attachment; filename=\"{}.{}\"", name, format.extension());
+    (
+        [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+async fn send(tx: &mpsc::Sender<Chunk>, bytes: Vec<u8>) -> Result<(), String> {
+    // A closed channel means the client went away; stop reading rows.
+    tx.send(Ok(Bytes::from(bytes))).await.map_err(|_| "client disconnected".to_string())
+}
+
+fn fields<T: Exportable>(row: &T) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match serde_json::to_value(row).map_err(|e| e.to_string())? {
+        serde_json::Value::Object(map) => Ok(map),
+        _ => Err("export row is not an object".to_string()),
+    }
+}
+
+async fn write_csv<T: Exportable>(mut rows: BoxStream<'static, Result<T, sqlx::Error>>, tx: &mpsc::Sender<Chunk>) -> Result<(), String> {
+    let buffer = SharedBuffer::default();
+    let mut writer = csv::Writer::from_writer(buffer.clone());
+    writer.write_record(T::COLUMNS.iter().map(|(name, _)| *name)).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().await {
+        let fields = fields(&row.map_err(|e| e.to_string())?)?;
+        let record = T::COLUMNS.iter().map(|(name, _)| match fields.get(*name) {
+            None | Some(serde_json::Value::Null) => String::new(),
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        });
+        writer.write_record(record).map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())?;
+        send(tx, buffer.take()).await?;
+    }
+    writer.flush().map_err(|e| e.to_string())?;
+    send(tx, buffer.take()).await
+}
+
+async fn write_ndjson<T: Exportable>(mut rows: BoxStream<'static, Result<T, sqlx::Error>>, tx: &mpsc::Sender<Chunk>) -> Result<(), String> {
+    while let Some(row) = rows.next().await {
+        let mut line = serde_json::to_vec(&row.map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        send(tx, line).await?;
+    }
+    Ok(())
+}
+
+/// Where the CSV and Parquet writers put their output, drained into the
+/// response as it fills.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum ColumnBuilder {
+    Int(Int64Builder),
+    Float(Float64Builder),
+    Text(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(kind: Kind) -> Self {
+        match kind {
+            Kind::Int => ColumnBuilder::Int(Int64Builder::new()),
+            Kind::Float => ColumnBuilder::Float(Float64Builder::new()),
+            Kind::Text => ColumnBuilder::Text(StringBuilder::new()),
+        }
+    }
+
+    fn append(&mut self, value: Option<&serde_json::Value>) {
+        match self {
+            ColumnBuilder::Int(b) => b.append_option(value.and_then(|v| v.as_i64())),
+            ColumnBuilder::Float(b) => b.append_option(value.and_then(|v| v.as_f64())),
+            ColumnBuilder::Text(b) => b.append_option(value.and_then(|v| v.as_str())),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Int(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float(b) => Arc::new(b.finish()),
+            ColumnBuilder::Text(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+fn schema<T: Exportable>() -> Arc<Schema> {
+    let fields: Vec<Field> = T::COLUMNS
+        .iter()
+        .map(|(name, kind)| {
+            let data_type = match kind {
+                Kind::Int => DataType::Int64,
+                Kind::Float => DataType::Float64,
+                Kind::Text => DataType::Utf8,
+            };
+            Field::new(*name, data_type, true)
+        })
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+async fn write_parquet<T: Exportable>(mut rows: BoxStream<'static, Result<T, sqlx::Error>>, tx: &mpsc::Sender<Chunk>) -> Result<(), String> {
+    let schema = schema::<T>();
+    let buffer = SharedBuffer::default();
+    let mut writer = ArrowWriter::try_new(buffer.clone(), schema.clone(), None).map_err(|e| e.to_string())?;
+    let mut builders: Vec<ColumnBuilder> = T::COLUMNS.iter().map(|(_, kind)| ColumnBuilder::new(*kind)).collect();
+    let mut buffered = 0;
+
+    loop {
+        let row = rows.next().await.transpose().map_err(|e| e.to_string())?;
+        if let Some(row) = &row {
+            let fields = fields(row)?;
+            for ((name, _), builder) in T::COLUMNS.iter().zip(builders.iter_mut()) {
+                builder.append(fields.get(*name));
+            }
+            buffered += 1;
+        }
+        if buffered > 0 && (buffered == PARQUET_BATCH || row.is_none()) {
+            let columns = builders.iter_mut().map(ColumnBuilder::finish).collect();
+            let batch = RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())?;
+            writer.write(&batch).map_err(|e| e.to_string())?;
+            writer.flush().map_err(|e| e.to_string())?;
+            send(tx, buffer.take()).await?;
+            buffered = 0;
+        }
+        if row.is_none() {
+            break;
+        }
+    }
+
+    writer.close().map_err(|e| e.to_string())?;
+    send(tx, buffer.take()).await
+}
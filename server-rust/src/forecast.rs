@@ -0,0 +1,224 @@
+use serde::Serialize;
+
+use crate::db;
+
+const THRESHOLDS: [u8; 3] = [80, 90, 100];
+// Two-sided 95% interval under a normal approximation.
+const Z_95: f64 = 1.96;
+// Hourly buckets needed before a trend is worth reporting.
+const MIN_SAMPLES: usize = 6;
+
+/// Ordinary least squares fit of bytes against days relative to now.
+struct Fit {
+    intercept: f64,
+    slope: f64,
+    slope_se: f64,
+    residual_se: f64,
+    n: f64,
+    mean_x: f64,
+    sxx: f64,
+}
+
+impl Fit {
+    fn new(points: &[(f64, f64)]) -> Option<Fit> {
+        if points.len() < MIN_SAMPLES {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        if sxx <= 0.0 {
+            return None;
+        }
+        let slope = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum::<f64>() / sxx;
+        let intercept = mean_y - slope * mean_x;
+        let sse: f64 = points.iter().map(|p| (p.1 - intercept - slope * p.0).powi(2)).sum();
+        let residual_se = (sse / (n - 2.0)).sqrt();
+
+        Some(Fit {
+            intercept,
+            slope,
+            slope_se: residual_se / sxx.sqrt(),
+            residual_se,
+            n,
+            mean_x,
+            sxx,
+        })
+    }
+
+    fn at(&self, days: f64) -> f64 {
+        self.intercept + self.slope * days
+    }
+
+    /// Half-width of the 95% prediction interval `days` from now.
+    fn band(&self, days: f64) -> f64 {
+        Z_95 * self.residual_se * (1.0 + 1.0 / self.n + (days - self.mean_x).powi(2) / self.sxx).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdEta {
+    pub percent: u8,
+    /// Days until the threshold at the fitted growth rate; `None` if storage
+    /// isn't growing.
+    pub days: Option<f64>,
+    pub earliest_days: Option<f64>,
+    pub latest_days: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrowthForecast {
+    pub storage_used: f64,
+    pub storage_committed: f64,
+    pub growth_bytes_per_day: f64,
+    pub growth_bytes_per_day_low: f64,
+    pub growth_bytes_per_day_high: f64,
+    pub samples: usize,
+    pub thresholds: Vec<ThresholdEta>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectionPoint {
+    pub timestamp: i64,
+    pub storage_used: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeForecast {
+    pub pubkey: String,
+    #[serde(flatten)]
+    pub growth: GrowthForecast,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkForecast {
+    #[serde(flatten)]
+    pub growth: GrowthForecast,
+    pub projection: Vec<ProjectionPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageForecast {
+    pub generated_at: i64,
+    pub lookback_days: i64,
+    pub horizon_days: i64,
+    pub network: Option<NetworkForecast>,
+    pub nodes: Vec<NodeForecast>,
+}
+
+fn days_until(current: f64, target: f64, slope: f64) -> Option<f64> {
+    if current >= target {
+        Some(0.0)
+    } else if slope > 0.0 {
+        Some((target - current) / slope)
+    } else {
+        None
+    }
+}
+
+/// Time to each fill threshold. The earliest/latest bounds come from the 95%
+/// interval on the growth rate; a lower bound at or below zero growth means the
+/// threshold may never be reached, reported as `None`.
+fn growth(fit: &Fit, committed: f64, samples: usize) -> GrowthForecast {
+    let current = fit.at(0.0).max(0.0);
+    let (low, high) = (fit.slope - Z_95 * fit.slope_se, fit.slope + Z_95 * fit.slope_se);
+
+    GrowthForecast {
+        storage_used: current,
+        storage_committed: committed,
+        growth_bytes_per_day: fit.slope,
+        growth_bytes_per_day_low: low,
+        growth_bytes_per_day_high: high,
+        samples,
+        thresholds: THRESHOLDS
+            .iter()
+            .map(|&percent| {
+                let target = committed * percent as f64 / 100.0;
+                ThresholdEta {
+                    percent,
+                    days: days_until(current, target, fit.slope),
+                    earliest_days: days_until(current, target, high),
+                    latest_days: days_until(current, target, low),
+                }
+            })
+            .collect(),
+    }
+}
+
+fn to_days(timestamp: i64, now: i64) -> f64 {
+    (timestamp - now) as f64 / 86400.0
+}
+
+async fn network_forecast(since: i64, now: i64, horizon_days: i64) -> Result<Option<NetworkForecast>, sqlx::Error> {
+    let rows = db::store().get_hourly_network_storage_since(since).await?;
+    let points: Vec<(f64, f64)> = rows.iter().map(|(ts, used, _)| (to_days(*ts, now), *used)).collect();
+    let Some(fit) = Fit::new(&points) else {
+        return Ok(None);
+    };
+
+    // Committed capacity was only recorded in metrics later; fall back to the
+    // current nodes table for older databases.
+    let committed = match rows.iter().rev().find_map(|(_, _, c)| *c) {
+        Some(c) => c,
+        None => db::store().get_all_nodes()
+            .await?
+            .iter()
+            .filter_map(|n| n.storage_committed)
+            .sum::<i64>() as f64,
+    };
+
+    let projection = (0..=horizon_days)
+        .map(|day| {
+            let d = day as f64;
+            let used = fit.at(d).max(0.0);
+            let band = fit.band(d);
+            ProjectionPoint {
+                timestamp: now + day * 86400,
+                storage_used: used,
+                lower: (used - band).max(0.0),
+                upper: used + band,
+            }
+        })
+        .collect();
+
+    Ok(Some(NetworkForecast {
+        growth: growth(&fit, committed, points.len()),
+        projection,
+    }))
+}
+
+pub async fn storage_forecast(now: i64, lookback_days: i64, horizon_days: i64) -> Result<StorageForecast, sqlx::Error> {
+    let since = now - lookback_days * 86400;
+    let network = network_forecast(since, now, horizon_days).await?;
+
+    let rows = db::store().get_hourly_node_storage_since(since).await?;
+    let mut nodes = Vec::new();
+    for chunk in rows.chunk_by(|a, b| a.0 == b.0) {
+        let points: Vec<(f64, f64)> = chunk.iter().map(|(_, ts, used, _)| (to_days(*ts, now), *used)).collect();
+        let committed = chunk.last().map(|r| r.3).unwrap_or(0.0);
+        if committed <= 0.0 {
+            continue;
+        }
+        if let Some(fit) = Fit::new(&points) {
+            nodes.push(NodeForecast {
+                pubkey: chunk[0].0.clone(),
+                growth: growth(&fit, committed, points.len()),
+            });
+        }
+    }
+
+    // Nodes closest to full first; nodes that aren't filling up go last.
+    let full_in = |n: &NodeForecast| n.growth.thresholds.last().and_then(|t| t.days).unwrap_or(f64::INFINITY);
+    nodes.sort_by(|a, b| full_in(a).total_cmp(&full_in(b)));
+
+    Ok(StorageForecast {
+        generated_at: now,
+        lookback_days,
+        horizon_days,
+        network,
+        nodes,
+    })
+}
@@ -1,45 +1,109 @@
+mod accounts;
+mod alerts;
+mod apikeys;
+mod backup;
+mod cli;
+mod config;
+mod cors;
+mod credits;
 mod db;
+mod earnings;
+mod email;
+mod events;
+mod export;
+mod forecast;
 mod latency;
+mod networks;
+mod notify;
+mod outbound;
+mod ownership;
+mod prediction;
+mod ratelimit;
+mod reputation;
+mod seeds;
+mod sla;
+#[cfg(test)]
+mod testing;
+mod webhooks;
 
 use axum::{
-    extract::Path,
-    routing::get,
+    extract::{Path, Query},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post, put},
     Json, Router,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
-use tower_http::cors::CorsLayer;
 use serde::{Serialize, Deserialize};
 use once_cell::sync::Lazy;
 use dashmap::DashMap;
-use rand::seq::SliceRandom;
 use db::NodeRecord;
+use outbound::{CallError, Failure};
 
-static GEO_CACHE: Lazy<DashMap<String, GeoData>> = Lazy::new(|| DashMap::new());
-
-// Seed IPs provided by user
-static SEED_IPS: Lazy<Vec<&str>> = Lazy::new(|| vec![
-    "173.212.203.145",
-    "173.212.220.65",
-    "161.97.97.41",
-    "192.190.136.36",
-    "192.190.136.37",
-    "192.190.136.38",
-    "192.190.136.28",
-    "192.190.136.29",
-    "207.244.255.1",
-]);
+static GEO_CACHE: Lazy<DashMap<String, GeoData>> = Lazy::new(DashMap::new);
 
 #[tokio::main]
 async fn main() {
+    let cli = <config::Cli as clap::Parser>::parse();
+    let config = match config::load(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.print_config {
+        print!("{}", config.dump());
+        return;
+    }
+    let command = cli.command.clone();
+    let json = cli.json;
+    config::install(cli, config);
+    networks::init();
+    match command {
+        None | Some(config::Command::Serve) => {}
+        Some(command) => {
+            if let Err(e) = cli::run(command, json).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+    for network in networks::all() {
+        println!(
+            "Observing {} with {} seed(s) every {}s, database {}",
+            network.name,
+            network.seeds.len(),
+            network.refresh_interval_secs,
+            db::display_url(&network.database_url)
+        );
+    }
+
     // Initialize Database
     db::init_db().await.expect("Failed to initialize database");
+    apikeys::bootstrap().await.expect("Failed to set up the admin API key");
+    cors::reload().expect("Invalid CORS configuration");
+    email::init();
+    for network in networks::all() {
+        load_network_state(network).await;
+        spawn_network_tasks(network);
+    }
 
-    // Spawn background task for history snapshots and data refreshing
+    // SIGHUP re-reads the CORS policy and seed lists without dropping connections
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(30)); // Refresh every 30s
-        loop {
-            interval.tick().await;
-            refresh_data().await;
+        let Ok(mut hangups) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) else {
+            return;
+        };
+        while hangups.recv().await.is_some() {
+            if let Err(e) = cors::reload() {
+                eprintln!("Kept the previous CORS policy: {}", e);
+            }
+            for network in networks::all() {
+                if let Err(e) = networks::scope(network, seeds::reload(now_secs())).await {
+                    eprintln!("Kept the previous seed list for {}: {}", network.name, e);
+                }
+            }
         }
     });
 
@@ -47,16 +111,143 @@ async fn main() {
         .route("/pods", get(get_pods))
         .route("/node/:id", get(get_node))
         .route("/node/:id/history", get(get_node_history_handler))
+        .route("/node/:id/sla", get(get_node_sla_handler))
+        .route("/node/:id/earnings", get(get_node_earnings_handler))
+        .route("/node/:id/prediction", get(get_node_prediction_handler))
+        .route("/node/:id/profile", get(get_node_profile))
+        .route("/predictions/at-risk", get(get_at_risk_predictions))
+        .route("/predictions/backtest", get(get_prediction_backtest))
+        .route("/forecast/storage", get(get_storage_forecast))
         .route("/history", get(get_history))
+        .route("/export/pods", get(export_pods))
+        .route("/export/history", get(export_history))
+        .route("/export/node/:id/history", get(export_node_history))
+        .route("/alerts", get(get_alerts))
+        .route("/alerts/rules", get(get_alert_rules).post(create_alert_rule))
+        .route("/alerts/rules/:id", get(get_alert_rule).put(update_alert_rule).delete(delete_alert_rule))
+        .route("/alerts/groups", get(get_alert_groups))
+        .route("/alerts/silences", get(get_silences).post(create_silence))
+        .route("/alerts/silences/:id", get(get_silence).delete(expire_silence))
+        .route("/alerts/inhibit-rules", get(get_inhibit_rules).post(create_inhibit_rule))
+        .route("/alerts/inhibit-rules/:id", get(get_inhibit_rule).put(update_inhibit_rule).delete(delete_inhibit_rule))
+        .route("/accounts", post(register_account))
+        .route("/accounts/login", post(login_account))
+        .route("/accounts/logout", post(logout_account))
+        .route("/me", get(get_me).put(update_me))
+        .route("/me/pubkeys", post(claim_pubkey))
+        .route("/me/pubkeys/challenge", post(create_ownership_challenge))
+        .route("/me/pubkeys/:pubkey", delete(release_pubkey))
+        .route("/me/watchlist", get(get_watchlist).post(add_to_watchlist))
+        .route("/me/watchlist/:pubkey", delete(remove_from_watchlist))
+        .route("/me/nodes", get(get_my_nodes))
+        .route("/me/alerts", get(get_my_alerts))
+        .route("/me/sla", get(get_my_sla))
+        .route("/me/alerts/rules", get(get_my_alert_rules).post(create_my_alert_rule))
+        .route("/me/alerts/rules/:id", put(update_my_alert_rule).delete(delete_my_alert_rule))
+        .route("/me/nodes/:pubkey/profile", put(update_node_profile))
+        .route("/webhooks", get(get_webhooks).post(create_webhook))
+        .route("/webhooks/:id", get(get_webhook).put(update_webhook).delete(delete_webhook))
+        .route("/webhooks/:id/deliveries", get(get_webhook_deliveries))
+        .route("/webhooks/:id/test", post(test_webhook))
+        .route("/email/subscriptions", get(get_email_subscriptions).post(create_email_subscription))
+        .route("/email/subscriptions/confirm", get(confirm_email_subscription))
+        .route("/email/subscriptions/:id", delete(delete_email_subscription))
+        .route("/email/log", get(get_email_log))
+        .route("/email/test", post(test_email))
         .route("/credits", get(get_credits))
-        .layer(CorsLayer::permissive());
+        .route("/leaderboard/credits", get(get_credits_leaderboard))
+        .route("/leaderboard/reputation", get(get_reputation_leaderboard))
+        .route("/networks", get(get_networks))
+        .route("/observer/cycles", get(get_observer_cycles))
+        .route("/observer/breakers", get(get_breakers))
+        .route("/api-keys", get(get_api_keys).post(create_api_key))
+        .route("/api-keys/:id", delete(revoke_api_key))
+        .route("/admin/config", get(get_effective_config))
+        .route("/admin/cors", get(get_cors_policy))
+        .route("/admin/seeds", get(get_seeds))
+        .route("/admin/seeds/reload", post(reload_seeds))
+        .route("/admin/seeds/:address/quarantine", delete(release_seed))
+        .route("/admin/cors/reload", post(reload_cors_policy))
+        .route("/admin/backups", get(get_backups).post(create_backup))
+        .layer(middleware::from_fn(apikeys::authenticate))
+        .layer(middleware::from_fn(cors::layer));
+    // Wraps the router rather than being a layer on it: `/networks/:net/...`
+    // is rewritten before routing.
+    let app = tower::Layer::layer(&middleware::from_fn(networks::route), app);
 
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3001".to_string());
-    let addr = format!("0.0.0.0:{}", port);
+    let addr = format!("0.0.0.0:{}", config::get().server.port);
     println!("Rust API Server listening on {}", addr);
     
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, axum::ServiceExt::<axum::extract::Request>::into_make_service_with_connect_info::<std::net::SocketAddr>(app)).await.unwrap();
+}
+
+/// Loads what a network's refresh cycle works from: cached credits, the
+/// prediction model and the seed list.
+async fn load_network_state(network: &'static config::Network) {
+    networks::scope(network, async {
+        if let Err(e) = credits::load_cached().await {
+            eprintln!("Failed to load cached credits for {}: {}", network.name, e);
+        }
+        if let Err(e) = prediction::load_model().await {
+            eprintln!("Failed to load prediction model for {}: {}", network.name, e);
+        }
+        seeds::init(&network.seeds, now_secs()).await.expect("Failed to load seeds");
+    })
+    .await;
+}
+
+/// The refresh, credits, digest, training and backup loops for one network,
+/// each working on that network's database and seeds.
+fn spawn_network_tasks(network: &'static config::Network) {
+    // Spawn background task for history snapshots and data refreshing
+    tokio::spawn(networks::scope(network, async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(network.refresh_interval_secs));
+        loop {
+            interval.tick().await;
+            refresh_data().await;
+        }
+    }));
+
+    // Credits come from a separate service and change slowly, so they are
+    // ingested on their own schedule rather than with every pod refresh.
+    tokio::spawn(networks::scope(network, async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(credits::refresh_interval_secs()));
+        loop {
+            interval.tick().await;
+            credits::refresh_credits().await;
+        }
+    }));
+
+    // Operators on digest mode, or over their mail limit, get one summary an hour
+    tokio::spawn(networks::scope(network, async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(email::DIGEST_INTERVAL_SECS));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            email::send_digests().await;
+        }
+    }));
+
+    // Retrain the failure model daily on the past week of history
+    tokio::spawn(networks::scope(network, async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = prediction::retrain(now_secs()).await {
+                eprintln!("Failed to train prediction model: {}", e);
+            }
+        }
+    }));
+
+    tokio::spawn(networks::scope(network, backup::run_schedule()));
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -100,6 +291,7 @@ struct PodDto {
     is_public: Option<bool>,
     geo: Option<GeoData>,
     latency_ms: Option<i64>,
+    credits: Option<i64>,
 }
 
 async fn fetch_geo(ip: String) -> Option<GeoData> {
@@ -110,129 +302,86 @@ async fn fetch_geo(ip: String) -> Option<GeoData> {
     // Basic rate limit protection
     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
-    let url = format!("http://ip-api.com/json/{}", ip);
-    match reqwest::get(&url).await {
-        Ok(resp) => {
-            if let Ok(json) = resp.json::<serde_json::Value>().await {
-                if json["status"] == "success" {
-                    let geo = GeoData {
-                        lat: json["lat"].as_f64().unwrap_or(0.0),
-                        lon: json["lon"].as_f64().unwrap_or(0.0),
-                        country: json["country"].as_str().unwrap_or("").to_string(),
-                        city: json["city"].as_str().unwrap_or("").to_string(),
-                    };
-                    GEO_CACHE.insert(ip.clone(), geo.clone());
-                    return Some(geo);
-                }
-            }
+    let url = config::get().geo.url.replace("{ip}", &ip);
+    let fetched = outbound::call("geo", || async {
+        let resp = outbound::CLIENT
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(Failure::request)?;
+        if !resp.status().is_success() {
+            return Err(Failure::status(resp.status()));
+        }
+        resp.json::<serde_json::Value>().await.map_err(|e| Failure::permanent(e.to_string()))
+    })
+    .await;
+    if let Ok(json) = fetched {
+        if json["status"] == "success" {
+            let geo = GeoData {
+                lat: json["lat"].as_f64().unwrap_or(0.0),
+                lon: json["lon"].as_f64().unwrap_or(0.0),
+                country: json["country"].as_str().unwrap_or("").to_string(),
+                city: json["city"].as_str().unwrap_or("").to_string(),
+            };
+            GEO_CACHE.insert(ip.clone(), geo.clone());
+            return Some(geo);
         }
-        Err(_) => {}
     }
     None
 }
 
-async fn check_seed_health(ip: &str) -> bool {
-    let client = reqwest::Client::new();
-    let url = format!("http://{}:6000/rpc", ip);
+/// Asks one seed for the pod list.
+async fn fetch_pods_from(seed: &str) -> Result<Vec<PodRaw>, Failure> {
+    let rpc = &config::get().rpc;
+    let network = networks::current();
     let body = serde_json::json!({
         "jsonrpc": "2.0",
         "method": "get-pods-with-stats",
         "id": 1
     });
 
-    match client.post(&url)
+    let resp = outbound::CLIENT.post(network.rpc_url(seed))
         .header("Content-Type", "application/json")
         .json(&body)
-        .timeout(std::time::Duration::from_secs(2))
+        .timeout(std::time::Duration::from_secs(rpc.request_timeout_secs))
         .send()
-        .await 
-    {
-        Ok(resp) => resp.status().is_success(),
-        Err(_) => false,
+        .await
+        .map_err(Failure::request)?;
+    if !resp.status().is_success() {
+        return Err(Failure::status(resp.status()));
     }
-}
-
-async fn call_rpc_get_pods() -> Result<Vec<PodRaw>, String> {
-    let client = reqwest::Client::new();
-    
-    let mut seeds = SEED_IPS.clone();
-    {
-        let mut rng = rand::thread_rng();
-        seeds.shuffle(&mut rng);
-    }
-
-    // First pass: Try seeds that respond to a quick health check
-    for ip in &seeds {
-        if check_seed_health(ip).await {
-            let url = format!("http://{}:6000/rpc", ip);
-            let body = serde_json::json!({
-                "jsonrpc": "2.0",
-                "method": "get-pods-with-stats",
-                "id": 1
-            });
-
-            match client.post(&url)
-                .header("Content-Type", "application/json")
-                .json(&body)
-                .timeout(std::time::Duration::from_secs(5))
-                .send()
-                .await 
-            {
-                Ok(resp) => {
-                    if let Ok(json) = resp.json::<serde_json::Value>().await {
-                        if let Some(result) = json.get("result") {
-                             if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(result.clone()) {
-                                 return Ok(pods);
-                             }
-                             if let Some(pods_val) = result.get("pods") {
-                                 if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(pods_val.clone()) {
-                                     return Ok(pods);
-                                 }
-                             }
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("Failed to fetch from healthy seed {}: {}", ip, e);
-                    continue;
-                }
-            }
+    let json = resp.json::<serde_json::Value>().await.map_err(|e| Failure::permanent(e.to_string()))?;
+    let result = json.get("result").ok_or_else(|| Failure::permanent("response has no result"))?;
+    if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(result.clone()) {
+        return Ok(pods);
+    }
+    if let Some(pods_val) = result.get("pods") {
+        if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(pods_val.clone()) {
+            return Ok(pods);
         }
     }
+    Err(Failure::permanent("result is not a pod list"))
+}
 
-    // Second pass: Try all seeds if no "healthy" ones worked
-    for ip in seeds {
-        let url = format!("http://{}:6000/rpc", ip);
-        let body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "get-pods-with-stats",
-            "id": 1
-        });
+/// Fetches the pod list from the first seed that answers, returning the seed
+/// used. Seeds are tried best score first, quarantined ones last; seeds whose
+/// circuit is open are skipped without a request. Every request feeds back
+/// into the seed's score.
+async fn call_rpc_get_pods() -> Result<(String, Vec<PodRaw>), String> {
+    let (available, quarantined) = seeds::ranked(now_secs());
 
-        match client.post(&url)
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .timeout(std::time::Duration::from_secs(5))
-            .send()
-            .await 
-        {
-            Ok(resp) => {
-                if let Ok(json) = resp.json::<serde_json::Value>().await {
-                    if let Some(result) = json.get("result") {
-                         if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(result.clone()) {
-                             return Ok(pods);
-                         }
-                         if let Some(pods_val) = result.get("pods") {
-                             if let Ok(pods) = serde_json::from_value::<Vec<PodRaw>>(pods_val.clone()) {
-                                 return Ok(pods);
-                             }
-                         }
-                    }
-                }
+    for ip in available.iter().chain(&quarantined) {
+        let started = std::time::Instant::now();
+        match outbound::call(&format!("seed:{}", ip), || fetch_pods_from(ip)).await {
+            Ok(pods) => {
+                seeds::record_success(ip, started.elapsed().as_secs_f64() * 1000.0, pods.len() as i64, now_secs()).await;
+                return Ok((ip.to_string(), pods));
             }
-            Err(e) => {
-                println!("Failed to fetch from {}: {}", ip, e);
-                continue;
+            Err(CallError::Open) => {}
+            Err(CallError::Failed(e)) => {
+                println!("Failed to fetch from seed {}: {}", ip, e);
+                seeds::record_failure(ip, now_secs()).await;
             }
         }
     }
@@ -240,69 +389,167 @@ async fn call_rpc_get_pods() -> Result<Vec<PodRaw>, String> {
     Err("All seed nodes failed".to_string())
 }
 
-async fn refresh_data() {
-    println!("Refreshing data...");
-    if let Ok(pods) = call_rpc_get_pods().await {
-        let total = pods.len() as u32;
-        let online = pods.iter().filter(|p| p.uptime.unwrap_or(0) > 0).count() as u32;
-        let storage: u64 = pods.iter().map(|p| p.storage_used.unwrap_or(0) as u64).sum();
-
-        // Save snapshot
-        if let Err(e) = db::save_snapshot(total, online, storage).await {
-            eprintln!("Failed to save snapshot: {}", e);
-        }
-
-        // Process each pod
-        for pod in pods {
-            let ip_full = pod.address.clone().unwrap_or_default();
-            let ip_clean = ip_full.split(':').next().unwrap_or(&ip_full).to_string();
-            
-            // Measure latency
-            let latency = if !ip_clean.is_empty() {
-                // Use the full address if it has a port, otherwise default to 6000? 
-                // Actually the pod.address usually has the port.
-                latency::measure_latency(&ip_full).await
-            } else {
-                None
-            };
-
-            // Fetch Geo (if not cached)
-            let mut geo_data = None;
-            if !ip_clean.is_empty() && ip_clean != "127.0.0.1" {
-                 geo_data = fetch_geo(ip_clean.clone()).await;
+/// Public pods answering as online, as seed addresses on their pRPC port.
+fn seed_candidates(pods: &[PodRaw]) -> Vec<String> {
+    let rpc_port = networks::current().rpc_port;
+    pods.iter()
+        .filter(|p| p.is_public == Some(true) && p.uptime.unwrap_or(0) > 0)
+        .filter_map(|p| {
+            let address = p.address.as_deref()?;
+            let host = address.rsplit_once(':').map(|(h, _)| h).unwrap_or(address);
+            if host.is_empty() || host == "127.0.0.1" {
+                return None;
             }
+            Some(match p.rpc_port {
+                Some(port) if port != rpc_port => format!("{}:{}", host, port),
+                _ => host.to_string(),
+            })
+        })
+        .collect()
+}
 
-            let record = NodeRecord {
-                pubkey: pod.pubkey.unwrap_or_default(),
-                ip: ip_full,
-                version: pod.version,
-                status: if pod.uptime.unwrap_or(0) > 0 { Some("online".to_string()) } else { Some("offline".to_string()) },
-                last_seen: pod.last_seen_timestamp,
-                storage_used: pod.storage_used,
-                storage_committed: pod.storage_committed,
-                storage_usage_percent: pod.storage_usage_percent,
-                credits: None, // Credits are fetched separately via proxy for now, or we could integrate here
-                latency_ms: latency.map(|l| l as i64),
-                country: geo_data.as_ref().map(|g| g.country.clone()),
-                city: geo_data.as_ref().map(|g| g.city.clone()),
-                lat: geo_data.as_ref().map(|g| g.lat),
-                lon: geo_data.as_ref().map(|g| g.lon),
-            };
+/// Runs one refresh cycle. Returns the webhook and email deliveries it
+/// started, which carry on in the background.
+async fn refresh_data() -> Vec<tokio::task::JoinHandle<()>> {
+    println!("Refreshing {}...", networks::current().name);
+    let started = std::time::Instant::now();
+    let started_at = now_secs();
 
-            if let Err(e) = db::upsert_node(&record).await {
-                eprintln!("Failed to upsert node: {}", e);
+    let mut deliveries = Vec::new();
+    let (outcome, seed, pods_received, error, cycle_id) = match call_rpc_get_pods().await {
+        Ok((seed, pods)) => {
+            let received = pods.len() as i64;
+            let outcome = if pods.is_empty() { "empty" } else { "ok" };
+            let rpc = &config::get().rpc;
+            if rpc.discover_seeds {
+                seeds::discover(&seed_candidates(&pods), rpc.max_discovered_seeds, started_at).await;
             }
-
-            // Save history
-            if let Err(e) = db::save_node_history(&record.pubkey, record.latency_ms, record.status.as_deref()).await {
-                eprintln!("Failed to save node history: {}", e);
+            let (records, cycle_id) = process_pods(pods, started_at).await;
+            if outcome == "ok" {
+                if let Err(e) = reputation::update_cycle(started_at, &records).await {
+                    eprintln!("Failed to update reputation: {}", e);
+                }
+                match alerts::evaluate(started_at, &records).await {
+                    Ok(notices) => {
+                        for notice in &notices {
+                            println!("Alert {} [{}] {}: {}", notice.state, notice.severity, notice.rule_name, notice.message);
+                        }
+                        match notify::route(started_at, &notices).await {
+                            Ok(routed) => {
+                                deliveries.extend(webhooks::dispatch(&routed.notifications).await);
+                                deliveries.extend(email::dispatch(&routed.notices).await);
+                            }
+                            Err(e) => eprintln!("Failed to route alert notifications: {}", e),
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to evaluate alerts: {}", e),
+                }
             }
+            (outcome, Some(seed), received, None, cycle_id)
+        }
+        Err(e) => {
+            eprintln!("Refresh failed: {}", e);
+            ("failed", None, 0, Some(e), None)
+        }
+    };
+
+    // Record the cycle even when it failed so gaps in node history can be told
+    // apart from the observer being blind.
+    if let Err(e) = db::store().save_observer_cycle(
+        cycle_id,
+        started_at,
+        started.elapsed().as_millis() as i64,
+        outcome,
+        seed.as_deref(),
+        pods_received,
+        error.as_deref(),
+    ).await {
+        eprintln!("Failed to save observer cycle: {}", e);
+    }
+    deliveries
+}
+
+/// Builds this cycle's node records and writes them, with the network
+/// snapshot, as one cycle. Returns the records and the cycle id if the write
+/// went through.
+async fn process_pods(pods: Vec<PodRaw>, cycle_at: i64) -> (Vec<NodeRecord>, Option<i64>) {
+    let snapshot = db::Snapshot {
+        total_nodes: pods.len() as i64,
+        online_nodes: pods.iter().filter(|p| p.uptime.unwrap_or(0) > 0).count() as i64,
+        total_storage: pods.iter().map(|p| p.storage_used.unwrap_or(0)).sum(),
+        total_committed: pods.iter().map(|p| p.storage_committed.unwrap_or(0)).sum(),
+    };
+
+    // Last cycle's view of each node, to detect what changed since
+    let previous: std::collections::HashMap<String, NodeRecord> = match db::store().get_all_nodes().await {
+        Ok(nodes) => nodes.into_iter().map(|n| (n.pubkey.clone(), n)).collect(),
+        Err(e) => {
+            eprintln!("Failed to load previous node state: {}", e);
+            Default::default()
         }
+    };
+
+    // Process each pod
+    let mut records = Vec::with_capacity(pods.len());
+    for pod in pods {
+        let ip_full = pod.address.clone().unwrap_or_default();
+        let ip_clean = ip_full.split(':').next().unwrap_or(&ip_full).to_string();
+        
+        // Measure latency
+        let latency = if !ip_clean.is_empty() {
+            // Use the full address if it has a port, otherwise default to 6000? 
+            // Actually the pod.address usually has the port.
+            latency::measure_latency(&ip_full).await
+        } else {
+            None
+        };
+
+        // Fetch Geo (if not cached)
+        let mut geo_data = None;
+        if !ip_clean.is_empty() && ip_clean != "127.0.0.1" {
+             geo_data = fetch_geo(ip_clean.clone()).await;
+        }
+
+        let pubkey = pod.pubkey.unwrap_or_default();
+        let node_credits = credits::cached(&pubkey);
+
+        let record = NodeRecord {
+            pubkey,
+            ip: ip_full,
+            version: pod.version,
+            status: if pod.uptime.unwrap_or(0) > 0 { Some("online".to_string()) } else { Some("offline".to_string()) },
+            last_seen: pod.last_seen_timestamp,
+            storage_used: pod.storage_used,
+            storage_committed: pod.storage_committed,
+            storage_usage_percent: pod.storage_usage_percent,
+            credits: node_credits,
+            latency_ms: latency.map(|l| l as i64),
+            country: geo_data.as_ref().map(|g| g.country.clone()),
+            city: geo_data.as_ref().map(|g| g.city.clone()),
+            lat: geo_data.as_ref().map(|g| g.lat),
+            lon: geo_data.as_ref().map(|g| g.lon),
+            uptime: pod.uptime,
+        };
+
+        records.push(record);
     }
+
+    let (node_events, gone) = events::detect_cycle(&previous, &records, cycle_at);
+
+    // Nothing of the cycle is visible until all of it is.
+    let cycle_id = match db::store().save_cycle(cycle_at, &snapshot, &records, &node_events, &gone).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            eprintln!("Failed to save refresh cycle: {}", e);
+            None
+        }
+    };
+
+    (records, cycle_id)
 }
 
 async fn get_history() -> impl IntoResponse {
-    match db::get_history(1440).await {
+    match db::store().get_history(config::get().history.network_limit).await {
         Ok(history) => {
             let mapped: Vec<_> = history.into_iter().map(|(ts, total, online, storage)| {
                 serde_json::json!({
@@ -318,37 +565,40 @@ async fn get_history() -> impl IntoResponse {
     }
 }
 
+fn pod_dto(n: NodeRecord) -> PodDto {
+    let geo = if n.lat.is_some() {
+        Some(GeoData {
+            lat: n.lat.unwrap_or(0.0),
+            lon: n.lon.unwrap_or(0.0),
+            country: n.country.unwrap_or_default(),
+            city: n.city.unwrap_or_default(),
+        })
+    } else {
+        None
+    };
+
+    PodDto {
+        pubkey: Some(n.pubkey),
+        address: Some(n.ip),
+        uptime: n.uptime,
+        storage_used: n.storage_used,
+        storage_committed: n.storage_committed,
+        storage_usage_percent: n.storage_usage_percent,
+        version: n.version,
+        last_seen_timestamp: n.last_seen,
+        is_public: None, // Store in DB if needed
+        geo,
+        latency_ms: n.latency_ms,
+        credits: n.credits,
+    }
+}
+
 async fn get_pods() -> impl IntoResponse {
-    match db::get_all_nodes().await {
+    match db::store().get_all_nodes().await {
         Ok(nodes) => {
             let dto = PodsResponseDto {
                 total_count: nodes.len(),
-                pods: nodes.into_iter().map(|n| {
-                    let geo = if n.lat.is_some() {
-                        Some(GeoData {
-                            lat: n.lat.unwrap_or(0.0),
-                            lon: n.lon.unwrap_or(0.0),
-                            country: n.country.unwrap_or_default(),
-                            city: n.city.unwrap_or_default(),
-                        })
-                    } else {
-                        None
-                    };
-
-                    PodDto {
-                        pubkey: Some(n.pubkey),
-                        address: Some(n.ip),
-                        uptime: None, // We might want to store uptime in DB too if needed for frontend
-                        storage_used: n.storage_used,
-                        storage_committed: n.storage_committed,
-                        storage_usage_percent: n.storage_usage_percent,
-                        version: n.version,
-                        last_seen_timestamp: n.last_seen,
-                        is_public: None, // Store in DB if needed
-                        geo,
-                        latency_ms: n.latency_ms,
-                    }
-                }).collect(),
+                pods: nodes.into_iter().map(pod_dto).collect(),
             };
             Json(serde_json::to_value(dto).unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })))
         },
@@ -357,55 +607,996 @@ async fn get_pods() -> impl IntoResponse {
 }
 
 async fn get_node(Path(id): Path<String>) -> impl IntoResponse {
-    match db::get_node_by_id(&id).await {
-        Ok(Some(n)) => {
-            let geo = if n.lat.is_some() {
-                Some(GeoData {
-                    lat: n.lat.unwrap_or(0.0),
-                    lon: n.lon.unwrap_or(0.0),
-                    country: n.country.unwrap_or_default(),
-                    city: n.city.unwrap_or_default(),
-                })
-            } else {
-                None
-            };
-
-            let dto = PodDto {
-                pubkey: Some(n.pubkey),
-                address: Some(n.ip),
-                uptime: None,
-                storage_used: n.storage_used,
-                storage_committed: n.storage_committed,
-                storage_usage_percent: n.storage_usage_percent,
-                version: n.version,
-                last_seen_timestamp: n.last_seen,
-                is_public: None,
-                geo,
-                latency_ms: n.latency_ms,
-            };
-            Json(serde_json::to_value(dto).unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })))
-        },
+    match db::store().get_node_by_id(&id).await {
+        Ok(Some(n)) => Json(serde_json::to_value(pod_dto(n)).unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }))),
         Ok(None) => Json(serde_json::json!({ "error": "Node not found" })),
         Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
     }
 }
 
 async fn get_credits() -> impl IntoResponse {
-    let url = "https://podcredits.xandeum.network/api/pods-credits";
-    match reqwest::get(url).await {
-        Ok(resp) => {
-            match resp.json::<serde_json::Value>().await {
-                Ok(json) => Json(json),
-                Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    let cache = credits::snapshot();
+    let now = now_secs();
+
+    let mut entries: Vec<_> = cache.credits.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1));
+
+    Json(serde_json::json!({
+        "fetched_at": cache.fetched_at,
+        "stale_secs": cache.fetched_at.map(|ts| now - ts),
+        "last_attempt_at": cache.last_attempt_at,
+        "last_error": cache.last_error,
+        "credits": entries.into_iter().map(|(pubkey, credits)| serde_json::json!({
+            "pubkey": pubkey,
+            "credits": credits
+        })).collect::<Vec<_>>()
+    }))
+}
+
+async fn get_node_history_handler(Path(id): Path<String>) -> impl IntoResponse {
+    match db::store().get_node_history(&id, config::get().history.node_limit).await {
+        Ok(history) => Json(serde_json::to_value(history).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn export_pods(Query(query): Query<export::ExportQuery>) -> Response {
+    match query.parse() {
+        Ok((format, from, to)) => {
+            let name = format!("{}-pods", networks::current().name);
+            export::response(db::store().stream_nodes(from, to), format, &name)
+        }
+        Err(e) => Json(serde_json::json!({ "error": e })).into_response(),
+    }
+}
+
+async fn export_history(Query(query): Query<export::ExportQuery>) -> Response {
+    match query.parse() {
+        Ok((format, from, to)) => {
+            let name = format!("{}-history", networks::current().name);
+            export::response(db::store().stream_metrics(from, to), format, &name)
+        }
+        Err(e) => Json(serde_json::json!({ "error": e })).into_response(),
+    }
+}
+
+async fn export_node_history(Path(id): Path<String>, Query(query): Query<export::ExportQuery>) -> Response {
+    let (format, from, to) = match query.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => return Json(serde_json::json!({ "error": e })).into_response(),
+    };
+    match db::store().get_node_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Json(serde_json::json!({ "error": "Node not found" })).into_response(),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+    }
+    // Known pubkeys are base58, so safe inside the quoted filename.
+    let name = format!("{}-{}-history", networks::current().name, id);
+    export::response(db::store().stream_node_history(&id, from, to), format, &name)
+}
+
+async fn get_node_sla_handler(Path(id): Path<String>) -> impl IntoResponse {
+    match db::store().get_node_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Json(serde_json::json!({ "error": "Node not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    }
+
+    let now = now_secs();
+
+    match sla::node_sla(&id, now).await {
+        Ok(report) => Json(serde_json::to_value(report).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_observer_cycles() -> impl IntoResponse {
+    match db::store().get_observer_cycles(100).await {
+        Ok(cycles) => Json(serde_json::to_value(cycles).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct EarningsQuery {
+    horizons: Option<String>,
+}
+
+async fn get_node_earnings_handler(Path(id): Path<String>, Query(query): Query<EarningsQuery>) -> impl IntoResponse {
+    let horizons = match earnings::parse_horizons(query.horizons.as_deref().unwrap_or(earnings::DEFAULT_HORIZONS)) {
+        Ok(h) => h,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    let now = now_secs();
+
+    match earnings::node_earnings(&id, &horizons, now).await {
+        Ok(Some(report)) => Json(serde_json::to_value(report).unwrap()),
+        Ok(None) => Json(serde_json::json!({ "error": "No credits recorded for node" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    limit: Option<usize>,
+}
+
+async fn get_credits_leaderboard(Query(query): Query<LeaderboardQuery>) -> impl IntoResponse {
+    let now = now_secs();
+
+    match earnings::credits_leaderboard(query.limit.unwrap_or(100), now).await {
+        Ok(entries) => Json(serde_json::to_value(entries).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+async fn get_reputation_leaderboard(Query(query): Query<PageQuery>) -> impl IntoResponse {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+
+    match reputation::leaderboard(page, per_page).await {
+        Ok(page) => Json(serde_json::to_value(page).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_node_prediction_handler(Path(id): Path<String>) -> impl IntoResponse {
+    match prediction::predict_node(&id, now_secs()).await {
+        Ok(Some(p)) => Json(serde_json::to_value(p).unwrap()),
+        Ok(None) => Json(serde_json::json!({ "error": "No recent history for node" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct AtRiskQuery {
+    threshold: Option<f64>,
+    limit: Option<usize>,
+}
+
+async fn get_at_risk_predictions(Query(query): Query<AtRiskQuery>) -> impl IntoResponse {
+    match prediction::at_risk(now_secs(), query.threshold.unwrap_or(0.2), query.limit.unwrap_or(50)).await {
+        Ok(predictions) => Json(serde_json::to_value(predictions).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct BacktestQuery {
+    days: Option<i64>,
+}
+
+async fn get_prediction_backtest(Query(query): Query<BacktestQuery>) -> impl IntoResponse {
+    match prediction::backtest(now_secs(), query.days.unwrap_or(7).clamp(2, 30)).await {
+        Ok(report) => Json(serde_json::to_value(report).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct ForecastQuery {
+    lookback_days: Option<i64>,
+    horizon_days: Option<i64>,
+}
+
+async fn get_storage_forecast(Query(query): Query<ForecastQuery>) -> impl IntoResponse {
+    let lookback_days = query.lookback_days.unwrap_or(7).clamp(1, 90);
+    let horizon_days = query.horizon_days.unwrap_or(30).clamp(1, 365);
+
+    match forecast::storage_forecast(now_secs(), lookback_days, horizon_days).await {
+        Ok(report) => Json(serde_json::to_value(report).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct AlertsQuery {
+    state: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn get_alerts(Query(query): Query<AlertsQuery>) -> impl IntoResponse {
+    match db::store().get_alerts(query.state.as_deref(), query.limit.unwrap_or(100).clamp(1, 1000)).await {
+        Ok(alerts) => Json(serde_json::to_value(alerts).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_alert_rules() -> impl IntoResponse {
+    match db::store().get_alert_rules().await {
+        Ok(rules) => {
+            let public: Vec<_> = rules.into_iter().filter(|r| r.owner_account_id.is_none()).collect();
+            Json(serde_json::to_value(public).unwrap())
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Public rule lookup; private rules are only reachable through `/me/alerts/rules`.
+async fn get_public_alert_rule(id: i64) -> Result<Option<db::AlertRule>, sqlx::Error> {
+    Ok(db::store().get_alert_rule(id).await?.filter(|r| r.owner_account_id.is_none()))
+}
+
+async fn get_alert_rule(Path(id): Path<i64>) -> impl IntoResponse {
+    match get_public_alert_rule(id).await {
+        Ok(Some(rule)) => Json(serde_json::to_value(rule).unwrap()),
+        Ok(None) => Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn create_alert_rule(_admin: apikeys::Admin, Json(input): Json<alerts::RuleInput>) -> impl IntoResponse {
+    let mut rule = match alerts::validate(input, 0, now_secs()) {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_alert_rule(&rule).await {
+        Ok(id) => {
+            rule.id = id;
+            Json(serde_json::to_value(rule).unwrap())
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn update_alert_rule(_admin: apikeys::Admin, Path(id): Path<i64>, Json(input): Json<alerts::RuleInput>) -> impl IntoResponse {
+    let existing = match get_public_alert_rule(id).await {
+        Ok(Some(rule)) => rule,
+        Ok(None) => return Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    };
+
+    let rule = match alerts::validate(input, id, existing.created_at) {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_alert_rule(&rule).await {
+        Ok(_) => Json(serde_json::to_value(rule).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn delete_alert_rule(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match get_public_alert_rule(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    }
+    match db::store().delete_alert_rule(id).await {
+        Ok(true) => Json(serde_json::json!({ "deleted": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_alert_groups(Query(query): Query<AlertsQuery>) -> impl IntoResponse {
+    match db::store().get_alert_groups(query.state.as_deref(), query.limit.unwrap_or(100).clamp(1, 1000)).await {
+        Ok(groups) => Json(serde_json::to_value(groups).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct SilencesQuery {
+    all: Option<bool>,
+}
+
+async fn get_silences(Query(query): Query<SilencesQuery>) -> impl IntoResponse {
+    let now = now_secs();
+    match db::store().get_silences(query.all.unwrap_or(false), now).await {
+        Ok(silences) => Json(serde_json::Value::Array(silences.iter().map(|s| notify::silence_json(s, now)).collect())),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_silence(Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().get_silence(id).await {
+        Ok(Some(silence)) => Json(notify::silence_json(&silence, now_secs())),
+        Ok(None) => Json(serde_json::json!({ "error": "Silence not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn create_silence(_admin: apikeys::Admin, Json(input): Json<notify::SilenceInput>) -> impl IntoResponse {
+    let now = now_secs();
+    let mut silence = match notify::validate_silence(input, now) {
+        Ok(silence) => silence,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().insert_silence(&silence).await {
+        Ok(id) => {
+            silence.id = id;
+            Json(notify::silence_json(&silence, now))
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn expire_silence(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().expire_silence(id, now_secs()).await {
+        Ok(true) => Json(serde_json::json!({ "expired": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "Silence not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_inhibit_rules() -> impl IntoResponse {
+    match db::store().get_inhibit_rules().await {
+        Ok(rules) => Json(serde_json::Value::Array(rules.iter().map(notify::inhibit_rule_json).collect())),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_inhibit_rule(Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().get_inhibit_rule(id).await {
+        Ok(Some(rule)) => Json(notify::inhibit_rule_json(&rule)),
+        Ok(None) => Json(serde_json::json!({ "error": "Inhibit rule not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn create_inhibit_rule(_admin: apikeys::Admin, Json(input): Json<notify::InhibitRuleInput>) -> impl IntoResponse {
+    let mut rule = match notify::validate_inhibit_rule(input, 0, now_secs()) {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_inhibit_rule(&rule).await {
+        Ok(id) => {
+            rule.id = id;
+            Json(notify::inhibit_rule_json(&rule))
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn update_inhibit_rule(_admin: apikeys::Admin, Path(id): Path<i64>, Json(input): Json<notify::InhibitRuleInput>) -> impl IntoResponse {
+    let existing = match db::store().get_inhibit_rule(id).await {
+        Ok(Some(rule)) => rule,
+        Ok(None) => return Json(serde_json::json!({ "error": "Inhibit rule not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    };
+
+    let rule = match notify::validate_inhibit_rule(input, id, existing.created_at) {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_inhibit_rule(&rule).await {
+        Ok(_) => Json(notify::inhibit_rule_json(&rule)),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn delete_inhibit_rule(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().delete_inhibit_rule(id).await {
+        Ok(true) => Json(serde_json::json!({ "deleted": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "Inhibit rule not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Webhooks never echo their secret back, only whether one is set.
+fn webhook_json(hook: &db::Webhook) -> serde_json::Value {
+    let mut value = serde_json::to_value(hook).unwrap();
+    value["has_secret"] = serde_json::Value::Bool(hook.secret.is_some());
+    value
+}
+
+async fn get_webhooks(_admin: apikeys::Admin) -> impl IntoResponse {
+    match db::store().get_webhooks().await {
+        Ok(hooks) => Json(serde_json::Value::Array(hooks.iter().map(webhook_json).collect())),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_webhook(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().get_webhook(id).await {
+        Ok(Some(hook)) => Json(webhook_json(&hook)),
+        Ok(None) => Json(serde_json::json!({ "error": "Webhook not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn create_webhook(_admin: apikeys::Admin, Json(input): Json<webhooks::WebhookInput>) -> impl IntoResponse {
+    let mut hook = match webhooks::validate(input, 0, now_secs(), None) {
+        Ok(hook) => hook,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_webhook(&hook).await {
+        Ok(id) => {
+            hook.id = id;
+            Json(webhook_json(&hook))
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn update_webhook(_admin: apikeys::Admin, Path(id): Path<i64>, Json(input): Json<webhooks::WebhookInput>) -> impl IntoResponse {
+    let existing = match db::store().get_webhook(id).await {
+        Ok(Some(hook)) => hook,
+        Ok(None) => return Json(serde_json::json!({ "error": "Webhook not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    };
+
+    let hook = match webhooks::validate(input, id, existing.created_at, existing.secret) {
+        Ok(hook) => hook,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_webhook(&hook).await {
+        Ok(_) => Json(webhook_json(&hook)),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn delete_webhook(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().delete_webhook(id).await {
+        Ok(true) => Json(serde_json::json!({ "deleted": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "Webhook not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct DeliveriesQuery {
+    limit: Option<i64>,
+}
+
+async fn get_webhook_deliveries(_admin: apikeys::Admin, Path(id): Path<i64>, Query(query): Query<DeliveriesQuery>) -> impl IntoResponse {
+    match db::store().get_webhook_deliveries(id, query.limit.unwrap_or(100).clamp(1, 1000)).await {
+        Ok(deliveries) => Json(serde_json::to_value(deliveries).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Sends a sample alert once, without retries, so a receiver can be checked
+/// while setting it up.
+async fn test_webhook(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::store().get_webhook(id).await {
+        Ok(Some(hook)) => Json(serde_json::to_value(webhooks::send_test(&hook).await).unwrap()),
+        Ok(None) => Json(serde_json::json!({ "error": "Webhook not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_email_subscriptions(_admin: apikeys::Admin) -> impl IntoResponse {
+    match db::shared().get_email_subscriptions().await {
+        Ok(subs) => Json(serde_json::to_value(subs).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Subscribes an address to a pubkey's alerts. Admins can subscribe any
+/// pubkey, operators only the ones they verifiably own. Either way the
+/// subscription stays inactive until the address confirms it through the
+/// token mailed to it.
+async fn create_email_subscription(
+    admin: Option<apikeys::Admin>,
+    operator: Option<accounts::Operator>,
+    Json(input): Json<email::SubscriptionInput>,
+) -> Response {
+    if admin.is_none() {
+        let Some(operator) = operator else {
+            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "sign in or use an admin API key" }))).into_response();
+        };
+        match db::shared().is_verified_owner(operator.account.id, &input.pubkey).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return (StatusCode::FORBIDDEN, Json(serde_json::json!({ "error": "you can only subscribe to pubkeys you have verified" })))
+                    .into_response()
             }
+            Err(e) => return Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+        }
+    }
+    if let Err(e) = email::validate_address(&input.email) {
+        return Json(serde_json::json!({ "error": e })).into_response();
+    }
+    if input.pubkey.trim().is_empty() {
+        return Json(serde_json::json!({ "error": "pubkey must not be empty" })).into_response();
+    }
+
+    let digest = input.digest.unwrap_or(false);
+    let (token, token_hash) = email::confirmation_token();
+    let (id, confirmed) = match db::shared().save_pending_email_subscription(&input.email, &input.pubkey, digest, &token_hash, now_secs()).await {
+        Ok(saved) => saved,
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+    };
+    if !confirmed {
+        if let Err(e) = email::send_confirmation(&input.email, &input.pubkey, &token).await {
+            return Json(serde_json::json!({ "error": format!("subscription saved but the confirmation mail failed: {}", e) })).into_response();
+        }
+    }
+    Json(serde_json::json!({ "id": id, "email": input.email, "pubkey": input.pubkey, "digest": digest, "confirmed": confirmed })).into_response()
+}
+
+#[derive(Deserialize)]
+struct ConfirmQuery {
+    token: String,
+}
+
+/// The link in the confirmation mail.
+async fn confirm_email_subscription(Query(query): Query<ConfirmQuery>) -> impl IntoResponse {
+    match db::shared().confirm_email_subscription(&email::hash_token(query.token.trim()), now_secs()).await {
+        Ok(Some(sub)) => Json(serde_json::json!({ "confirmed": sub.id, "email": sub.email, "pubkey": sub.pubkey })),
+        Ok(None) => Json(serde_json::json!({ "error": "Unknown or already used confirmation token" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn delete_email_subscription(_admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    match db::shared().delete_email_subscription(id).await {
+        Ok(true) => Json(serde_json::json!({ "deleted": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "Subscription not found" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_email_log(_admin: apikeys::Admin, Query(query): Query<DeliveriesQuery>) -> impl IntoResponse {
+    match db::shared().get_email_log(query.limit.unwrap_or(100).clamp(1, 1000)).await {
+        Ok(log) => Json(serde_json::to_value(log).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct TestEmailInput {
+    email: String,
+}
+
+async fn test_email(_admin: apikeys::Admin, Json(input): Json<TestEmailInput>) -> impl IntoResponse {
+    if let Err(e) = email::validate_address(&input.email) {
+        return Json(serde_json::json!({ "error": e }));
+    }
+    match email::send_test(&input.email).await {
+        Ok(()) => Json(serde_json::json!({ "sent": input.email })),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn register_account(Json(input): Json<accounts::Credentials>) -> impl IntoResponse {
+    match accounts::register(input, now_secs()).await {
+        Ok((account, session)) => Json(serde_json::json!({
+            "account": account,
+            "token": session.token,
+            "expires_at": session.expires_at,
+        })),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn login_account(Json(input): Json<accounts::Credentials>) -> impl IntoResponse {
+    match accounts::login(input, now_secs()).await {
+        Ok((account, session)) => Json(serde_json::json!({
+            "account": account,
+            "token": session.token,
+            "expires_at": session.expires_at,
+        })),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn logout_account(operator: accounts::Operator) -> impl IntoResponse {
+    match db::shared().delete_session(&operator.token_hash, now_secs()).await {
+        Ok(()) => Json(serde_json::json!({ "signed_out": operator.account.username })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_me(operator: accounts::Operator) -> impl IntoResponse {
+    let id = operator.account.id;
+    match tokio::try_join!(db::shared().get_claimed_pubkeys(id), db::shared().get_watchlist(id)) {
+        Ok((pubkeys, watchlist)) => Json(serde_json::json!({
+            "account": operator.account,
+            "pubkeys": pubkeys,
+            "watchlist": watchlist,
+        })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateAccountInput {
+    email: Option<String>,
+}
+
+/// Changing the email moves the alert subscriptions for claimed pubkeys to the
+/// new address.
+async fn update_me(operator: accounts::Operator, Json(input): Json<UpdateAccountInput>) -> impl IntoResponse {
+    let email = input.email.filter(|e| !e.trim().is_empty());
+    if let Some(email) = &email {
+        if let Err(e) = email::validate_address(email) {
+            return Json(serde_json::json!({ "error": e }));
+        }
+    }
+
+    let mut account = operator.account;
+    match db::shared().update_account_email(account.id, email.as_deref(), now_secs()).await {
+        Ok(()) => {
+            account.email = email;
+            Json(serde_json::to_value(account).unwrap())
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct PubkeyInput {
+    pubkey: String,
+    label: Option<String>,
+}
+
+async fn create_ownership_challenge(operator: accounts::Operator, Json(input): Json<PubkeyInput>) -> impl IntoResponse {
+    match db::store().get_node_by_id(&input.pubkey).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Json(serde_json::json!({ "error": "Node not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    }
+
+    match ownership::issue_challenge(&operator.account, &input.pubkey, now_secs()).await {
+        Ok((message, expires_at)) => Json(serde_json::json!({
+            "pubkey": input.pubkey,
+            "message": message,
+            "expires_at": expires_at,
+        })),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+#[derive(Deserialize)]
+struct ClaimInput {
+    pubkey: String,
+    signature: String,
+}
+
+/// Links a node to the account once the challenge signature checks out, and
+/// subscribes the account's email to its alerts.
+async fn claim_pubkey(operator: accounts::Operator, Json(input): Json<ClaimInput>) -> impl IntoResponse {
+    let account = operator.account;
+    let now = now_secs();
+    if let Err(e) = ownership::verify_claim(&account, &input.pubkey, &input.signature, now).await {
+        return Json(serde_json::json!({ "error": e }));
+    }
+
+    match db::shared().get_claimed_pubkeys(account.id).await {
+        Ok(pubkeys) => Json(serde_json::to_value(pubkeys).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn release_pubkey(operator: accounts::Operator, Path(pubkey): Path<String>) -> impl IntoResponse {
+    match db::shared().release_pubkey(operator.account.id, &pubkey).await {
+        Ok(true) => Json(serde_json::json!({ "released": pubkey })),
+        Ok(false) => Json(serde_json::json!({ "error": "Pubkey not claimed" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_watchlist(operator: accounts::Operator) -> impl IntoResponse {
+    match db::shared().get_watchlist(operator.account.id).await {
+        Ok(entries) => Json(serde_json::to_value(entries).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn add_to_watchlist(operator: accounts::Operator, Json(input): Json<PubkeyInput>) -> impl IntoResponse {
+    if input.pubkey.trim().is_empty() {
+        return Json(serde_json::json!({ "error": "pubkey must not be empty" }));
+    }
+    let id = operator.account.id;
+    match db::shared().save_watchlist_entry(id, &input.pubkey, input.label.as_deref(), now_secs()).await {
+        Ok(()) => match db::shared().get_watchlist(id).await {
+            Ok(entries) => Json(serde_json::to_value(entries).unwrap()),
+            Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
         },
         Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
     }
 }
 
-async fn get_node_history_handler(Path(id): Path<String>) -> impl IntoResponse {
-    match db::get_node_history(&id, 100).await {
-        Ok(history) => Json(serde_json::to_value(history).unwrap()),
+async fn remove_from_watchlist(operator: accounts::Operator, Path(pubkey): Path<String>) -> impl IntoResponse {
+    match db::shared().delete_watchlist_entry(operator.account.id, &pubkey).await {
+        Ok(true) => Json(serde_json::json!({ "removed": pubkey })),
+        Ok(false) => Json(serde_json::json!({ "error": "Pubkey not on watchlist" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Claimed and watched nodes in the same shape as `/pods`, plus which of them
+/// the operator owns. Watched pubkeys not seen yet are left out.
+async fn get_my_nodes(operator: accounts::Operator) -> impl IntoResponse {
+    let id = operator.account.id;
+    let result = async {
+        let owned: Vec<String> = db::shared().get_claimed_pubkeys(id)
+            .await?
+            .into_iter()
+            .filter(|c| c.verified_at.is_some())
+            .map(|c| c.pubkey)
+            .collect();
+        let mut pods = Vec::new();
+        for pubkey in accounts::my_pubkeys(id).await? {
+            if let Some(node) = db::store().get_node_by_id(&pubkey).await? {
+                pods.push(pod_dto(node));
+            }
+        }
+        Ok::<_, sqlx::Error>((owned, pods))
+    }
+    .await;
+
+    match result {
+        Ok((owned, pods)) => Json(serde_json::json!({
+            "total_count": pods.len(),
+            "owned": owned,
+            "pods": pods,
+        })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_my_alerts(operator: accounts::Operator, Query(query): Query<AlertsQuery>) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let result = async {
+        let pubkeys = accounts::my_pubkeys(operator.account.id).await?;
+        db::store().get_alerts_for_subjects(operator.account.id, &pubkeys, query.state.as_deref(), limit).await
+    }
+    .await;
+
+    match result {
+        Ok(alerts) => Json(serde_json::to_value(alerts).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_my_sla(operator: accounts::Operator) -> impl IntoResponse {
+    let now = now_secs();
+    let result = async {
+        let mut reports = Vec::new();
+        for pubkey in accounts::my_pubkeys(operator.account.id).await? {
+            if db::store().get_node_by_id(&pubkey).await?.is_some() {
+                reports.push(sla::node_sla(&pubkey, now).await?);
+            }
+        }
+        Ok::<_, sqlx::Error>(reports)
+    }
+    .await;
+
+    match result {
+        Ok(reports) => Json(serde_json::to_value(reports).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// The operator's private rules. Only verified owners can create them, and only
+/// for their own nodes.
+async fn get_my_alert_rules(operator: accounts::Operator) -> impl IntoResponse {
+    match db::store().get_alert_rules().await {
+        Ok(rules) => {
+            let mine: Vec<_> = rules.into_iter().filter(|r| r.owner_account_id == Some(operator.account.id)).collect();
+            Json(serde_json::to_value(mine).unwrap())
+        }
         Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
     }
 }
+
+async fn validate_private_rule(account_id: i64, input: alerts::RuleInput, id: i64, created_at: i64) -> Result<db::AlertRule, String> {
+    let mut rule = alerts::validate(input, id, created_at)?;
+    let pubkey = rule.pubkey.as_deref().ok_or("private rules must name one of your pubkeys")?;
+    if !db::shared().is_verified_owner(account_id, pubkey).await.map_err(|e| e.to_string())? {
+        return Err("only the verified owner of a node can set private alerts for it".to_string());
+    }
+    rule.owner_account_id = Some(account_id);
+    Ok(rule)
+}
+
+async fn create_my_alert_rule(operator: accounts::Operator, Json(input): Json<alerts::RuleInput>) -> impl IntoResponse {
+    let mut rule = match validate_private_rule(operator.account.id, input, 0, now_secs()).await {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_alert_rule(&rule).await {
+        Ok(id) => {
+            rule.id = id;
+            Json(serde_json::to_value(rule).unwrap())
+        }
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_my_alert_rule(account_id: i64, id: i64) -> Result<Option<db::AlertRule>, sqlx::Error> {
+    Ok(db::store().get_alert_rule(id).await?.filter(|r| r.owner_account_id == Some(account_id)))
+}
+
+async fn update_my_alert_rule(
+    operator: accounts::Operator,
+    Path(id): Path<i64>,
+    Json(input): Json<alerts::RuleInput>,
+) -> impl IntoResponse {
+    let account_id = operator.account.id;
+    let existing = match get_my_alert_rule(account_id, id).await {
+        Ok(Some(rule)) => rule,
+        Ok(None) => return Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    };
+
+    let rule = match validate_private_rule(account_id, input, id, existing.created_at).await {
+        Ok(rule) => rule,
+        Err(e) => return Json(serde_json::json!({ "error": e })),
+    };
+
+    match db::store().save_alert_rule(&rule).await {
+        Ok(_) => Json(serde_json::to_value(rule).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn delete_my_alert_rule(operator: accounts::Operator, Path(id): Path<i64>) -> impl IntoResponse {
+    match get_my_alert_rule(operator.account.id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Json(serde_json::json!({ "error": "Rule not found" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    }
+    match db::store().delete_alert_rule(id).await {
+        Ok(_) => Json(serde_json::json!({ "deleted": id })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Public profile of a node. The contact is left out unless its owner chose to
+/// publish it.
+async fn get_node_profile(Path(id): Path<String>) -> impl IntoResponse {
+    match db::shared().get_node_profile(&id).await {
+        Ok(Some(mut profile)) => {
+            if !profile.contact_public {
+                profile.contact = None;
+            }
+            Json(serde_json::to_value(profile).unwrap())
+        }
+        Ok(None) => Json(serde_json::json!({ "error": "No profile for this node" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct NodeProfileInput {
+    display_name: Option<String>,
+    website: Option<String>,
+    contact: Option<String>,
+    contact_public: Option<bool>,
+}
+
+async fn update_node_profile(
+    operator: accounts::Operator,
+    Path(pubkey): Path<String>,
+    Json(input): Json<NodeProfileInput>,
+) -> impl IntoResponse {
+    let account_id = operator.account.id;
+    match db::shared().is_verified_owner(account_id, &pubkey).await {
+        Ok(true) => {}
+        Ok(false) => return Json(serde_json::json!({ "error": "only the verified owner of a node can edit its profile" })),
+        Err(e) => return Json(serde_json::json!({ "error": e.to_string() })),
+    }
+
+    let clean = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let profile = db::NodeProfile {
+        pubkey,
+        account_id,
+        display_name: clean(input.display_name),
+        website: clean(input.website),
+        contact: clean(input.contact),
+        contact_public: input.contact_public.unwrap_or(false),
+        updated_at: now_secs(),
+    };
+
+    match db::shared().save_node_profile(&profile).await {
+        Ok(()) => Json(serde_json::to_value(profile).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_api_keys(_admin: apikeys::Admin) -> impl IntoResponse {
+    match db::shared().get_api_keys().await {
+        Ok(keys) => Json(serde_json::to_value(keys).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn create_api_key(_admin: apikeys::Admin, Json(input): Json<apikeys::ApiKeyInput>) -> impl IntoResponse {
+    match apikeys::create(input, now_secs()).await {
+        Ok((api_key, key)) => {
+            let mut value = serde_json::to_value(api_key).unwrap();
+            value["key"] = serde_json::json!(key);
+            Json(value)
+        }
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn revoke_api_key(admin: apikeys::Admin, Path(id): Path<i64>) -> impl IntoResponse {
+    if admin.0.id == id {
+        return Json(serde_json::json!({ "error": "an API key cannot revoke itself" }));
+    }
+    match db::shared().revoke_api_key(id, now_secs()).await {
+        Ok(true) => Json(serde_json::json!({ "revoked": id })),
+        Ok(false) => Json(serde_json::json!({ "error": "API key not found or already revoked" })),
+        Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+async fn get_cors_policy(_admin: apikeys::Admin) -> impl IntoResponse {
+    Json(serde_json::to_value(&*cors::current()).unwrap())
+}
+
+async fn reload_cors_policy(_admin: apikeys::Admin) -> impl IntoResponse {
+    match cors::reload() {
+        Ok(policy) => Json(serde_json::to_value(&*policy).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn get_effective_config(_admin: apikeys::Admin) -> impl IntoResponse {
+    Json(serde_json::to_value(config::get().redacted()).unwrap())
+}
+
+/// Observed networks. Seed addresses stay on the admin-only `/admin/seeds`.
+async fn get_networks() -> impl IntoResponse {
+    let default = networks::default();
+    let list: Vec<serde_json::Value> = networks::all()
+        .iter()
+        .map(|n| serde_json::json!({
+            "name": n.name,
+            "default": n.name == default.name,
+            "path": format!("/networks/{}", n.name),
+            "seeds": n.seeds.len(),
+            "refresh_interval_secs": n.refresh_interval_secs,
+            "credits_refresh_interval_secs": n.credits_refresh_interval_secs,
+        }))
+        .collect();
+    Json(serde_json::json!(list))
+}
+
+async fn get_breakers() -> impl IntoResponse {
+    Json(serde_json::to_value(outbound::statuses()).unwrap())
+}
+
+async fn get_seeds(_admin: apikeys::Admin) -> impl IntoResponse {
+    Json(serde_json::to_value(seeds::statuses(now_secs())).unwrap())
+}
+
+async fn reload_seeds(_admin: apikeys::Admin) -> impl IntoResponse {
+    match seeds::reload(now_secs()).await {
+        Ok(_) => Json(serde_json::to_value(seeds::statuses(now_secs())).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn release_seed(_admin: apikeys::Admin, Path(address): Path<String>) -> impl IntoResponse {
+    if seeds::release(&address).await {
+        Json(serde_json::json!({ "released": address }))
+    } else {
+        Json(serde_json::json!({ "error": "Seed not found" }))
+    }
+}
+
+/// The current network's backups, newest first.
+async fn get_backups(_admin: apikeys::Admin) -> impl IntoResponse {
+    Json(serde_json::to_value(backup::list()).unwrap())
+}
+
+/// Takes a backup of the current network's database now.
+async fn create_backup(_admin: apikeys::Admin) -> impl IntoResponse {
+    match backup::snapshot().await {
+        Ok(b) => Json(serde_json::to_value(b).unwrap()),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
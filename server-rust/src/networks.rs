@@ -0,0 +1,92 @@
+use std::future::Future;
+
+use axum::extract::Request;
+use axum::http::{StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use once_cell::sync::OnceCell;
+
+use crate::config::Network;
+
+const PREFIX: &str = "/networks/";
+
+static NETWORKS: OnceCell<Vec<Network>> = OnceCell::new();
+
+tokio::task_local! {
+    // The network whose database, seeds and caches the current task works on.
+    static CURRENT: &'static Network;
+}
+
+/// Resolves the configured network profiles. Called once at startup.
+pub fn init() {
+    NETWORKS.set(crate::config::get().networks()).expect("networks initialised twice");
+}
+
+pub fn all() -> &'static [Network] {
+    NETWORKS.get().expect("networks not initialised")
+}
+
+pub fn find(name: &str) -> Option<&'static Network> {
+    all().iter().find(|n| n.name == name)
+}
+
+pub fn default() -> &'static Network {
+    find(&crate::config::get().server.default_network).expect("default network is validated")
+}
+
+/// The network the current task belongs to; the default one outside any
+/// [`scope`].
+pub fn current() -> &'static Network {
+    CURRENT.try_with(|n| *n).unwrap_or_else(|_| default())
+}
+
+/// Runs `f` on behalf of `network`.
+pub async fn scope<F: Future>(network: &'static Network, f: F) -> F::Output {
+    CURRENT.scope(network, f).await
+}
+
+/// `tokio::spawn` keeping the caller's network; a plain spawn would fall back
+/// to the default one.
+pub fn spawn<F>(f: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(scope(current(), f))
+}
+
+/// Splits `/networks/<name>/rest` into the network name and `/rest`.
+fn split(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(PREFIX)?;
+    match rest.find('/') {
+        Some(i) => Some((&rest[..i], &rest[i..])),
+        None => Some((rest, "/")),
+    }
+}
+
+/// Serves `/networks/<name>/...` as the unprefixed route scoped to that
+/// network, so every route exists once per network. Everything else runs
+/// against the default network. Must wrap the router, as routing happens on
+/// the rewritten path.
+pub async fn route(mut request: Request, next: Next) -> Response {
+    let Some((name, rest)) = split(request.uri().path()) else {
+        return scope(default(), next.run(request)).await;
+    };
+    let Some(network) = find(name) else {
+        let msg = format!("unknown network '{}'", name);
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": msg }))).into_response();
+    };
+
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{}?{}", rest, query),
+        None => rest.to_string(),
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = path_and_query.parse().ok();
+    match Uri::from_parts(parts) {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    }
+    scope(network, next.run(request)).await
+}
@@ -0,0 +1,703 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::AlertNotice;
+use crate::db::{self, AlertGroup, FiringAlertLabels, InhibitRule, Silence};
+
+/// Alert fields that silences and inhibition rules can match on.
+pub const LABELS: [&str; 5] = ["rule_id", "rule_name", "kind", "severity", "subject"];
+pub const OPERATORS: [&str; 4] = ["=", "!=", "=~", "!~"];
+
+// Grouped notifications name this many subjects and count the rest.
+const MAX_LISTED_SUBJECTS: usize = 10;
+
+type Labels = HashMap<&'static str, String>;
+
+/// Alertmanager-style matcher. Regex operators match the whole label value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Matcher {
+    pub name: String,
+    #[serde(default = "default_op")]
+    pub op: String,
+    pub value: String,
+}
+
+fn default_op() -> String {
+    "=".to_string()
+}
+
+fn anchored(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+impl Matcher {
+    fn validate(&self) -> Result<(), String> {
+        if !LABELS.contains(&self.name.as_str()) {
+            return Err(format!("unknown label '{}', expected one of {}", self.name, LABELS.join(", ")));
+        }
+        if !OPERATORS.contains(&self.op.as_str()) {
+            return Err(format!("unknown operator '{}', expected one of {}", self.op, OPERATORS.join(", ")));
+        }
+        if self.op.ends_with('~') {
+            anchored(&self.value).map_err(|e| format!("invalid regex for {}: {}", self.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Compiles the regex, if the operator takes one, so matching many
+    /// alerts doesn't recompile it each time.
+    fn compile(self) -> CompiledMatcher {
+        let regex = if self.op.ends_with('~') { anchored(&self.value).ok() } else { None };
+        CompiledMatcher { matcher: self, regex }
+    }
+}
+
+/// A matcher as `route` evaluates it, holding its compiled regex.
+struct CompiledMatcher {
+    matcher: Matcher,
+    regex: Option<Regex>,
+}
+
+impl CompiledMatcher {
+    fn matches(&self, labels: &Labels) -> bool {
+        let actual = labels.get(self.matcher.name.as_str()).map(String::as_str).unwrap_or_default();
+        match self.matcher.op.as_str() {
+            "=" => actual == self.matcher.value,
+            "!=" => actual != self.matcher.value,
+            op => {
+                let hit = self.regex.as_ref().is_some_and(|re| re.is_match(actual));
+                hit == (op == "=~")
+            }
+        }
+    }
+}
+
+fn all_match(matchers: &[CompiledMatcher], labels: &Labels) -> bool {
+    matchers.iter().all(|m| m.matches(labels))
+}
+
+fn validate_matchers(field: &str, matchers: &[Matcher]) -> Result<(), String> {
+    if matchers.is_empty() {
+        return Err(format!("{} must contain at least one matcher", field));
+    }
+    matchers.iter().try_for_each(Matcher::validate)
+}
+
+/// Matchers as stored in the database; they were validated on the way in.
+fn parse_matchers(raw: &str) -> Vec<Matcher> {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+fn compile_matchers(raw: &str) -> Vec<CompiledMatcher> {
+    parse_matchers(raw).into_iter().map(Matcher::compile).collect()
+}
+
+fn notice_labels(n: &AlertNotice) -> Labels {
+    HashMap::from([
+        ("rule_id", n.rule_id.to_string()),
+        ("rule_name", n.rule_name.clone()),
+        ("kind", n.kind.clone()),
+        ("severity", n.severity.clone()),
+        ("subject", n.subject.clone()),
+    ])
+}
+
+fn firing_labels(a: &FiringAlertLabels) -> Labels {
+    HashMap::from([
+        ("rule_id", a.rule_id.to_string()),
+        ("rule_name", a.rule_name.clone()),
+        ("kind", a.kind.clone()),
+        ("severity", a.severity.clone()),
+        ("subject", a.subject.clone()),
+    ])
+}
+
+/// Body accepted when creating a silence. The end is either `ends_at` or
+/// `duration_secs` after the start, which defaults to now.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SilenceInput {
+    pub matchers: Vec<Matcher>,
+    pub starts_at: Option<i64>,
+    pub ends_at: Option<i64>,
+    pub duration_secs: Option<i64>,
+    pub comment: String,
+    pub created_by: Option<String>,
+}
+
+pub fn validate_silence(input: SilenceInput, now: i64) -> Result<Silence, String> {
+    validate_matchers("matchers", &input.matchers)?;
+    if input.comment.trim().is_empty() {
+        return Err("comment must say why the alerts are silenced".to_string());
+    }
+    let starts_at = input.starts_at.unwrap_or(now);
+    let ends_at = match (input.ends_at, input.duration_secs) {
+        (Some(end), _) => end,
+        (None, Some(secs)) => starts_at + secs,
+        (None, None) => return Err("either ends_at or duration_secs is required".to_string()),
+    };
+    if ends_at <= starts_at || ends_at <= now {
+        return Err("silence must end in the future and after it starts".to_string());
+    }
+
+    Ok(Silence {
+        id: 0,
+        matchers: serde_json::to_string(&input.matchers).unwrap(),
+        starts_at,
+        ends_at,
+        comment: input.comment,
+        created_by: input.created_by,
+        created_at: now,
+    })
+}
+
+pub fn silence_json(silence: &Silence, now: i64) -> serde_json::Value {
+    let status = if silence.ends_at <= now {
+        "expired"
+    } else if silence.starts_at > now {
+        "pending"
+    } else {
+        "active"
+    };
+    serde_json::json!({
+        "id": silence.id,
+        "matchers": parse_matchers(&silence.matchers),
+        "starts_at": silence.starts_at,
+        "ends_at": silence.ends_at,
+        "comment": silence.comment,
+        "created_by": silence.created_by,
+        "created_at": silence.created_at,
+        "status": status,
+    })
+}
+
+/// Body accepted when creating or replacing an inhibition rule.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InhibitRuleInput {
+    pub name: String,
+    pub source_matchers: Vec<Matcher>,
+    pub target_matchers: Vec<Matcher>,
+    pub equal: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+pub fn validate_inhibit_rule(input: InhibitRuleInput, id: i64, created_at: i64) -> Result<InhibitRule, String> {
+    if input.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    validate_matchers("source_matchers", &input.source_matchers)?;
+    validate_matchers("target_matchers", &input.target_matchers)?;
+    let equal = input.equal.unwrap_or_default();
+    if let Some(label) = equal.iter().find(|l| !LABELS.contains(&l.as_str())) {
+        return Err(format!("unknown label '{}' in equal, expected one of {}", label, LABELS.join(", ")));
+    }
+
+    Ok(InhibitRule {
+        id,
+        name: input.name,
+        source_matchers: serde_json::to_string(&input.source_matchers).unwrap(),
+        target_matchers: serde_json::to_string(&input.target_matchers).unwrap(),
+        equal: equal.join(","),
+        enabled: input.enabled.unwrap_or(true),
+        created_at,
+    })
+}
+
+pub fn inhibit_rule_json(rule: &InhibitRule) -> serde_json::Value {
+    serde_json::json!({
+        "id": rule.id,
+        "name": rule.name,
+        "source_matchers": parse_matchers(&rule.source_matchers),
+        "target_matchers": parse_matchers(&rule.target_matchers),
+        "equal": rule.equal.split(',').filter(|l| !l.is_empty()).collect::<Vec<_>>(),
+        "enabled": rule.enabled,
+        "created_at": rule.created_at,
+    })
+}
+
+/// An enabled inhibition rule with its matchers parsed.
+struct Inhibition {
+    id: i64,
+    source: Vec<CompiledMatcher>,
+    target: Vec<CompiledMatcher>,
+    equal: Vec<String>,
+}
+
+/// One notification for a group of alerts that changed state together. With a
+/// single alert the subject, message and value are that alert's own.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub network: String,
+    pub group_key: String,
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub kind: String,
+    pub severity: String,
+    pub state: String,
+    pub subject: String,
+    pub value: Option<f64>,
+    pub message: String,
+    pub at: i64,
+    pub count: usize,
+    #[serde(skip)]
+    pub owner_account_id: Option<i64>,
+    pub alerts: Vec<AlertNotice>,
+}
+
+impl Notification {
+    /// `alerts` must not be empty.
+    pub fn new(group_key: &str, state: &str, alerts: Vec<AlertNotice>) -> Self {
+        let first = &alerts[0];
+        let (subject, value, message) = if alerts.len() == 1 {
+            (first.subject.clone(), first.value, first.message.clone())
+        } else {
+            let listed: Vec<&str> = alerts.iter().take(MAX_LISTED_SUBJECTS).map(|a| a.subject.as_str()).collect();
+            let more = alerts.len().saturating_sub(MAX_LISTED_SUBJECTS);
+            let message = format!(
+                "{} alerts: {}{}",
+                alerts.len(),
+                listed.join(", "),
+                if more > 0 { format!(" and {} more", more) } else { String::new() }
+            );
+            (format!("{} subjects", alerts.len()), None, message)
+        };
+
+        Notification {
+            network: crate::networks::current().name.clone(),
+            group_key: group_key.to_string(),
+            rule_id: first.rule_id,
+            rule_name: first.rule_name.clone(),
+            kind: first.kind.clone(),
+            severity: first.severity.clone(),
+            state: state.to_string(),
+            subject,
+            value,
+            message,
+            at: first.at,
+            count: alerts.len(),
+            owner_account_id: first.owner_account_id,
+            alerts,
+        }
+    }
+}
+
+/// What survives silencing, inhibition and deduplication for one cycle.
+pub struct Routed {
+    /// Grouped notifications for channels that fan out to everyone (webhooks).
+    pub notifications: Vec<Notification>,
+    /// Individual notices that weren't silenced or inhibited, for per-operator
+    /// channels (email) that only ever see their own nodes.
+    pub notices: Vec<AlertNotice>,
+}
+
+/// Decides which of a cycle's alert notices notify anyone. Silenced and
+/// inhibited alerts are marked with what suppressed them, and their eventual
+/// resolution stays quiet too; an alert that fires while silenced is not
+/// announced later when the silence ends. The rest are grouped by their rule's
+/// group key: a group notifies when it opens and once more when its last
+/// announced alert resolves, and alerts joining an open group only add to it.
+/// Retired alerts never notify, but still let their group close.
+pub async fn route(cycle_at: i64, notices: &[AlertNotice]) -> Result<Routed, sqlx::Error> {
+    let silences: Vec<(i64, Vec<CompiledMatcher>)> = db::store().get_silences(false, cycle_at)
+        .await?
+        .into_iter()
+        .filter(|s| s.starts_at <= cycle_at)
+        .map(|s| (s.id, compile_matchers(&s.matchers)))
+        .collect();
+    let inhibitions: Vec<Inhibition> = db::store().get_inhibit_rules()
+        .await?
+        .into_iter()
+        .filter(|r| r.enabled)
+        .map(|r| Inhibition {
+            id: r.id,
+            source: compile_matchers(&r.source_matchers),
+            target: compile_matchers(&r.target_matchers),
+            equal: r.equal.split(',').filter(|l| !l.is_empty()).map(str::to_string).collect(),
+        })
+        .collect();
+    let firing: Vec<(i64, Labels)> = if inhibitions.is_empty() {
+        Vec::new()
+    } else {
+        db::store().get_firing_alert_labels().await?.iter().map(|a| (a.id, firing_labels(a))).collect()
+    };
+
+    let (retired, notices): (Vec<&AlertNotice>, Vec<&AlertNotice>) = notices.iter().partition(|n| n.state == "retired");
+    let mut passed: Vec<AlertNotice> = Vec::new();
+    for notice in notices {
+        let labels = notice_labels(notice);
+        let suppressed_by = if notice.state == "resolved" {
+            // A resolution is only news to whoever heard the alert fire.
+            db::store().get_alert(notice.alert_id).await?.and_then(|a| a.suppressed_by)
+        } else if let Some((id, _)) = silences.iter().find(|(_, m)| all_match(m, &labels)) {
+            Some(format!("silence:{}", id))
+        } else {
+            inhibitions
+                .iter()
+                .find(|rule| {
+                    all_match(&rule.target, &labels)
+                        && firing.iter().any(|(alert_id, src)| {
+                            *alert_id != notice.alert_id
+                                && all_match(&rule.source, src)
+                                && rule.equal.iter().all(|l| src.get(l.as_str()) == labels.get(l.as_str()))
+                        })
+                })
+                .map(|rule| format!("inhibit:{}", rule.id))
+        };
+
+        db::store().set_alert_routing(notice.alert_id, Some(&notice.group_key), suppressed_by.as_deref()).await?;
+        if suppressed_by.is_none() {
+            passed.push(notice.clone());
+        }
+    }
+
+    let mut order: Vec<&str> = Vec::new();
+    let mut groups: HashMap<&str, Vec<AlertNotice>> = HashMap::new();
+    for notice in &passed {
+        if !groups.contains_key(notice.group_key.as_str()) {
+            order.push(&notice.group_key);
+        }
+        groups.entry(&notice.group_key).or_default().push(notice.clone());
+    }
+
+    let mut notifications = Vec::new();
+    for key in order {
+        let (fired, resolved): (Vec<AlertNotice>, Vec<AlertNotice>) =
+            groups.remove(key).unwrap_or_default().into_iter().partition(|n| n.state == "firing");
+        let mut group = db::store().get_alert_group(key).await?;
+        let open = group.as_ref().is_some_and(|g| g.state == "firing");
+
+        if !fired.is_empty() {
+            match group.as_mut().filter(|_| open) {
+                Some(g) => g.alerts += fired.len() as i64,
+                None => {
+                    group = Some(AlertGroup {
+                        group_key: key.to_string(),
+                        rule_id: fired[0].rule_id,
+                        state: "firing".to_string(),
+                        opened_at: cycle_at,
+                        notified_at: cycle_at,
+                        resolved_at: None,
+                        alerts: fired.len() as i64,
+                    });
+                    notifications.push(Notification::new(key, "firing", fired));
+                }
+            }
+        }
+
+        // Groups from before grouping existed have no row but were announced
+        // alert by alert, so their resolution is still worth a notification.
+        let closed = group.as_ref().is_some_and(|g| g.state == "resolved");
+        if !resolved.is_empty() && !closed && db::store().count_notified_firing_in_group(key).await? == 0 {
+            let g = group.get_or_insert_with(|| AlertGroup {
+                group_key: key.to_string(),
+                rule_id: resolved[0].rule_id,
+                state: "firing".to_string(),
+                opened_at: cycle_at,
+                notified_at: cycle_at,
+                resolved_at: None,
+                alerts: resolved.len() as i64,
+            });
+            g.state = "resolved".to_string();
+            g.notified_at = cycle_at;
+            g.resolved_at = Some(cycle_at);
+            notifications.push(Notification::new(key, "resolved", resolved));
+        }
+
+        if let Some(g) = &group {
+            db::store().save_alert_group(g).await?;
+        }
+    }
+
+    // A retired alert closes a group nobody is left hearing about, silently.
+    for notice in retired {
+        let key = notice.group_key.as_str();
+        if let Some(mut g) = db::store().get_alert_group(key).await?.filter(|g| g.state == "firing") {
+            if db::store().count_notified_firing_in_group(key).await? == 0 {
+                g.state = "resolved".to_string();
+                g.resolved_at = Some(cycle_at);
+                db::store().save_alert_group(&g).await?;
+            }
+        }
+    }
+
+    Ok(Routed { notifications, notices: passed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Alert, AlertRule};
+    use crate::testing;
+
+    fn matcher(name: &str, op: &str, value: &str) -> Matcher {
+        Matcher { name: name.to_string(), op: op.to_string(), value: value.to_string() }
+    }
+
+    fn labels(subject: &str) -> Labels {
+        HashMap::from([("kind", "offline".to_string()), ("severity", "critical".to_string()), ("subject", subject.to_string())])
+    }
+
+    fn silence_input(ends_at: Option<i64>, duration_secs: Option<i64>) -> SilenceInput {
+        SilenceInput {
+            matchers: vec![matcher("subject", "=", "node-1")],
+            starts_at: None,
+            ends_at,
+            duration_secs,
+            comment: "maintenance".to_string(),
+            created_by: None,
+        }
+    }
+
+    fn inhibit_input(equal: &[&str]) -> InhibitRuleInput {
+        InhibitRuleInput {
+            name: "network down".to_string(),
+            source_matchers: vec![matcher("kind", "=", "network_offline")],
+            target_matchers: vec![matcher("kind", "=", "offline")],
+            equal: Some(equal.iter().map(|l| l.to_string()).collect()),
+            enabled: None,
+        }
+    }
+
+    async fn stored_rule(name: &str, group_by: &str) -> AlertRule {
+        let mut rule = AlertRule {
+            id: 0,
+            name: name.to_string(),
+            kind: "offline".to_string(),
+            pubkey: None,
+            threshold: None,
+            min_version: None,
+            duration_secs: 0,
+            severity: "critical".to_string(),
+            enabled: true,
+            created_at: 0,
+            group_by: group_by.to_string(),
+            owner_account_id: None,
+        };
+        rule.id = db::store().save_alert_rule(&rule).await.unwrap();
+        rule
+    }
+
+    /// Stores a firing alert of `rule` for `subject` and returns its notice.
+    async fn firing(rule: &AlertRule, subject: &str, at: i64) -> AlertNotice {
+        let alert = Alert {
+            id: 0,
+            rule_id: rule.id,
+            subject: subject.to_string(),
+            state: "firing".to_string(),
+            value: None,
+            message: format!("{} is offline", subject),
+            started_at: at,
+            fired_at: Some(at),
+            resolved_at: None,
+            updated_at: at,
+            group_key: None,
+            suppressed_by: None,
+        };
+        let alert_id = db::store().insert_alert(&alert).await.unwrap();
+        AlertNotice {
+            alert_id,
+            rule_id: rule.id,
+            rule_name: rule.name.clone(),
+            kind: rule.kind.clone(),
+            severity: rule.severity.clone(),
+            subject: subject.to_string(),
+            state: "firing".to_string(),
+            value: None,
+            message: alert.message,
+            at,
+            group_key: match rule.group_by.as_str() {
+                "subject" => format!("rule:{}/subject:{}", rule.id, subject),
+                _ => format!("rule:{}", rule.id),
+            },
+            owner_account_id: None,
+        }
+    }
+
+    /// Marks the alert behind `notice` resolved and returns the matching notice.
+    async fn resolve(notice: &AlertNotice, at: i64) -> AlertNotice {
+        let mut alert = db::store().get_alert(notice.alert_id).await.unwrap().unwrap();
+        alert.state = "resolved".to_string();
+        alert.resolved_at = Some(at);
+        alert.updated_at = at;
+        db::store().update_alert(&alert).await.unwrap();
+        AlertNotice { state: "resolved".to_string(), at, ..notice.clone() }
+    }
+
+    #[test]
+    fn matchers_compare_whole_label_values() {
+        let labels = labels("node-12");
+        assert!(matcher("subject", "=", "node-12").compile().matches(&labels));
+        assert!(!matcher("subject", "=", "node-1").compile().matches(&labels));
+        assert!(matcher("subject", "!=", "node-1").compile().matches(&labels));
+        assert!(matcher("subject", "=~", "node-[0-9]+").compile().matches(&labels));
+        assert!(!matcher("subject", "=~", "node-1").compile().matches(&labels), "regexes are anchored");
+        assert!(matcher("subject", "!~", "node-1").compile().matches(&labels));
+        assert!(matcher("severity", "=~", "warning|critical").compile().matches(&labels));
+        // Labels the alert doesn't carry compare as empty.
+        assert!(matcher("rule_name", "=", "").compile().matches(&labels));
+        assert!(!matcher("rule_name", "=~", ".+").compile().matches(&labels));
+    }
+
+    #[test]
+    fn matchers_are_validated() {
+        assert!(validate_matchers("matchers", &[]).unwrap_err().contains("at least one matcher"));
+        let err = validate_matchers("matchers", &[matcher("pubkey", "=", "x")]).unwrap_err();
+        assert!(err.starts_with("unknown label 'pubkey'"), "{}", err);
+        let err = validate_matchers("matchers", &[matcher("subject", "~", "x")]).unwrap_err();
+        assert!(err.starts_with("unknown operator '~'"), "{}", err);
+        let err = validate_matchers("matchers", &[matcher("subject", "=~", "node-(")]).unwrap_err();
+        assert!(err.starts_with("invalid regex for subject"), "{}", err);
+        // Only regex operators compile the value.
+        assert!(validate_matchers("matchers", &[matcher("subject", "=", "node-(")]).is_ok());
+    }
+
+    #[test]
+    fn silence_end_comes_from_ends_at_or_duration() {
+        let now = 1_000;
+        let silence = validate_silence(silence_input(None, Some(3600)), now).unwrap();
+        assert_eq!((silence.starts_at, silence.ends_at), (now, now + 3600));
+        let silence = validate_silence(silence_input(Some(now + 60), Some(3600)), now).unwrap();
+        assert_eq!(silence.ends_at, now + 60);
+
+        let err = validate_silence(silence_input(None, None), now).unwrap_err();
+        assert_eq!(err, "either ends_at or duration_secs is required");
+        let err = validate_silence(silence_input(Some(now), None), now).unwrap_err();
+        assert_eq!(err, "silence must end in the future and after it starts");
+        let err = validate_silence(SilenceInput { comment: " ".to_string(), ..silence_input(None, Some(60)) }, now).unwrap_err();
+        assert_eq!(err, "comment must say why the alerts are silenced");
+    }
+
+    #[test]
+    fn silence_status_follows_its_window() {
+        let silence = Silence {
+            id: 1,
+            matchers: serde_json::to_string(&[matcher("subject", "=", "node-1")]).unwrap(),
+            starts_at: 100,
+            ends_at: 200,
+            comment: "maintenance".to_string(),
+            created_by: None,
+            created_at: 50,
+        };
+        assert_eq!(silence_json(&silence, 50)["status"], "pending");
+        assert_eq!(silence_json(&silence, 100)["status"], "active");
+        assert_eq!(silence_json(&silence, 200)["status"], "expired");
+        assert_eq!(silence_json(&silence, 100)["matchers"][0]["value"], "node-1");
+    }
+
+    #[test]
+    fn inhibit_rules_check_their_equal_labels() {
+        let rule = validate_inhibit_rule(inhibit_input(&["subject", "severity"]), 7, 10).unwrap();
+        assert_eq!((rule.id, rule.equal.as_str(), rule.enabled), (7, "subject,severity", true));
+        assert_eq!(inhibit_rule_json(&rule)["equal"], serde_json::json!(["subject", "severity"]));
+
+        let err = validate_inhibit_rule(inhibit_input(&["pubkey"]), 0, 0).unwrap_err();
+        assert!(err.starts_with("unknown label 'pubkey' in equal"), "{}", err);
+        let err = validate_inhibit_rule(InhibitRuleInput { target_matchers: vec![], ..inhibit_input(&[]) }, 0, 0).unwrap_err();
+        assert_eq!(err, "target_matchers must contain at least one matcher");
+    }
+
+    #[test]
+    fn silenced_alerts_are_dropped_and_stay_quiet_when_resolved() {
+        testing::run(async {
+            let rule = stored_rule("notify-silence", "subject").await;
+            let silence = validate_silence(
+                SilenceInput { matchers: vec![matcher("subject", "=~", "silenced-[0-9]+")], ..silence_input(None, Some(3600)) },
+                100,
+            )
+            .unwrap();
+            let silence_id = db::store().insert_silence(&silence).await.unwrap();
+
+            let quiet = firing(&rule, "silenced-1", 200).await;
+            let loud = firing(&rule, "silenced-1x", 200).await;
+            let routed = route(200, &[quiet.clone(), loud.clone()]).await.unwrap();
+            assert_eq!(routed.notices.iter().map(|n| n.alert_id).collect::<Vec<_>>(), vec![loud.alert_id]);
+            assert_eq!(routed.notifications.len(), 1);
+            let stored = db::store().get_alert(quiet.alert_id).await.unwrap().unwrap();
+            assert_eq!(stored.suppressed_by, Some(format!("silence:{}", silence_id)));
+
+            // Outside the silence the resolution is still not announced.
+            let resolved = resolve(&quiet, 10_000).await;
+            let routed = route(10_000, &[resolved]).await.unwrap();
+            assert!(routed.notices.is_empty() && routed.notifications.is_empty());
+        });
+    }
+
+    #[test]
+    fn inhibition_needs_a_firing_source_with_equal_labels() {
+        testing::run(async {
+            let source_rule = stored_rule("notify-inhibit-source", "subject").await;
+            let target_rule = stored_rule("notify-inhibit-target", "subject").await;
+            let input = InhibitRuleInput {
+                name: "source covers target".to_string(),
+                source_matchers: vec![matcher("rule_name", "=", "notify-inhibit-source")],
+                target_matchers: vec![matcher("rule_name", "=", "notify-inhibit-target")],
+                equal: Some(vec!["subject".to_string()]),
+                enabled: None,
+            };
+            let rule_id = db::store().save_inhibit_rule(&validate_inhibit_rule(input, 0, 0).unwrap()).await.unwrap();
+
+            let covered = firing(&target_rule, "inhibit-a", 100).await;
+            let other = firing(&target_rule, "inhibit-b", 100).await;
+            // Nothing is inhibited before the source fires.
+            let routed = route(100, std::slice::from_ref(&covered)).await.unwrap();
+            assert_eq!(routed.notices.len(), 1);
+
+            firing(&source_rule, "inhibit-a", 200).await;
+            let routed = route(200, &[covered.clone(), other.clone()]).await.unwrap();
+            assert_eq!(routed.notices.iter().map(|n| n.alert_id).collect::<Vec<_>>(), vec![other.alert_id]);
+            let stored = db::store().get_alert(covered.alert_id).await.unwrap().unwrap();
+            assert_eq!(stored.suppressed_by, Some(format!("inhibit:{}", rule_id)));
+        });
+    }
+
+    #[test]
+    fn groups_notify_when_they_open_and_when_they_resolve() {
+        testing::run(async {
+            let rule = stored_rule("notify-group", "rule").await;
+            let first = firing(&rule, "group-1", 100).await;
+            let second = firing(&rule, "group-2", 100).await;
+
+            let routed = route(100, &[first.clone(), second.clone()]).await.unwrap();
+            assert_eq!(routed.notifications.len(), 1);
+            let opened = &routed.notifications[0];
+            assert_eq!((opened.state.as_str(), opened.count, opened.subject.as_str()), ("firing", 2, "2 subjects"));
+            assert_eq!(opened.message, "2 alerts: group-1, group-2");
+
+            // Joining an open group passes the notice on but doesn't notify.
+            let third = firing(&rule, "group-3", 200).await;
+            let routed = route(200, std::slice::from_ref(&third)).await.unwrap();
+            assert!(routed.notifications.is_empty());
+            assert_eq!(routed.notices.len(), 1);
+
+            let routed = route(300, &[resolve(&first, 300).await, resolve(&second, 300).await]).await.unwrap();
+            assert!(routed.notifications.is_empty(), "group-3 is still firing");
+
+            let routed = route(400, &[resolve(&third, 400).await]).await.unwrap();
+            assert_eq!(routed.notifications.len(), 1);
+            assert_eq!((routed.notifications[0].state.as_str(), routed.notifications[0].count), ("resolved", 1));
+            let group = db::store().get_alert_group(&first.group_key).await.unwrap().unwrap();
+            assert_eq!((group.state.as_str(), group.resolved_at), ("resolved", Some(400)));
+        });
+    }
+
+    #[test]
+    fn retired_alerts_close_their_group_without_notifying() {
+        testing::run(async {
+            let rule = stored_rule("notify-retired", "rule").await;
+            let gone = firing(&rule, "retired-1", 100).await;
+            route(100, std::slice::from_ref(&gone)).await.unwrap();
+
+            let retired = AlertNotice { state: "retired".to_string(), ..resolve(&gone, 200).await };
+            let routed = route(200, &[retired]).await.unwrap();
+            assert!(routed.notices.is_empty() && routed.notifications.is_empty());
+            let group = db::store().get_alert_group(&gone.group_key).await.unwrap().unwrap();
+            assert_eq!((group.state.as_str(), group.resolved_at), ("resolved", Some(200)));
+
+            // The next alert of the rule opens the group afresh.
+            let next = firing(&rule, "retired-2", 300).await;
+            let routed = route(300, &[next]).await.unwrap();
+            assert_eq!(routed.notifications.len(), 1);
+        });
+    }
+}
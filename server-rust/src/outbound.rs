@@ -0,0 +1,244 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Serialize;
+
+/// The one HTTP client for everything the observer calls out to, so
+/// connections to seeds and APIs are pooled and reused across cycles.
+pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    let policy = &crate::config::get().outbound;
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(policy.connect_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build()
+        .expect("Failed to build HTTP client")
+});
+
+static BREAKERS: Lazy<DashMap<String, Breaker>> = Lazy::new(DashMap::new);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through.
+    Closed,
+    /// Calls fail fast until the cool-down is over.
+    Open,
+    /// One trial call is let through to decide whether to close again.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened: Option<Instant>,
+    opened_at: Option<i64>,
+    probe_in_flight: bool,
+    successes: u64,
+    failures: u64,
+    rejected: u64,
+    last_error: Option<String>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Breaker {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            opened: None,
+            opened_at: None,
+            probe_in_flight: false,
+            successes: 0,
+            failures: 0,
+            rejected: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// A breaker as served by `/observer/breakers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub endpoint: String,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub opened_at: Option<i64>,
+    /// Seconds until an open breaker lets a trial call through.
+    pub retry_in_secs: Option<u64>,
+    pub successes: u64,
+    pub failures: u64,
+    pub rejected: u64,
+    pub last_error: Option<String>,
+}
+
+/// Why a call through [`call`] failed.
+#[derive(Debug, Clone)]
+pub enum CallError {
+    /// The breaker is open; nothing was sent.
+    Open,
+    Failed(String),
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::Open => write!(f, "circuit open"),
+            CallError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A failed attempt, and whether trying again could help.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl Failure {
+    pub fn permanent(message: impl Into<String>) -> Self {
+        Failure { message: message.into(), retryable: false }
+    }
+
+    /// Network errors and timeouts are worth another attempt.
+    pub fn request(e: reqwest::Error) -> Self {
+        Failure { message: e.to_string(), retryable: true }
+    }
+
+    /// 5xx and 429 are worth another attempt; other statuses aren't.
+    pub fn status(status: reqwest::StatusCode) -> Self {
+        Failure {
+            message: format!("HTTP {}", status.as_u16()),
+            retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+/// Whether a call to `endpoint` may go out now, moving an open breaker whose
+/// cool-down is over to half-open.
+fn allow(endpoint: &str) -> bool {
+    let open_for = Duration::from_secs(crate::config::get().outbound.breaker_open_secs);
+    let mut breaker = BREAKERS.entry(endpoint.to_string()).or_default();
+    let allowed = match breaker.state {
+        BreakerState::Closed => true,
+        BreakerState::Open if breaker.opened.is_some_and(|at| at.elapsed() >= open_for) => {
+            breaker.state = BreakerState::HalfOpen;
+            breaker.probe_in_flight = true;
+            true
+        }
+        BreakerState::Open => false,
+        BreakerState::HalfOpen if !breaker.probe_in_flight => {
+            breaker.probe_in_flight = true;
+            true
+        }
+        BreakerState::HalfOpen => false,
+    };
+    if !allowed {
+        breaker.rejected += 1;
+    }
+    allowed
+}
+
+fn record(endpoint: &str, result: Result<(), &str>) {
+    let threshold = crate::config::get().outbound.breaker_failure_threshold;
+    let mut breaker = BREAKERS.entry(endpoint.to_string()).or_default();
+    breaker.probe_in_flight = false;
+    match result {
+        Ok(()) => {
+            if breaker.state != BreakerState::Closed {
+                println!("Circuit for {} closed again", endpoint);
+            }
+            breaker.successes += 1;
+            breaker.consecutive_failures = 0;
+            breaker.state = BreakerState::Closed;
+            breaker.opened = None;
+            breaker.opened_at = None;
+        }
+        Err(e) => {
+            breaker.failures += 1;
+            breaker.consecutive_failures += 1;
+            breaker.last_error = Some(e.to_string());
+            // A failed trial reopens straight away.
+            if breaker.state == BreakerState::HalfOpen || breaker.consecutive_failures >= threshold {
+                if breaker.state == BreakerState::Closed {
+                    println!("Circuit for {} opened after {} failures: {}", endpoint, breaker.consecutive_failures, e);
+                }
+                breaker.state = BreakerState::Open;
+                breaker.opened = Some(Instant::now());
+                breaker.opened_at = Some(crate::now_secs());
+            }
+        }
+    }
+}
+
+/// Full-jitter exponential backoff before retry number `retry` (0-based).
+fn backoff(retry: u32) -> Duration {
+    let policy = &crate::config::get().outbound;
+    let cap = policy.backoff_base_ms.saturating_mul(1 << retry.min(16)).min(policy.backoff_max_ms);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+}
+
+/// Runs `attempt` against `endpoint` behind its circuit breaker, retrying
+/// retryable failures with jittered backoff. The whole call, retries
+/// included, counts as one success or failure for the breaker.
+pub async fn call<T, F, Fut>(endpoint: &str, mut attempt: F) -> Result<T, CallError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Failure>>,
+{
+    if !allow(endpoint) {
+        return Err(CallError::Open);
+    }
+    let retries = crate::config::get().outbound.retries;
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Ok(value) => {
+                record(endpoint, Ok(()));
+                return Ok(value);
+            }
+            Err(failure) if failure.retryable && tries < retries => {
+                tokio::time::sleep(backoff(tries)).await;
+                tries += 1;
+            }
+            Err(failure) => {
+                record(endpoint, Err(&failure.message));
+                return Err(CallError::Failed(failure.message));
+            }
+        }
+    }
+}
+
+/// Every endpoint called so far, open breakers first.
+pub fn statuses() -> Vec<BreakerStatus> {
+    let open_for = crate::config::get().outbound.breaker_open_secs;
+    let mut statuses: Vec<BreakerStatus> = BREAKERS
+        .iter()
+        .map(|entry| {
+            let b = entry.value();
+            BreakerStatus {
+                endpoint: entry.key().clone(),
+                state: b.state,
+                consecutive_failures: b.consecutive_failures,
+                opened_at: b.opened_at,
+                retry_in_secs: (b.state == BreakerState::Open)
+                    .then(|| b.opened.map(|at| open_for.saturating_sub(at.elapsed().as_secs())))
+                    .flatten(),
+                successes: b.successes,
+                failures: b.failures,
+                rejected: b.rejected,
+                last_error: b.last_error.clone(),
+            }
+        })
+        .collect();
+    let rank = |s: BreakerState| match s {
+        BreakerState::Open => 0,
+        BreakerState::HalfOpen => 1,
+        BreakerState::Closed => 2,
+    };
+    statuses.sort_by(|a, b| rank(a.state).cmp(&rank(b.state)).then(a.endpoint.cmp(&b.endpoint)));
+    statuses
+}
@@ -0,0 +1,100 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::RngCore;
+
+use crate::db;
+
+const CHALLENGE_TTL_SECS: i64 = 10 * 60;
+
+// `solana sign-offchain-message` signs the message wrapped in this envelope
+// rather than the raw bytes, so both forms are accepted.
+const OFFCHAIN_SIGNING_DOMAIN: &[u8] = b"\xffsolana offchain";
+const OFFCHAIN_MAX_ASCII_LEN: usize = 1212;
+
+/// Parses a base58 pubkey such as `EcTqXgB6...` into an Ed25519 key.
+pub fn parse_pubkey(pubkey: &str) -> Result<VerifyingKey, String> {
+    let bytes = bs58::decode(pubkey)
+        .into_vec()
+        .map_err(|e| format!("pubkey is not valid base58: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("pubkey decodes to {} bytes, expected 32", b.len()))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| "pubkey is not a valid Ed25519 key".to_string())
+}
+
+/// Signatures are accepted as base58, like Solana tools print them, or hex.
+fn parse_signature(signature: &str) -> Result<Signature, String> {
+    let signature = signature.trim();
+    let bytes = if signature.len() == 128 && signature.chars().all(|c| c.is_ascii_hexdigit()) {
+        hex::decode(signature).map_err(|e| e.to_string())?
+    } else {
+        bs58::decode(signature)
+            .into_vec()
+            .map_err(|e| format!("signature is neither base58 nor hex: {}", e))?
+    };
+    Signature::from_slice(&bytes).map_err(|_| format!("signature decodes to {} bytes, expected 64", bytes.len()))
+}
+
+/// The version 0 off-chain message envelope around `message`.
+fn offchain_envelope(message: &str) -> Vec<u8> {
+    let restricted_ascii = message.len() <= OFFCHAIN_MAX_ASCII_LEN && message.bytes().all(|b| (0x20..=0x7e).contains(&b));
+    let mut out = OFFCHAIN_SIGNING_DOMAIN.to_vec();
+    out.push(0);
+    out.push(if restricted_ascii { 0 } else { 1 });
+    out.extend_from_slice(&(message.len() as u16).to_le_bytes());
+    out.extend_from_slice(message.as_bytes());
+    out
+}
+
+/// Issues a fresh challenge for the account to sign with the node's keypair,
+/// replacing any earlier one. Returns the message and when it expires.
+pub async fn issue_challenge(account: &db::Account, pubkey: &str, now: i64) -> Result<(String, i64), String> {
+    parse_pubkey(pubkey)?;
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let expires_at = now + CHALLENGE_TTL_SECS;
+
+    // One printable line, so wallets and the Solana CLI sign it unchanged.
+    let message = format!(
+        "xandeum-observer ownership proof: pubkey {} account {} nonce {} expires {}",
+        pubkey,
+        account.username,
+        hex::encode(nonce),
+        expires_at
+    );
+    db::shared().save_ownership_challenge(account.id, pubkey, &message, expires_at)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((message, expires_at))
+}
+
+/// Checks the signature over the outstanding challenge and, if it holds,
+/// records the account as the node's verified owner. A previous owner loses
+/// their private rules and email subscription for the node on every network.
+pub async fn verify_claim(account: &db::Account, pubkey: &str, signature: &str, now: i64) -> Result<(), String> {
+    let key = parse_pubkey(pubkey)?;
+    let signature = parse_signature(signature)?;
+    let message = db::shared().get_ownership_challenge(account.id, pubkey, now)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("no outstanding challenge for this pubkey, or it expired; request a new one")?;
+
+    let valid = key.verify(message.as_bytes(), &signature).is_ok()
+        || key.verify(&offchain_envelope(&message), &signature).is_ok();
+    if !valid {
+        return Err("signature does not match the challenge for this pubkey".to_string());
+    }
+
+    let lapsed = db::shared().save_verified_claim(account.id, pubkey, now).await.map_err(|e| e.to_string())?;
+    if lapsed.is_empty() {
+        return Ok(());
+    }
+    // The shared store was cleared along with the claim; the other networks
+    // keep their own rules.
+    let default = &crate::networks::default().name;
+    for network in crate::networks::all().iter().filter(|n| &n.name != default) {
+        crate::networks::scope(network, async { db::store().revoke_owners(&lapsed, pubkey).await })
+            .await
+            .map_err(|e| format!("claim saved, but revoking the previous owner's access on {} failed: {}", network.name, e))?;
+    }
+    Ok(())
+}
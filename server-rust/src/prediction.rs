@@ -0,0 +1,598 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{self, NodeEvent, NodeHistoryRecord};
+
+const HORIZON_SECS: i64 = 24 * 3600;
+const FEATURE_WINDOW_SECS: i64 = 24 * 3600;
+const LATENCY_TREND_SECS: i64 = 6 * 3600;
+const TRAINING_WINDOW_SECS: i64 = 7 * 24 * 3600;
+// The newest part of the training window is held out of training so the
+// backtest has points the model hasn't seen.
+const HOLDOUT_SECS: i64 = 2 * 24 * 3600;
+// One training point per node per hour; consecutive 30s cycles are too
+// correlated to add information.
+const SAMPLE_STRIDE_SECS: i64 = 3600;
+// Nodes without a known uptime are treated as having run for 30 days.
+const DEFAULT_HOURS_SINCE_RESTART: f64 = 720.0;
+
+const FEATURES: usize = 5;
+
+// Each network trains its own model on its own history.
+static MODELS: Lazy<RwLock<HashMap<String, Model>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Features {
+    pub flap_rate_per_hour: f64,
+    pub latency_slope_ms_per_hour: f64,
+    pub storage_fill_pct_per_day: f64,
+    pub hours_since_restart: Option<f64>,
+    pub version_age_days: Option<f64>,
+}
+
+impl Features {
+    fn vector(&self) -> [f64; FEATURES] {
+        [
+            self.flap_rate_per_hour,
+            self.latency_slope_ms_per_hour,
+            self.storage_fill_pct_per_day,
+            // Restarts matter most in the first hours, so the model sees log time.
+            self.hours_since_restart.unwrap_or(DEFAULT_HOURS_SINCE_RESTART).ln_1p(),
+            self.version_age_days.unwrap_or(0.0),
+        ]
+    }
+}
+
+/// Logistic regression over standardised features.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub weights: [f64; FEATURES],
+    pub bias: f64,
+    pub means: [f64; FEATURES],
+    pub scales: [f64; FEATURES],
+    pub trained_at: Option<i64>,
+    /// The last sample time trained on. Models stored before it was recorded
+    /// trained on everything up to `trained_at` less the 24h horizon.
+    #[serde(default)]
+    pub trained_through: Option<i64>,
+    pub samples: usize,
+    pub positives: usize,
+}
+
+impl Model {
+    /// Hand-set coefficients used until enough history exists to train on.
+    fn prior() -> Self {
+        Model {
+            weights: [1.5, 0.01, 0.05, -0.3, 0.01],
+            bias: -2.5,
+            means: [0.0; FEATURES],
+            scales: [1.0; FEATURES],
+            trained_at: None,
+            trained_through: None,
+            samples: 0,
+            positives: 0,
+        }
+    }
+
+    fn standardise(&self, x: &[f64; FEATURES]) -> [f64; FEATURES] {
+        std::array::from_fn(|i| (x[i] - self.means[i]) / self.scales[i])
+    }
+
+    fn predict(&self, features: &Features) -> f64 {
+        let x = self.standardise(&features.vector());
+        sigmoid(self.bias + (0..FEATURES).map(|i| self.weights[i] * x[i]).sum::<f64>())
+    }
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub pubkey: String,
+    pub failure_probability_24h: f64,
+    pub risk: &'static str,
+    pub features: Features,
+    pub model_trained_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_predicted: f64,
+    pub observed_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backtest {
+    pub from: i64,
+    pub to: i64,
+    pub samples: usize,
+    pub positives: usize,
+    pub base_rate: f64,
+    pub brier_score: f64,
+    pub log_loss: f64,
+    pub calibration: Vec<CalibrationBin>,
+    pub model_trained_at: Option<i64>,
+}
+
+/// Everything the feature extractor needs about one node.
+#[derive(Default)]
+struct NodeData {
+    history: Vec<NodeHistoryRecord>,
+    events: Vec<NodeEvent>,
+}
+
+fn risk_level(p: f64) -> &'static str {
+    if p >= 0.5 {
+        "high"
+    } else if p >= 0.2 {
+        "medium"
+    } else {
+        "low"
+    }
+}
+
+fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let var: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    if var > 0.0 { cov / var } else { 0.0 }
+}
+
+/// Features for a node as they would have looked at time `at`, using only data
+/// recorded up to then. `None` if the node wasn't observed in the last day.
+fn features_at(data: &NodeData, version_first_seen: &HashMap<String, i64>, at: i64) -> Option<Features> {
+    let lo = data.history.partition_point(|r| r.timestamp <= at - FEATURE_WINDOW_SECS);
+    let hi = data.history.partition_point(|r| r.timestamp <= at);
+    let window = &data.history[lo..hi];
+    let last = window.last()?;
+
+    let flaps = window.windows(2).filter(|w| w[0].status != w[1].status).count();
+
+    let latency: Vec<(f64, f64)> = window
+        .iter()
+        .filter(|r| r.timestamp > at - LATENCY_TREND_SECS)
+        .filter_map(|r| r.latency_ms.map(|l| (r.timestamp as f64 / 3600.0, l as f64)))
+        .collect();
+    let latency_slope = if latency.len() >= 3 { slope(&latency) } else { 0.0 };
+
+    let fill: Vec<(i64, f64)> = window
+        .iter()
+        .filter_map(|r| match (r.storage_used, r.storage_committed) {
+            (Some(used), Some(committed)) if committed > 0 => Some((r.timestamp, used as f64 / committed as f64 * 100.0)),
+            _ => None,
+        })
+        .collect();
+    let storage_fill = match (fill.first(), fill.last()) {
+        (Some(first), Some(last)) if last.0 - first.0 >= 3600 => (last.1 - first.1) / ((last.0 - first.0) as f64 / 86400.0),
+        _ => 0.0,
+    };
+
+    let version = data
+        .events
+        .iter()
+        .take_while(|e| e.timestamp <= at)
+        .filter(|e| e.event_type == "first_seen" || e.event_type == "version_changed")
+        .filter_map(|e| e.current.as_ref())
+        .last();
+    let version_age_days = version
+        .and_then(|v| version_first_seen.get(v))
+        .map(|first| (at - first).max(0) as f64 / 86400.0);
+
+    Some(Features {
+        flap_rate_per_hour: flaps as f64 / (FEATURE_WINDOW_SECS as f64 / 3600.0),
+        latency_slope_ms_per_hour: latency_slope,
+        storage_fill_pct_per_day: storage_fill,
+        hours_since_restart: last.uptime.map(|u| u as f64 / 3600.0),
+        version_age_days,
+    })
+}
+
+fn failed_within_horizon(events: &[NodeEvent], at: i64) -> bool {
+    events
+        .iter()
+        .any(|e| e.event_type == "went_offline" && e.timestamp > at && e.timestamp <= at + HORIZON_SECS)
+}
+
+async fn load_node(pubkey: &str, since: i64) -> Result<NodeData, sqlx::Error> {
+    Ok(NodeData {
+        history: db::store().get_node_history_since(pubkey, since).await?,
+        events: db::store().get_node_events_since(pubkey, since).await?,
+    })
+}
+
+/// [`load_node`] for every node with history since `since`, reading each
+/// table once rather than once per node.
+async fn load_nodes(since: i64) -> Result<BTreeMap<String, NodeData>, sqlx::Error> {
+    let mut nodes: BTreeMap<String, NodeData> = BTreeMap::new();
+    for (pubkey, record) in db::store().get_all_node_history_since(since).await? {
+        nodes.entry(pubkey).or_default().history.push(record);
+    }
+    for event in db::store().get_all_node_events_since(since).await? {
+        if let Some(data) = nodes.get_mut(&event.pubkey) {
+            data.events.push(event);
+        }
+    }
+    Ok(nodes)
+}
+
+/// Labelled points for every node, one per `SAMPLE_STRIDE_SECS` in
+/// `[from, to]`, labelled by whether `node_events` recorded the node going
+/// offline within the following 24h.
+async fn dataset(from: i64, to: i64) -> Result<Vec<(Features, bool)>, sqlx::Error> {
+    let version_first_seen: HashMap<String, i64> = db::store().get_version_first_seen().await?.into_iter().collect();
+    let mut points = Vec::new();
+
+    for data in load_nodes(from - FEATURE_WINDOW_SECS).await?.values() {
+        let mut at = from;
+        while at <= to {
+            if let Some(features) = features_at(data, &version_first_seen, at) {
+                let failed = failed_within_horizon(&data.events, at);
+                points.push((features, failed));
+            }
+            at += SAMPLE_STRIDE_SECS;
+        }
+    }
+    Ok(points)
+}
+
+/// Fits the model by batch gradient descent on log loss with a small L2
+/// penalty. Log loss keeps the output probabilities calibrated to the observed
+/// failure rate rather than just ranking nodes.
+fn fit(points: &[(Features, bool)], trained_at: i64, trained_through: i64) -> Model {
+    let xs: Vec<[f64; FEATURES]> = points.iter().map(|(f, _)| f.vector()).collect();
+    let n = xs.len() as f64;
+
+    let means: [f64; FEATURES] = std::array::from_fn(|i| xs.iter().map(|x| x[i]).sum::<f64>() / n);
+    let scales: [f64; FEATURES] = std::array::from_fn(|i| {
+        let var = xs.iter().map(|x| (x[i] - means[i]).powi(2)).sum::<f64>() / n;
+        if var > 1e-12 { var.sqrt() } else { 1.0 }
+    });
+
+    let mut model = Model {
+        weights: [0.0; FEATURES],
+        bias: 0.0,
+        means,
+        scales,
+        trained_at: Some(trained_at),
+        trained_through: Some(trained_through),
+        samples: points.len(),
+        positives: points.iter().filter(|(_, y)| *y).count(),
+    };
+    let zs: Vec<[f64; FEATURES]> = xs.iter().map(|x| model.standardise(x)).collect();
+
+    const ITERATIONS: usize = 500;
+    const LEARNING_RATE: f64 = 0.5;
+    const L2: f64 = 0.01;
+
+    for _ in 0..ITERATIONS {
+        let mut grad_w = [0.0; FEATURES];
+        let mut grad_b = 0.0;
+        for (z, (_, y)) in zs.iter().zip(points) {
+            let p = sigmoid(model.bias + (0..FEATURES).map(|i| model.weights[i] * z[i]).sum::<f64>());
+            let err = p - if *y { 1.0 } else { 0.0 };
+            grad_b += err;
+            for (g, zi) in grad_w.iter_mut().zip(z) {
+                *g += err * zi;
+            }
+        }
+        model.bias -= LEARNING_RATE * grad_b / n;
+        for (w, g) in model.weights.iter_mut().zip(grad_w) {
+            *w -= LEARNING_RATE * (g / n + L2 * *w);
+        }
+    }
+    model
+}
+
+/// The current network's model, or the prior if it hasn't trained one yet.
+fn model() -> Model {
+    let name = &crate::networks::current().name;
+    MODELS.read().unwrap().get(name).cloned().unwrap_or_else(Model::prior)
+}
+
+fn set_model(model: Model) {
+    MODELS.write().unwrap().insert(crate::networks::current().name.clone(), model);
+}
+
+/// Loads the most recently trained model, if one has been stored.
+pub async fn load_model() -> Result<(), sqlx::Error> {
+    if let Some(json) = db::store().get_latest_prediction_model().await? {
+        match serde_json::from_str::<Model>(&json) {
+            Ok(model) => set_model(model),
+            Err(e) => eprintln!("Ignoring stored prediction model: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Retrains on the last week of history. Points need a full 24h of recorded
+/// outcome after them, so the newest day is left out, and the two days before
+/// it are held out for the backtest. Without both failures and non-failures
+/// in the data the current model is kept.
+pub async fn retrain(now: i64) -> Result<(), sqlx::Error> {
+    let trained_through = now - HORIZON_SECS - HOLDOUT_SECS;
+    let points = dataset(now - TRAINING_WINDOW_SECS, trained_through).await?;
+    let positives = points.iter().filter(|(_, y)| *y).count();
+    if positives == 0 || positives == points.len() {
+        println!("Skipping prediction model training: {} samples, {} failures", points.len(), positives);
+        return Ok(());
+    }
+
+    let model = fit(&points, now, trained_through);
+    let json = serde_json::to_string(&model).unwrap();
+    db::store().save_prediction_model(now, model.samples as i64, model.positives as i64, &json).await?;
+    println!("Trained prediction model on {} samples ({} failures)", model.samples, model.positives);
+    set_model(model);
+    Ok(())
+}
+
+fn predict(pubkey: &str, data: &NodeData, version_first_seen: &HashMap<String, i64>, model: &Model, now: i64) -> Option<Prediction> {
+    features_at(data, version_first_seen, now).map(|features| {
+        let p = model.predict(&features);
+        Prediction {
+            pubkey: pubkey.to_string(),
+            failure_probability_24h: p,
+            risk: risk_level(p),
+            features,
+            model_trained_at: model.trained_at,
+        }
+    })
+}
+
+pub async fn predict_node(pubkey: &str, now: i64) -> Result<Option<Prediction>, sqlx::Error> {
+    let version_first_seen: HashMap<String, i64> = db::store().get_version_first_seen().await?.into_iter().collect();
+    let data = load_node(pubkey, now - FEATURE_WINDOW_SECS).await?;
+    let model = model();
+
+    Ok(predict(pubkey, &data, &version_first_seen, &model, now))
+}
+
+pub async fn at_risk(now: i64, threshold: f64, limit: usize) -> Result<Vec<Prediction>, sqlx::Error> {
+    let version_first_seen: HashMap<String, i64> = db::store().get_version_first_seen().await?.into_iter().collect();
+    let model = model();
+
+    let mut predictions = Vec::new();
+    for (pubkey, data) in load_nodes(now - FEATURE_WINDOW_SECS).await? {
+        if let Some(p) = predict(&pubkey, &data, &version_first_seen, &model, now) {
+            if p.failure_probability_24h >= threshold {
+                predictions.push(p);
+            }
+        }
+    }
+
+    predictions.sort_by(|a, b| b.failure_probability_24h.total_cmp(&a.failure_probability_24h));
+    predictions.truncate(limit);
+    Ok(predictions)
+}
+
+/// The span `backtest` scores over: the last `days` days, excluding the final
+/// 24h whose outcome isn't known yet. Only points the model wasn't trained on
+/// count, so it starts no earlier than the first point whose outcome falls
+/// after the training data. Empty when `from > to`.
+fn backtest_window(model: &Model, now: i64, days: i64) -> (i64, i64) {
+    let mut from = now - days * 86400;
+    if let Some(through) = model.trained_through.or(model.trained_at.map(|at| at - HORIZON_SECS)) {
+        from = from.max(through + HORIZON_SECS);
+    }
+    (from, now - HORIZON_SECS)
+}
+
+/// Scores the current model against what `node_events` says actually happened
+/// over the held-out part of the last `days` days.
+pub async fn backtest(now: i64, days: i64) -> Result<Backtest, sqlx::Error> {
+    let model = model();
+    let (from, to) = backtest_window(&model, now, days);
+    let points = if from <= to { dataset(from, to).await? } else { Vec::new() };
+    Ok(score(&model, &points, from, to))
+}
+
+fn score(model: &Model, points: &[(Features, bool)], from: i64, to: i64) -> Backtest {
+    let scored: Vec<(f64, bool)> = points.iter().map(|(f, y)| (model.predict(f), *y)).collect();
+    let n = scored.len().max(1) as f64;
+    let positives = scored.iter().filter(|(_, y)| *y).count();
+
+    let brier_score = scored.iter().map(|(p, y)| (p - if *y { 1.0 } else { 0.0 }).powi(2)).sum::<f64>() / n;
+    let log_loss = -scored
+        .iter()
+        .map(|(p, y)| {
+            let p = p.clamp(1e-9, 1.0 - 1e-9);
+            if *y { p.ln() } else { (1.0 - p).ln() }
+        })
+        .sum::<f64>()
+        / n;
+
+    let calibration = (0..10)
+        .map(|bin| {
+            let (lower, upper) = (bin as f64 / 10.0, (bin + 1) as f64 / 10.0);
+            let members: Vec<&(f64, bool)> = scored
+                .iter()
+                .filter(|(p, _)| *p >= lower && (*p < upper || (bin == 9 && *p <= upper)))
+                .collect();
+            let count = members.len();
+            let denom = count.max(1) as f64;
+            CalibrationBin {
+                lower,
+                upper,
+                count,
+                mean_predicted: members.iter().map(|(p, _)| p).sum::<f64>() / denom,
+                observed_rate: members.iter().filter(|(_, y)| *y).count() as f64 / denom,
+            }
+        })
+        .filter(|bin| bin.count > 0)
+        .collect();
+
+    Backtest {
+        from,
+        to,
+        samples: scored.len(),
+        positives,
+        base_rate: positives as f64 / n,
+        brier_score,
+        log_loss,
+        calibration,
+        model_trained_at: model.trained_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86400;
+
+    fn features(flap_rate_per_hour: f64) -> Features {
+        Features {
+            flap_rate_per_hour,
+            latency_slope_ms_per_hour: 0.0,
+            storage_fill_pct_per_day: 0.0,
+            hours_since_restart: None,
+            version_age_days: None,
+        }
+    }
+
+    /// A model predicting `p` for every node.
+    fn constant(p: f64) -> Model {
+        Model { weights: [0.0; FEATURES], bias: (p / (1.0 - p)).ln(), ..Model::prior() }
+    }
+
+    fn went_offline(at: i64) -> NodeEvent {
+        NodeEvent {
+            pubkey: "pk".to_string(),
+            timestamp: at,
+            event_type: "went_offline".to_string(),
+            previous: Some("online".to_string()),
+            current: Some("missing".to_string()),
+        }
+    }
+
+    #[test]
+    fn backtest_starts_after_the_training_data() {
+        let model = Model { trained_at: Some(NOW - DAY), trained_through: Some(NOW - 4 * DAY), ..constant(0.1) };
+
+        // Points up to four days ago were trained on; the first unseen one is
+        // a horizon later, and the last scoreable one a horizon before now.
+        assert_eq!(backtest_window(&model, NOW, 7), (NOW - 3 * DAY, NOW - DAY));
+        assert_eq!(backtest_window(&model, NOW, 2), (NOW - 2 * DAY, NOW - DAY));
+    }
+
+    #[test]
+    fn backtest_of_a_model_stored_before_trained_through_uses_trained_at() {
+        let model = Model { trained_at: Some(NOW - 2 * DAY), trained_through: None, ..constant(0.1) };
+
+        assert_eq!(backtest_window(&model, NOW, 7), (NOW - 2 * DAY, NOW - DAY));
+    }
+
+    #[test]
+    fn backtest_of_the_prior_covers_the_whole_window() {
+        assert_eq!(backtest_window(&Model::prior(), NOW, 7), (NOW - 7 * DAY, NOW - DAY));
+    }
+
+    #[test]
+    fn backtest_is_empty_when_everything_was_trained_on() {
+        let model = Model { trained_at: Some(NOW), trained_through: Some(NOW - DAY), ..constant(0.1) };
+
+        let (from, to) = backtest_window(&model, NOW, 7);
+        assert!(from > to);
+        let report = score(&model, &[], from, to);
+        assert_eq!(report.samples, 0);
+        assert!(report.calibration.is_empty());
+    }
+
+    #[test]
+    fn score_reports_brier_log_loss_and_calibration() {
+        let points = vec![(features(0.0), true), (features(0.0), false), (features(0.0), false), (features(0.0), false)];
+        let report = score(&constant(0.25), &points, NOW - DAY, NOW);
+
+        assert_eq!((report.samples, report.positives), (4, 1));
+        assert_eq!(report.base_rate, 0.25);
+        assert!((report.brier_score - 0.1875).abs() < 1e-9);
+        let expected_log_loss = -(0.25f64.ln() + 3.0 * 0.75f64.ln()) / 4.0;
+        assert!((report.log_loss - expected_log_loss).abs() < 1e-9);
+
+        assert_eq!(report.calibration.len(), 1);
+        let bin = &report.calibration[0];
+        assert_eq!((bin.lower, bin.upper, bin.count), (0.2, 0.3, 4));
+        assert!((bin.mean_predicted - 0.25).abs() < 1e-9);
+        assert_eq!(bin.observed_rate, 0.25);
+    }
+
+    #[test]
+    fn fit_learns_that_flapping_nodes_fail() {
+        let mut points = Vec::new();
+        for i in 0..40 {
+            points.push((features(1.0 + i as f64 * 0.01), i % 5 != 0));
+            points.push((features(i as f64 * 0.001), i % 10 == 0));
+        }
+        let model = fit(&points, NOW, NOW - 3 * DAY);
+
+        assert_eq!((model.samples, model.positives), (80, 36));
+        assert_eq!(model.trained_through, Some(NOW - 3 * DAY));
+        assert!(model.weights[0] > 0.0);
+        assert!(model.predict(&features(1.2)) > 0.6);
+        assert!(model.predict(&features(0.0)) < 0.3);
+    }
+
+    #[test]
+    fn failure_counts_only_within_the_next_24h() {
+        let events = vec![went_offline(NOW + DAY)];
+
+        assert!(failed_within_horizon(&events, NOW));
+        assert!(!failed_within_horizon(&events, NOW + DAY));
+        assert!(!failed_within_horizon(&events, NOW - 1));
+    }
+
+    #[test]
+    fn events_since_start_with_the_version_running_then() {
+        let event = |pubkey: &str, at: i64, event_type: &str, current: &str| NodeEvent {
+            pubkey: pubkey.to_string(),
+            timestamp: at,
+            event_type: event_type.to_string(),
+            previous: None,
+            current: Some(current.to_string()),
+        };
+        let (pk, other) = ("prediction-events-pk", "prediction-events-other");
+        let events = vec![
+            event(pk, NOW - 3 * DAY, "first_seen", "1.0"),
+            event(pk, NOW - 2 * DAY, "version_changed", "1.1"),
+            event(pk, NOW - 2 * DAY + 60, "went_offline", "missing"),
+            event(pk, NOW - 60, "went_offline", "missing"),
+            event(other, NOW - 3 * DAY, "first_seen", "2.0"),
+        ];
+        let snapshot = db::Snapshot { total_nodes: 0, online_nodes: 0, total_storage: 0, total_committed: 0 };
+
+        let (one, all) = testing::run(async {
+            db::store().save_cycle(NOW, &snapshot, &[], &events, &[]).await.unwrap();
+            let one = db::store().get_node_events_since(pk, NOW - DAY).await.unwrap();
+            let all = db::store().get_all_node_events_since(NOW - DAY).await.unwrap();
+            (one, all)
+        });
+
+        let summary = |events: &[NodeEvent]| -> Vec<(String, i64, String)> {
+            events
+                .iter()
+                .filter(|e| e.pubkey == pk || e.pubkey == other)
+                .map(|e| (e.pubkey.clone(), e.timestamp, e.current.clone().unwrap()))
+                .collect()
+        };
+        assert_eq!(summary(&one), vec![(pk.to_string(), NOW - 2 * DAY, "1.1".to_string()), (pk.to_string(), NOW - 60, "missing".to_string())]);
+        assert_eq!(
+            summary(&all),
+            vec![
+                (other.to_string(), NOW - 3 * DAY, "2.0".to_string()),
+                (pk.to_string(), NOW - 2 * DAY, "1.1".to_string()),
+                (pk.to_string(), NOW - 60, "missing".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+// Past this many tracked clients, buckets that have refilled are dropped; a
+// full bucket behaves exactly like a missing one.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+static BUCKETS: Lazy<DashMap<String, Bucket>> = Lazy::new(DashMap::new);
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, per_sec: f64) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(self.capacity);
+        self.updated = now;
+    }
+}
+
+/// Outcome of taking one request from a client's bucket, in the shape of the
+/// `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next request would be allowed; 0 when allowed.
+    pub retry_after_secs: u64,
+}
+
+/// Takes one token from the bucket for `client`. Buckets hold `per_minute`
+/// tokens and refill continuously, so a client can burst up to its limit and
+/// then sustain `per_minute` requests a minute.
+pub fn check(client: &str, per_minute: u32) -> Decision {
+    let now = Instant::now();
+    let capacity = per_minute.max(1) as f64;
+    let per_sec = capacity / 60.0;
+
+    if BUCKETS.len() > MAX_TRACKED_BUCKETS {
+        BUCKETS.retain(|_, b| {
+            b.refill(now, b.capacity / 60.0);
+            b.tokens < b.capacity
+        });
+    }
+
+    let mut bucket = BUCKETS
+        .entry(client.to_string())
+        .or_insert_with(|| Bucket { tokens: capacity, capacity, updated: now });
+    // A key's limit can change while its bucket is live.
+    if bucket.capacity != capacity {
+        bucket.tokens = bucket.tokens.min(capacity);
+        bucket.capacity = capacity;
+    }
+    bucket.refill(now, per_sec);
+
+    let allowed = bucket.tokens >= 1.0;
+    if allowed {
+        bucket.tokens -= 1.0;
+    }
+    Decision {
+        allowed,
+        limit: capacity as u32,
+        remaining: bucket.tokens.floor() as u32,
+        reset_secs: ((capacity - bucket.tokens) / per_sec).ceil() as u64,
+        retry_after_secs: if allowed { 0 } else { ((1.0 - bucket.tokens) / per_sec).ceil() as u64 },
+    }
+}
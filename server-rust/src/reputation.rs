@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::credits;
+use crate::db::{self, NodeRecord, ReputationRecord};
+
+// Older behaviour loses half its weight every week, so a single bad refresh
+// among the ~20k cycles a week holds barely registers in the score.
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 3600.0;
+// Tenure stops adding to the score once a node has been seen for 90 days.
+const FULL_TENURE_SECS: f64 = 90.0 * 24.0 * 3600.0;
+
+const WEIGHT_AVAILABILITY: f64 = 0.35;
+const WEIGHT_LATENCY: f64 = 0.20;
+const WEIGHT_VERSION: f64 = 0.15;
+const WEIGHT_CREDITS: f64 = 0.15;
+const WEIGHT_TENURE: f64 = 0.15;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReputationComponents {
+    pub availability: f64,
+    pub latency_consistency: f64,
+    pub version_timeliness: f64,
+    pub credits: f64,
+    pub tenure: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReputationEntry {
+    pub rank: i64,
+    pub pubkey: String,
+    pub score: f64,
+    pub components: ReputationComponents,
+    pub first_seen: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReputationPage {
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub entries: Vec<ReputationEntry>,
+}
+
+/// What one refresh cycle observed about a node.
+struct Observation {
+    online: bool,
+    latency_ms: Option<i64>,
+    version_score: Option<f64>,
+    credits_percentile: Option<f64>,
+}
+
+fn new_record(pubkey: &str, first_seen: i64) -> ReputationRecord {
+    ReputationRecord {
+        pubkey: pubkey.to_string(),
+        first_seen,
+        updated_at: first_seen,
+        weight: 0.0,
+        up_weight: 0.0,
+        latency_weight: 0.0,
+        latency_sum: 0.0,
+        latency_sq_sum: 0.0,
+        version_weight: 0.0,
+        version_sum: 0.0,
+        credits_weight: 0.0,
+        credits_sum: 0.0,
+        availability_score: 0.0,
+        latency_score: 0.0,
+        version_score: 0.0,
+        credits_score: 0.0,
+        tenure_score: 0.0,
+        score: 0.0,
+    }
+}
+
+/// Decays every accumulator to `at` and folds in one observation.
+fn apply(rec: &mut ReputationRecord, at: i64, obs: &Observation) {
+    let elapsed = (at - rec.updated_at).max(0) as f64;
+    let factor = 0.5f64.powf(elapsed / HALF_LIFE_SECS);
+    for acc in [
+        &mut rec.weight,
+        &mut rec.up_weight,
+        &mut rec.latency_weight,
+        &mut rec.latency_sum,
+        &mut rec.latency_sq_sum,
+        &mut rec.version_weight,
+        &mut rec.version_sum,
+        &mut rec.credits_weight,
+        &mut rec.credits_sum,
+    ] {
+        *acc *= factor;
+    }
+
+    rec.weight += 1.0;
+    if obs.online {
+        rec.up_weight += 1.0;
+    }
+    if let Some(latency) = obs.latency_ms {
+        let latency = latency as f64;
+        rec.latency_weight += 1.0;
+        rec.latency_sum += latency;
+        rec.latency_sq_sum += latency * latency;
+    }
+    if let Some(v) = obs.version_score {
+        rec.version_weight += 1.0;
+        rec.version_sum += v;
+    }
+    if let Some(c) = obs.credits_percentile {
+        rec.credits_weight += 1.0;
+        rec.credits_sum += c;
+    }
+    rec.updated_at = rec.updated_at.max(at);
+}
+
+fn ratio(sum: f64, weight: f64) -> f64 {
+    if weight > 0.0 { sum / weight } else { 0.0 }
+}
+
+/// Recomputes the component scores (each 0..1) and the weighted total (0..100).
+fn score(rec: &mut ReputationRecord) {
+    rec.availability_score = ratio(rec.up_weight, rec.weight);
+
+    // Consistency is judged by the coefficient of variation, so a steady 150ms
+    // node outranks one that swings between 20ms and 400ms.
+    rec.latency_score = if rec.latency_weight > 0.0 {
+        let mean = rec.latency_sum / rec.latency_weight;
+        let variance = (rec.latency_sq_sum / rec.latency_weight - mean * mean).max(0.0);
+        if mean > 0.0 { 1.0 / (1.0 + variance.sqrt() / mean) } else { 1.0 }
+    } else {
+        0.0
+    };
+
+    rec.version_score = ratio(rec.version_sum, rec.version_weight);
+    rec.credits_score = ratio(rec.credits_sum, rec.credits_weight);
+    rec.tenure_score = ((rec.updated_at - rec.first_seen) as f64 / FULL_TENURE_SECS).clamp(0.0, 1.0);
+
+    rec.score = 100.0
+        * (WEIGHT_AVAILABILITY * rec.availability_score
+            + WEIGHT_LATENCY * rec.latency_score
+            + WEIGHT_VERSION * rec.version_score
+            + WEIGHT_CREDITS * rec.credits_score
+            + WEIGHT_TENURE * rec.tenure_score);
+}
+
+/// Leading numeric components of a version string, e.g. `v0.8.1-trynet` -> [0, 8, 1].
+pub fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// 1.0 for the newest version seen this cycle, 0.5 for an older patch of the
+/// same minor release, 0 otherwise.
+fn version_timeliness(version: &str, newest: &[u64]) -> f64 {
+    let key = version_key(version);
+    if key.as_slice() >= newest {
+        1.0
+    } else if key.len() >= 2 && newest.len() >= 2 && key[..2] == newest[..2] {
+        0.5
+    } else {
+        0.0
+    }
+}
+
+/// Replays a node's stored history so a node seen for the first time by the
+/// engine starts from its long-term record instead of from zero.
+async fn backfill(pubkey: &str, before: i64) -> Result<Option<ReputationRecord>, sqlx::Error> {
+    let history = db::store().get_node_history_before(pubkey, before).await?;
+    let Some(first) = history.first() else {
+        return Ok(None);
+    };
+
+    let mut rec = new_record(pubkey, first.timestamp);
+    for row in &history {
+        apply(
+            &mut rec,
+            row.timestamp,
+            &Observation {
+                online: row.status.as_deref() == Some("online"),
+                latency_ms: row.latency_ms,
+                version_score: None,
+                credits_percentile: None,
+            },
+        );
+    }
+    Ok(Some(rec))
+}
+
+/// Folds one successful refresh cycle into every node's reputation. Nodes with
+/// a reputation that were missing from this cycle's gossip count as down.
+pub async fn update_cycle(cycle_at: i64, nodes: &[NodeRecord]) -> Result<(), sqlx::Error> {
+    let mut records: HashMap<String, ReputationRecord> = db::store().get_all_reputations()
+        .await?
+        .into_iter()
+        .map(|r| (r.pubkey.clone(), r))
+        .collect();
+
+    let newest = nodes
+        .iter()
+        .filter_map(|n| n.version.as_deref())
+        .map(version_key)
+        .max()
+        .unwrap_or_default();
+
+    let credits = credits::snapshot().credits;
+    let credits_total = credits.len();
+    let credits_percentile = |pubkey: &str| {
+        let own = *credits.get(pubkey)?;
+        let below = credits.values().filter(|c| **c < own).count();
+        Some(if credits_total > 1 { below as f64 / (credits_total - 1) as f64 } else { 1.0 })
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    for node in nodes.iter().filter(|n| !n.pubkey.is_empty()) {
+        seen.insert(node.pubkey.as_str());
+        if !records.contains_key(&node.pubkey) {
+            let rec = backfill(&node.pubkey, cycle_at)
+                .await?
+                .unwrap_or_else(|| new_record(&node.pubkey, cycle_at));
+            records.insert(node.pubkey.clone(), rec);
+        }
+
+        let rec = records.get_mut(&node.pubkey).unwrap();
+        apply(
+            rec,
+            cycle_at,
+            &Observation {
+                online: node.status.as_deref() == Some("online"),
+                latency_ms: node.latency_ms,
+                version_score: node.version.as_deref().map(|v| version_timeliness(v, &newest)),
+                credits_percentile: credits_percentile(&node.pubkey),
+            },
+        );
+    }
+
+    for (pubkey, rec) in records.iter_mut() {
+        if !seen.contains(pubkey.as_str()) {
+            apply(
+                rec,
+                cycle_at,
+                &Observation { online: false, latency_ms: None, version_score: None, credits_percentile: None },
+            );
+        }
+        score(rec);
+    }
+
+    let records: Vec<&ReputationRecord> = records.values().collect();
+    db::store().save_reputations(&records).await
+}
+
+pub async fn leaderboard(page: i64, per_page: i64) -> Result<ReputationPage, sqlx::Error> {
+    let (total, rows) = db::store().get_reputation_page(per_page, (page - 1) * per_page).await?;
+
+    Ok(ReputationPage {
+        page,
+        per_page,
+        total,
+        entries: rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| ReputationEntry {
+                rank: (page - 1) * per_page + i as i64 + 1,
+                pubkey: r.pubkey,
+                score: r.score,
+                components: ReputationComponents {
+                    availability: r.availability_score,
+                    latency_consistency: r.latency_score,
+                    version_timeliness: r.version_score,
+                    credits: r.credits_score,
+                    tenure: r.tenure_score,
+                },
+                first_seen: r.first_seen,
+                updated_at: r.updated_at,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::db::{self, Seed};
+
+// A seed failing this many fetches in a row sits out for a while; each
+// failure once it is back starts another quarantine.
+const QUARANTINE_AFTER_FAILURES: i64 = 5;
+const QUARANTINE_SECS: i64 = 30 * 60;
+// Discovered seeds that never recover are forgotten so the slot can go to
+// another pod.
+const DROP_DISCOVERED_AFTER_FAILURES: i64 = 20;
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+// Latency at which the latency factor halves the score.
+const LATENCY_HALF_SCORE_MS: f64 = 500.0;
+
+// Seeds by network, then by address.
+static SEEDS: Lazy<RwLock<HashMap<String, HashMap<String, Seed>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A seed with its current score, as served by `/admin/seeds`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeedStatus {
+    #[serde(flatten)]
+    pub seed: Seed,
+    pub score: f64,
+    pub quarantined: bool,
+}
+
+fn new_seed(address: &str, source: &str, now: i64) -> Seed {
+    Seed {
+        address: address.to_string(),
+        source: source.to_string(),
+        successes: 0,
+        failures: 0,
+        consecutive_failures: 0,
+        latency_ms: None,
+        pod_count: None,
+        last_success_at: None,
+        last_failure_at: None,
+        quarantined_until: None,
+        added_at: now,
+    }
+}
+
+fn is_quarantined(seed: &Seed, now: i64) -> bool {
+    seed.quarantined_until.is_some_and(|until| until > now)
+}
+
+/// 0..1 from the seed's success rate, latency and how much of the network it
+/// reports. A seed with no history scores like one with an even record.
+fn score(seed: &Seed, max_pods: i64) -> f64 {
+    let success_rate = (seed.successes as f64 + 1.0) / ((seed.successes + seed.failures) as f64 + 2.0);
+    let latency = seed.latency_ms.map(|ms| 1.0 / (1.0 + ms / LATENCY_HALF_SCORE_MS)).unwrap_or(0.5);
+    // A seed with a partial view of gossip still helps, so this never zeroes.
+    let coverage = match (seed.pod_count, max_pods) {
+        (Some(pods), max) if max > 0 => 0.25 + 0.75 * (pods as f64 / max as f64),
+        _ => 0.5,
+    };
+    success_rate * latency * coverage
+}
+
+/// Loads the stored seeds and reconciles them with the configured list.
+pub async fn init(configured: &[String], now: i64) -> Result<(), sqlx::Error> {
+    let stored = db::store().get_seeds().await?;
+    let network = crate::networks::current().name.clone();
+    SEEDS.write().unwrap().entry(network).or_default().extend(stored.into_iter().map(|s| (s.address.clone(), s)));
+    sync_configured(configured, now).await
+}
+
+/// Makes the configured seeds exactly `configured`, keeping the track record
+/// of seeds that stay. A discovered seed that is now configured is promoted;
+/// one dropped from the config is forgotten.
+pub async fn sync_configured(configured: &[String], now: i64) -> Result<(), sqlx::Error> {
+    let (changed, removed) = {
+        let mut all = SEEDS.write().unwrap();
+        let seeds = all.entry(crate::networks::current().name.clone()).or_default();
+        let removed: Vec<String> = seeds
+            .values()
+            .filter(|s| s.source == "config" && !configured.contains(&s.address))
+            .map(|s| s.address.clone())
+            .collect();
+        for address in &removed {
+            seeds.remove(address);
+        }
+
+        let mut changed = Vec::new();
+        for address in configured {
+            match seeds.get_mut(address) {
+                Some(seed) if seed.source == "config" => {}
+                Some(seed) => {
+                    seed.source = "config".to_string();
+                    changed.push(seed.clone());
+                }
+                None => {
+                    let seed = new_seed(address, "config", now);
+                    seeds.insert(address.clone(), seed.clone());
+                    changed.push(seed);
+                }
+            }
+        }
+        (changed, removed)
+    };
+
+    for address in &removed {
+        db::store().delete_seed(address).await?;
+    }
+    for seed in &changed {
+        db::store().save_seed(seed).await?;
+    }
+    Ok(())
+}
+
+/// Seed addresses to try this cycle: a weighted random order of the seeds not
+/// in quarantine, then the quarantined ones as a last resort so the observer
+/// is never left with nothing to ask.
+pub fn ranked(now: i64) -> (Vec<String>, Vec<String>) {
+    let all = SEEDS.read().unwrap();
+    let Some(seeds) = all.get(&crate::networks::current().name) else {
+        return Default::default();
+    };
+    let max_pods = seeds.values().filter_map(|s| s.pod_count).max().unwrap_or(0);
+    let mut rng = rand::thread_rng();
+
+    // Weighted sampling without replacement: sort by u^(1/w), u uniform.
+    let mut available: Vec<(f64, String)> = Vec::new();
+    let mut quarantined: Vec<(i64, String)> = Vec::new();
+    for seed in seeds.values() {
+        if is_quarantined(seed, now) {
+            quarantined.push((seed.quarantined_until.unwrap_or(0), seed.address.clone()));
+        } else {
+            let weight = score(seed, max_pods).max(1e-6);
+            let key = rng.gen::<f64>().powf(1.0 / weight);
+            available.push((key, seed.address.clone()));
+        }
+    }
+    available.sort_by(|a, b| b.0.total_cmp(&a.0));
+    // Those closest to release first.
+    quarantined.sort();
+
+    (
+        available.into_iter().map(|(_, a)| a).collect(),
+        quarantined.into_iter().map(|(_, a)| a).collect(),
+    )
+}
+
+/// Applies `apply` to the seed and stores the result, returning the updated
+/// seed and whatever `apply` returned.
+async fn update<R>(address: &str, apply: impl FnOnce(&mut Seed) -> R) -> Option<(Seed, R)> {
+    let (seed, drop, outcome) = {
+        let mut all = SEEDS.write().unwrap();
+        let seeds = all.entry(crate::networks::current().name.clone()).or_default();
+        let seed = seeds.get_mut(address)?;
+        let outcome = apply(seed);
+        let drop = seed.source == "discovered" && seed.consecutive_failures >= DROP_DISCOVERED_AFTER_FAILURES;
+        let snapshot = seed.clone();
+        if drop {
+            seeds.remove(address);
+        }
+        (snapshot, drop, outcome)
+    };
+
+    let result = if drop {
+        println!("Dropping discovered seed {} after {} failures in a row", address, seed.consecutive_failures);
+        db::store().delete_seed(address).await
+    } else {
+        db::store().save_seed(&seed).await
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to save seed {}: {}", address, e);
+    }
+    Some((seed, outcome))
+}
+
+pub async fn record_success(address: &str, latency_ms: f64, pod_count: i64, now: i64) {
+    update(address, |seed| {
+        seed.successes += 1;
+        seed.consecutive_failures = 0;
+        seed.quarantined_until = None;
+        seed.latency_ms = Some(match seed.latency_ms {
+            Some(avg) => avg + LATENCY_EWMA_ALPHA * (latency_ms - avg),
+            None => latency_ms,
+        });
+        seed.pod_count = Some(pod_count);
+        seed.last_success_at = Some(now);
+    })
+    .await;
+}
+
+pub async fn record_failure(address: &str, now: i64) {
+    let updated = update(address, |seed| {
+        seed.failures += 1;
+        seed.consecutive_failures += 1;
+        seed.last_failure_at = Some(now);
+        let quarantine = seed.consecutive_failures >= QUARANTINE_AFTER_FAILURES && !is_quarantined(seed, now);
+        if quarantine {
+            seed.quarantined_until = Some(now + QUARANTINE_SECS);
+        }
+        quarantine
+    })
+    .await;
+
+    if let Some((seed, quarantined)) = updated {
+        if quarantined {
+            println!(
+                "Quarantined seed {} for {}s after {} failures in a row",
+                seed.address, QUARANTINE_SECS, seed.consecutive_failures
+            );
+        }
+    }
+}
+
+/// Adds public pods seen online this cycle as seed candidates, up to the
+/// configured number of discovered seeds.
+pub async fn discover(candidates: &[String], max_discovered: usize, now: i64) {
+    let added: Vec<Seed> = {
+        let mut all = SEEDS.write().unwrap();
+        let seeds = all.entry(crate::networks::current().name.clone()).or_default();
+        let mut room = max_discovered.saturating_sub(seeds.values().filter(|s| s.source == "discovered").count());
+        let mut added = Vec::new();
+        for address in candidates {
+            if room == 0 {
+                break;
+            }
+            if !seeds.contains_key(address) {
+                let seed = new_seed(address, "discovered", now);
+                seeds.insert(address.clone(), seed.clone());
+                added.push(seed);
+                room -= 1;
+            }
+        }
+        added
+    };
+
+    for seed in &added {
+        println!("Discovered seed candidate {}", seed.address);
+        if let Err(e) = db::store().save_seed(seed).await {
+            eprintln!("Failed to save seed {}: {}", seed.address, e);
+        }
+    }
+}
+
+/// Lifts a seed's quarantine and clears its failure streak.
+pub async fn release(address: &str) -> bool {
+    update(address, |seed| {
+        seed.quarantined_until = None;
+        seed.consecutive_failures = 0;
+    })
+    .await
+    .is_some()
+}
+
+/// Every known seed, best score first.
+pub fn statuses(now: i64) -> Vec<SeedStatus> {
+    let all = SEEDS.read().unwrap();
+    let Some(seeds) = all.get(&crate::networks::current().name) else {
+        return Default::default();
+    };
+    let max_pods = seeds.values().filter_map(|s| s.pod_count).max().unwrap_or(0);
+    let mut statuses: Vec<SeedStatus> = seeds
+        .values()
+        .map(|seed| SeedStatus {
+            score: (score(seed, max_pods) * 1000.0).round() / 1000.0,
+            quarantined: is_quarantined(seed, now),
+            seed: seed.clone(),
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.quarantined.cmp(&b.quarantined).then(b.score.total_cmp(&a.score)));
+    statuses
+}
+
+/// Re-reads the current network's seed list from the configuration sources.
+pub async fn reload(now: i64) -> Result<Vec<String>, String> {
+    let name = &crate::networks::current().name;
+    let configured = crate::config::reload()?
+        .networks()
+        .into_iter()
+        .find(|n| n.name == *name)
+        .map(|n| n.seeds)
+        .ok_or_else(|| format!("network '{}' is no longer configured; restart to change networks", name))?;
+    sync_configured(&configured, now).await.map_err(|e| e.to_string())?;
+    println!("Seed list for {} reloaded: {}", name, configured.join(", "));
+    Ok(configured)
+}
@@ -0,0 +1,288 @@
+use serde::Serialize;
+
+use crate::db;
+
+/// How long a cycle's observation holds. Cycles run every refresh interval,
+/// with half an interval of slack for one that probes many pods and runs long.
+/// Past that we assume the observer itself stopped running and the time is
+/// counted as unknown rather than as up or down.
+fn max_cycle_gap(refresh_interval_secs: u64) -> i64 {
+    refresh_interval_secs as i64 * 3 / 2
+}
+
+const WINDOWS: [(&str, i64); 3] = [
+    ("24h", 24 * 3600),
+    ("7d", 7 * 24 * 3600),
+    ("30d", 30 * 24 * 3600),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: i64,
+    end: i64,
+    state: State,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowAvailability {
+    pub window: &'static str,
+    pub up_secs: i64,
+    pub down_secs: i64,
+    pub unknown_secs: i64,
+    /// `None` when the observer has no data for the node in this window.
+    pub availability_percent: Option<f64>,
+    pub coverage_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    pub start: i64,
+    pub end: i64,
+    pub duration_secs: i64,
+    pub ongoing: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlaReport {
+    pub pubkey: String,
+    pub generated_at: i64,
+    pub windows: Vec<WindowAvailability>,
+    pub mtbf_secs: Option<f64>,
+    pub mttr_secs: Option<f64>,
+    pub longest_outage_secs: i64,
+    pub incidents: Vec<Incident>,
+}
+
+pub async fn node_sla(pubkey: &str, now: i64) -> Result<SlaReport, sqlx::Error> {
+    let since = now - WINDOWS[WINDOWS.len() - 1].1;
+    let cycles = db::store().get_cycles_since(since).await?;
+    let samples: Vec<(i64, bool)> = db::store().get_node_history_since(pubkey, since)
+        .await?
+        .into_iter()
+        .map(|r| (r.timestamp, r.status.as_deref() == Some("online")))
+        .collect();
+
+    let max_gap = max_cycle_gap(crate::networks::current().refresh_interval_secs);
+    Ok(compute(pubkey, &cycles, &samples, max_gap, now))
+}
+
+/// Builds the node's up/down timeline. Every cycle in which the observer saw the
+/// network covers the time until the next cycle (capped at `max_gap`);
+/// the node's state for that cycle is the status it was recorded with, or down
+/// if the node was missing from gossip. Blind cycles (every seed failed) cover
+/// nothing, so that time stays unknown. Cycles before the node's first sample
+/// are ignored so a newly joined node isn't penalised for not existing yet.
+fn timeline(cycles: &[(i64, bool)], samples: &[(i64, bool)], max_gap: i64, now: i64) -> Vec<Span> {
+    let tracked_from = match samples.first() {
+        Some((ts, _)) => *ts,
+        None => return Vec::new(),
+    };
+
+    let mut spans: Vec<Span> = Vec::new();
+    let mut cursor = 0;
+
+    for (i, &(start, observed)) in cycles.iter().enumerate() {
+        let next_cycle = cycles.get(i + 1).map(|c| c.0);
+
+        let mut recorded = None;
+        while cursor < samples.len() && next_cycle.is_none_or(|next| samples[cursor].0 < next) {
+            if samples[cursor].0 >= start {
+                recorded = Some(samples[cursor].1);
+            }
+            cursor += 1;
+        }
+
+        if !observed || (recorded.is_none() && start < tracked_from) {
+            continue;
+        }
+
+        let state = if recorded == Some(true) { State::Up } else { State::Down };
+        let end = next_cycle.unwrap_or(now).min(start + max_gap).min(now);
+        if end <= start {
+            continue;
+        }
+
+        match spans.last_mut() {
+            Some(last) if last.state == state && last.end == start => last.end = end,
+            _ => spans.push(Span { start, end, state }),
+        }
+    }
+
+    spans
+}
+
+fn window_availability(spans: &[Span], window: &'static str, len: i64, now: i64) -> WindowAvailability {
+    let from = now - len;
+    let (mut up_secs, mut down_secs) = (0, 0);
+
+    for span in spans {
+        let overlap = span.end.min(now) - span.start.max(from);
+        if overlap <= 0 {
+            continue;
+        }
+        match span.state {
+            State::Up => up_secs += overlap,
+            State::Down => down_secs += overlap,
+        }
+    }
+
+    let observed = up_secs + down_secs;
+    WindowAvailability {
+        window,
+        up_secs,
+        down_secs,
+        unknown_secs: len - observed,
+        availability_percent: (observed > 0).then(|| up_secs as f64 / observed as f64 * 100.0),
+        coverage_percent: observed as f64 / len as f64 * 100.0,
+    }
+}
+
+fn compute(pubkey: &str, cycles: &[(i64, bool)], samples: &[(i64, bool)], max_gap: i64, now: i64) -> SlaReport {
+    let spans = timeline(cycles, samples, max_gap, now);
+
+    let windows: Vec<WindowAvailability> = WINDOWS
+        .iter()
+        .map(|(name, len)| window_availability(&spans, name, *len, now))
+        .collect();
+
+    let from = now - WINDOWS[WINDOWS.len() - 1].1;
+    let incidents: Vec<Incident> = spans
+        .iter()
+        .filter(|s| s.state == State::Down && s.end > from)
+        .map(|s| {
+            let start = s.start.max(from);
+            Incident {
+                start,
+                end: s.end,
+                duration_secs: s.end - start,
+                ongoing: s.end == now,
+            }
+        })
+        .collect();
+
+    let resolved: Vec<i64> = incidents.iter().filter(|i| !i.ongoing).map(|i| i.duration_secs).collect();
+    let mttr_secs = (!resolved.is_empty()).then(|| resolved.iter().sum::<i64>() as f64 / resolved.len() as f64);
+
+    let up_30d = windows.last().map(|w| w.up_secs).unwrap_or(0);
+    let mtbf_secs = (!incidents.is_empty()).then(|| up_30d as f64 / incidents.len() as f64);
+
+    SlaReport {
+        pubkey: pubkey.to_string(),
+        generated_at: now,
+        longest_outage_secs: incidents.iter().map(|i| i.duration_secs).max().unwrap_or(0),
+        windows,
+        mtbf_secs,
+        mttr_secs,
+        incidents,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: i64 = 1_700_000_000;
+    const GAP: i64 = 45;
+
+    fn cycles(offsets: &[i64]) -> Vec<(i64, bool)> {
+        offsets.iter().map(|o| (T0 + o, true)).collect()
+    }
+
+    fn samples(points: &[(i64, bool)]) -> Vec<(i64, bool)> {
+        points.iter().map(|&(o, online)| (T0 + o, online)).collect()
+    }
+
+    fn day(report: &SlaReport) -> &WindowAvailability {
+        &report.windows[0]
+    }
+
+    #[test]
+    fn gap_cap_is_one_and_a_half_refresh_intervals() {
+        assert_eq!(max_cycle_gap(30), 45);
+        assert_eq!(max_cycle_gap(60), 90);
+    }
+
+    #[test]
+    fn node_up_every_cycle_is_fully_available() {
+        let report = compute("pk", &cycles(&[0, 30, 60]), &samples(&[(0, true), (30, true), (60, true)]), GAP, T0 + 90);
+
+        let day = day(&report);
+        assert_eq!(day.up_secs, 90);
+        assert_eq!(day.down_secs, 0);
+        assert_eq!(day.unknown_secs, 24 * 3600 - 90);
+        assert_eq!(day.availability_percent, Some(100.0));
+        assert!(report.incidents.is_empty());
+        assert_eq!(report.mtbf_secs, None);
+    }
+
+    #[test]
+    fn missing_from_a_cycle_is_an_outage() {
+        let report = compute("pk", &cycles(&[0, 30, 60, 90]), &samples(&[(0, true), (60, true), (90, true)]), GAP, T0 + 120);
+
+        let day = day(&report);
+        assert_eq!((day.up_secs, day.down_secs), (90, 30));
+        assert_eq!(day.availability_percent, Some(75.0));
+        assert_eq!(report.incidents.len(), 1);
+        let incident = &report.incidents[0];
+        assert_eq!((incident.start, incident.end, incident.ongoing), (T0 + 30, T0 + 60, false));
+        assert_eq!(report.mttr_secs, Some(30.0));
+        assert_eq!(report.mtbf_secs, Some(90.0));
+        assert_eq!(report.longest_outage_secs, 30);
+    }
+
+    #[test]
+    fn outage_still_running_is_ongoing() {
+        let report = compute("pk", &cycles(&[0, 30]), &samples(&[(0, true), (30, false)]), GAP, T0 + 60);
+
+        let incident = &report.incidents[0];
+        assert_eq!((incident.start, incident.end), (T0 + 30, T0 + 60));
+        assert!(incident.ongoing);
+        assert_eq!(report.mttr_secs, None);
+    }
+
+    #[test]
+    fn time_past_the_gap_cap_is_unknown() {
+        let all_up = samples(&[(0, true), (30, true), (300, true), (330, true)]);
+        let report = compute("pk", &cycles(&[0, 30, 300, 330]), &all_up, GAP, T0 + 360);
+
+        // 30s to the second cycle, 45s after it until the cap, then 60s
+        // once cycles resume; the 225s the observer was gone are unknown.
+        let day = day(&report);
+        assert_eq!((day.up_secs, day.down_secs), (30 + GAP + 60, 0));
+        assert!(report.incidents.is_empty());
+    }
+
+    #[test]
+    fn blind_cycles_are_unknown_not_down() {
+        let cycles = vec![(T0, true), (T0 + 30, false), (T0 + 60, true)];
+        let report = compute("pk", &cycles, &samples(&[(0, true), (60, true)]), GAP, T0 + 90);
+
+        let day = day(&report);
+        assert_eq!((day.up_secs, day.down_secs), (60, 0));
+        assert!(report.incidents.is_empty());
+    }
+
+    #[test]
+    fn cycles_before_the_node_joined_are_ignored() {
+        let report = compute("pk", &cycles(&[0, 30, 60]), &samples(&[(60, true)]), GAP, T0 + 90);
+
+        let day = day(&report);
+        assert_eq!((day.up_secs, day.down_secs), (30, 0));
+        assert!(report.incidents.is_empty());
+    }
+
+    #[test]
+    fn node_without_samples_has_no_availability() {
+        let report = compute("pk", &cycles(&[0, 30]), &[], GAP, T0 + 60);
+
+        let day = day(&report);
+        assert_eq!(day.availability_percent, None);
+        assert_eq!(day.coverage_percent, 0.0);
+    }
+}
@@ -0,0 +1,31 @@
+//! Shared state for tests that reach the database or the outbound client:
+//! the default configuration, with the default network's store in a shared
+//! in-memory SQLite database, installed once per test binary. Everything
+//! runs on one runtime that outlives the tests so the pool's connections,
+//! and with them the database, stay alive.
+
+use std::future::Future;
+
+use once_cell::sync::Lazy;
+use tokio::runtime::Runtime;
+
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build the test runtime")
+});
+
+static READY: Lazy<()> = Lazy::new(|| {
+    let mut config = crate::config::Config::default();
+    config.server.database_url = "sqlite:file:observer-test?mode=memory&cache=shared".to_string();
+    crate::config::install(crate::config::Cli::default(), config);
+    crate::networks::init();
+    RUNTIME.block_on(crate::db::init_db()).expect("Failed to open the test database");
+});
+
+/// Runs `f` against the shared test database.
+pub fn run<F: Future>(f: F) -> F::Output {
+    Lazy::force(&READY);
+    RUNTIME.handle().block_on(f)
+}
@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+
+use crate::alerts::AlertNotice;
+use crate::db::{self, Webhook};
+use crate::notify::Notification;
+
+pub const FORMATS: [&str; 3] = ["json", "slack", "discord"];
+
+pub const MAX_ATTEMPTS: u32 = 5;
+// Retries wait 1s, 2s, 4s, 8s: about 15s before a delivery is given up.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Discord rejects messages longer than this.
+const DISCORD_MAX_CHARS: usize = 2000;
+
+const DEFAULT_TEXT_TEMPLATE: &str = "[{{severity}}] {{rule_name}} {{state}}: {{message}}";
+
+/// Body accepted when creating or replacing a webhook. Leaving `secret` out of
+/// an update keeps the stored one; an empty string removes it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookInput {
+    pub name: String,
+    pub url: String,
+    pub format: Option<String>,
+    pub template: Option<String>,
+    pub secret: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+/// Outcome of the last attempt at delivering one notice.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryResult {
+    pub delivered: bool,
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+fn sample_notification() -> Notification {
+    let notice = AlertNotice {
+        alert_id: 0,
+        rule_id: 0,
+        rule_name: "Test alert".to_string(),
+        kind: "node_offline".to_string(),
+        severity: "info".to_string(),
+        subject: "test".to_string(),
+        state: "firing".to_string(),
+        value: Some(1.0),
+        message: "Test delivery from the Xandeum observer".to_string(),
+        at: crate::now_secs(),
+        group_key: "test".to_string(),
+        owner_account_id: None,
+    };
+    Notification::new("test", "firing", vec![notice])
+}
+
+/// Checks a webhook body and turns it into a webhook ready to store.
+pub fn validate(input: WebhookInput, id: i64, created_at: i64, existing_secret: Option<String>) -> Result<Webhook, String> {
+    if input.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    match reqwest::Url::parse(&input.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        Ok(_) => return Err("url must be http or https".to_string()),
+        Err(e) => return Err(format!("invalid url: {}", e)),
+    }
+    let format = input.format.unwrap_or_else(|| "json".to_string());
+    if !FORMATS.contains(&format.as_str()) {
+        return Err(format!("unknown format '{}', expected one of {}", format, FORMATS.join(", ")));
+    }
+
+    let hook = Webhook {
+        id,
+        name: input.name,
+        url: input.url,
+        format,
+        template: input.template.filter(|t| !t.trim().is_empty()),
+        secret: match input.secret {
+            Some(s) if s.is_empty() => None,
+            Some(s) => Some(s),
+            None => existing_secret,
+        },
+        enabled: input.enabled.unwrap_or(true),
+        created_at,
+    };
+
+    // A JSON template that doesn't produce JSON would fail on every delivery.
+    if let Err(e) = payload(&hook, &sample_notification()) {
+        return Err(format!("template does not render to valid JSON: {}", e));
+    }
+    Ok(hook)
+}
+
+/// Replaces `{{field}}` placeholders with the notification's fields. In JSON
+/// templates values are escaped so they can sit inside a quoted string; a
+/// missing value renders as `null`.
+fn render(template: &str, notice: &Notification, escape_json: bool) -> String {
+    let text = |s: &str| {
+        if escape_json {
+            let quoted = serde_json::to_string(s).unwrap();
+            quoted[1..quoted.len() - 1].to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    let value = notice.value.map(|v| v.to_string()).unwrap_or_else(|| "null".to_string());
+    let alert_id = notice.alerts.first().map(|a| a.alert_id).unwrap_or_default();
+
+    template
+        .replace("{{network}}", &text(&notice.network))
+        .replace("{{group_key}}", &text(&notice.group_key))
+        .replace("{{count}}", &notice.count.to_string())
+        .replace("{{alert_id}}", &alert_id.to_string())
+        .replace("{{rule_id}}", &notice.rule_id.to_string())
+        .replace("{{rule_name}}", &text(&notice.rule_name))
+        .replace("{{kind}}", &text(&notice.kind))
+        .replace("{{severity}}", &text(&notice.severity))
+        .replace("{{subject}}", &text(&notice.subject))
+        .replace("{{state}}", &text(&notice.state))
+        .replace("{{value}}", &value)
+        .replace("{{message}}", &text(&notice.message))
+        .replace("{{at}}", &notice.at.to_string())
+}
+
+/// The request body for a notification in the webhook's format.
+fn payload(hook: &Webhook, notice: &Notification) -> Result<String, serde_json::Error> {
+    let template = hook.template.as_deref();
+    match hook.format.as_str() {
+        "slack" => {
+            let text = render(template.unwrap_or(DEFAULT_TEXT_TEMPLATE), notice, false);
+            serde_json::to_string(&serde_json::json!({ "text": text }))
+        }
+        "discord" => {
+            let text: String = render(template.unwrap_or(DEFAULT_TEXT_TEMPLATE), notice, false)
+                .chars()
+                .take(DISCORD_MAX_CHARS)
+                .collect();
+            serde_json::to_string(&serde_json::json!({ "content": text }))
+        }
+        _ => match template {
+            Some(t) => {
+                let body = render(t, notice, true);
+                serde_json::from_str::<serde_json::Value>(&body)?;
+                Ok(body)
+            }
+            None => serde_json::to_string(notice),
+        },
+    }
+}
+
+/// `sha256=<hex>` HMAC of `{timestamp}.{body}`, so a receiver can reject both
+/// forged and replayed requests.
+fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Posts a notification to the webhook, retrying with exponential backoff on
+/// network errors, 429 and 5xx. Every attempt is written to the delivery log
+/// against the group's first alert.
+pub async fn deliver(hook: &Webhook, notice: &Notification, max_attempts: u32) -> DeliveryResult {
+    let alert_id = notice.alerts.first().map(|a| a.alert_id).filter(|id| *id > 0);
+    let mut result = DeliveryResult { delivered: false, attempts: 0, status_code: None, error: None };
+
+    let body = match payload(hook, notice) {
+        Ok(body) => body,
+        Err(e) => {
+            result.error = Some(format!("failed to render payload: {}", e));
+            return result;
+        }
+    };
+
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 1..=max_attempts {
+        let timestamp = crate::now_secs();
+        let mut request = crate::outbound::CLIENT
+            .post(&hook.url)
+            .timeout(REQUEST_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header("X-Observer-Event", "alert")
+            .header("X-Observer-Timestamp", timestamp.to_string());
+        if let Some(secret) = &hook.secret {
+            request = request.header("X-Observer-Signature", signature(secret, timestamp, &body));
+        }
+
+        let retryable = match request.body(body.clone()).send().await {
+            Ok(resp) => {
+                let status = resp.status();
+                result.status_code = Some(status.as_u16());
+                result.delivered = status.is_success();
+                result.error = (!status.is_success()).then(|| format!("HTTP {}", status.as_u16()));
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                result.status_code = None;
+                result.error = Some(e.to_string());
+                true
+            }
+        };
+        result.attempts = attempt;
+
+        if let Err(e) = db::store().save_webhook_delivery(
+            hook.id,
+            alert_id,
+            &notice.state,
+            attempt as i64,
+            result.status_code.map(i64::from),
+            result.error.as_deref(),
+            result.delivered,
+            timestamp,
+        ).await {
+            eprintln!("Failed to save webhook delivery: {}", e);
+        }
+
+        if result.delivered || !retryable || attempt == max_attempts {
+            break;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+
+    if !result.delivered {
+        eprintln!(
+            "Webhook {} gave up on {} after {} attempt(s): {}",
+            hook.name,
+            notice.group_key,
+            result.attempts,
+            result.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    result
+}
+
+/// Sends the notifications to every enabled webhook in the background. Each
+/// webhook gets its own task so a slow receiver never holds up the others, and
+/// notifications reach a given receiver in the order they happened. Private
+/// rules' notifications never leave through webhooks. Returns the delivery
+/// tasks for callers that must not exit before they finish.
+pub async fn dispatch(notices: &[Notification]) -> Vec<JoinHandle<()>> {
+    let notices: Vec<Notification> = notices.iter().filter(|n| n.owner_account_id.is_none()).cloned().collect();
+    if notices.is_empty() {
+        return Vec::new();
+    }
+    let hooks = match db::store().get_webhooks().await {
+        Ok(hooks) => hooks,
+        Err(e) => {
+            eprintln!("Failed to load webhooks: {}", e);
+            return Vec::new();
+        }
+    };
+
+    hooks
+        .into_iter()
+        .filter(|h| h.enabled)
+        .map(|hook| {
+            let notices = notices.clone();
+            crate::networks::spawn(async move {
+                for notice in &notices {
+                    deliver(&hook, notice, MAX_ATTEMPTS).await;
+                }
+            })
+        })
+        .collect()
+}
+
+pub async fn send_test(hook: &Webhook) -> DeliveryResult {
+    deliver(hook, &sample_notification(), 1).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+
+    use crate::testing;
+
+    fn input(format: &str, template: Option<&str>) -> WebhookInput {
+        WebhookInput {
+            name: "ops".to_string(),
+            url: "https://hooks.example.com/alert".to_string(),
+            format: Some(format.to_string()),
+            template: template.map(str::to_string),
+            secret: None,
+            enabled: None,
+        }
+    }
+
+    fn hook(format: &str, template: Option<&str>) -> Webhook {
+        testing::run(async { validate(input(format, template), 0, 0, None) }).unwrap()
+    }
+
+    fn notice(message: &str, value: Option<f64>) -> Notification {
+        let mut notice = testing::run(async { sample_notification() });
+        notice.message = message.to_string();
+        notice.value = value;
+        notice
+    }
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    /// A local receiver answering with `statuses` in turn, then 200.
+    async fn receiver(statuses: Vec<u16>) -> (String, Received) {
+        let received: Received = Arc::default();
+        let statuses = Arc::new(Mutex::new(statuses.into_iter()));
+        let log = received.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: String| async move {
+                log.lock().unwrap().push((headers, body));
+                let status = statuses.lock().unwrap().next().unwrap_or(200);
+                StatusCode::from_u16(status).unwrap()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    async fn stored_hook(url: &str, secret: Option<&str>) -> Webhook {
+        let mut hook = validate(WebhookInput { url: url.to_string(), secret: secret.map(str::to_string), ..input("json", None) }, 0, 0, None).unwrap();
+        hook.id = db::store().save_webhook(&hook).await.unwrap();
+        hook
+    }
+
+    #[test]
+    fn signature_is_hmac_of_timestamp_and_body() {
+        assert_eq!(
+            signature("topsecret", 1_700_000_000, r#"{"a":1}"#),
+            "sha256=6a939b0c71853d606167625a15168ee9188c6a511c773ef4f42d307f3849e50f"
+        );
+        assert_ne!(signature("topsecret", 1_700_000_001, r#"{"a":1}"#), signature("topsecret", 1_700_000_000, r#"{"a":1}"#));
+    }
+
+    #[test]
+    fn json_template_escapes_values() {
+        let hook = hook("json", Some(r#"{"text": "{{rule_name}}: {{message}}", "value": {{value}}}"#));
+        let body = payload(&hook, &notice("disk \"full\"\nnow", None)).unwrap();
+
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["text"], "Test alert: disk \"full\"\nnow");
+        assert_eq!(parsed["value"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn template_that_is_not_json_is_rejected() {
+        let err = testing::run(async { validate(input("json", Some("{{message}}")), 0, 0, None) }).unwrap_err();
+        assert!(err.starts_with("template does not render to valid JSON"), "{}", err);
+    }
+
+    #[test]
+    fn slack_and_discord_wrap_the_text_template() {
+        let notice = notice("node down", Some(1.0));
+
+        let slack: serde_json::Value = serde_json::from_str(&payload(&hook("slack", None), &notice).unwrap()).unwrap();
+        assert_eq!(slack["text"], "[info] Test alert firing: node down");
+
+        let long = self::notice(&"x".repeat(3000), None);
+        let discord: serde_json::Value = serde_json::from_str(&payload(&hook("discord", None), &long).unwrap()).unwrap();
+        assert_eq!(discord["content"].as_str().unwrap().chars().count(), DISCORD_MAX_CHARS);
+    }
+
+    #[test]
+    fn delivery_is_retried_after_a_server_error_and_signed() {
+        let (result, received, deliveries) = testing::run(async {
+            let (url, received) = receiver(vec![503]).await;
+            let hook = stored_hook(&url, Some("topsecret")).await;
+            let result = deliver(&hook, &sample_notification(), MAX_ATTEMPTS).await;
+            let deliveries = db::store().get_webhook_deliveries(hook.id, 10).await.unwrap();
+            (result, received, deliveries)
+        });
+
+        assert!(result.delivered);
+        assert_eq!((result.attempts, result.status_code), (2, Some(200)));
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries.iter().filter(|d| d.delivered).count(), 1);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        for (headers, body) in received.iter() {
+            let timestamp: i64 = headers["x-observer-timestamp"].to_str().unwrap().parse().unwrap();
+            assert_eq!(headers["x-observer-signature"], signature("topsecret", timestamp, body).as_str());
+        }
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let (result, received) = testing::run(async {
+            let (url, received) = receiver(vec![400]).await;
+            let hook = stored_hook(&url, None).await;
+            (deliver(&hook, &sample_notification(), MAX_ATTEMPTS).await, received)
+        });
+
+        assert!(!result.delivered);
+        assert_eq!((result.attempts, result.status_code), (1, Some(400)));
+        assert_eq!(result.error.as_deref(), Some("HTTP 400"));
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].0.get("x-observer-signature").is_none());
+    }
+}
@@ -36,6 +36,14 @@ interface CreditsData {
     credits: number;
 }
 
+interface CreditsResponse {
+    fetched_at: number | null;
+    stale_secs: number | null;
+    last_attempt_at: number | null;
+    last_error: string | null;
+    credits: CreditsData[];
+}
+
 /**
  * Service for interacting with Xandeum pRPC network via backend API
  */
@@ -44,7 +52,7 @@ class PRPCService {
     private lastCreditsFetch: number = 0;
 
     /**
-     * Fetch credits from backend proxy
+     * Fetch cached credits from the backend
      */
     private async fetchCredits(): Promise<void> {
         const now = Date.now();
@@ -53,13 +61,13 @@ class PRPCService {
         }
 
         try {
-            // Use local proxy to avoid CORS
+            // Credits are ingested and cached by the backend
             const response = await fetch(`${API_BASE_URL}/credits`);
             if (response.ok) {
-                const data: CreditsData[] = await response.json();
+                const data: CreditsResponse = await response.json();
                 this.creditsCache.clear();
-                if (Array.isArray(data)) {
-                    data.forEach(item => {
+                if (Array.isArray(data.credits)) {
+                    data.credits.forEach(item => {
                         this.creditsCache.set(item.pubkey, item.credits);
                     });
                 }
@@ -33,6 +33,32 @@ export interface SLAViolation {
   impact: string;
 }
 
+export interface SLAWindow {
+  window: '24h' | '7d' | '30d';
+  up_secs: number;
+  down_secs: number;
+  unknown_secs: number;
+  availability_percent: number | null;
+  coverage_percent: number;
+}
+
+export interface SLAIncident {
+  start: number;
+  end: number;
+  duration_secs: number;
+  ongoing: boolean;
+}
+
+export interface NodeSLAReport {
+  pubkey: string;
+  generated_at: number;
+  windows: SLAWindow[];
+  mtbf_secs: number | null;
+  mttr_secs: number | null;
+  longest_outage_secs: number;
+  incidents: SLAIncident[];
+}
+
 class SLAVerificationService {
   private readonly SLA_TARGETS = {
     UPTIME_THRESHOLD: 99.9, // 99.9% uptime
@@ -131,13 +157,12 @@ class SLAVerificationService {
       }
     }
 
-    // Calculate real uptime percentage from historical data
+    // Uptime comes from the backend SLA report, which accounts for observer gaps
     let uptimePercentage = node.metrics.uptime;
-    if (realHistoricalData.length > 0) {
-      const onlineRecords = realHistoricalData.filter((record) => 
-        record.status === 'online' || (record.latency_ms !== undefined && record.latency_ms !== null)
-      );
-      uptimePercentage = (onlineRecords.length / realHistoricalData.length) * 100;
+    const slaReport = await this.fetchSLAReport(node.id);
+    const dayWindow = slaReport?.windows.find(w => w.window === '24h');
+    if (dayWindow && dayWindow.availability_percent !== null) {
+      uptimePercentage = dayWindow.availability_percent;
     }
 
     // Real uptime violation check
@@ -235,6 +260,23 @@ class SLAVerificationService {
     };
   }
 
+  /**
+   * Fetch the availability report computed by the backend from node history
+   */
+  async fetchSLAReport(nodeId: string): Promise<NodeSLAReport | null> {
+    try {
+      const response = await fetch(`${API_BASE_URL}/node/${nodeId}/sla`);
+      if (!response.ok) {
+        return null;
+      }
+      const data = await response.json();
+      return data.error ? null : data;
+    } catch (error) {
+      console.warn('Could not fetch SLA report:', error);
+      return null;
+    }
+  }
+
   /**
    * Generate proof hash from real data
    */